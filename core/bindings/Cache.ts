@@ -0,0 +1,50 @@
+/**
+ * How the in-memory cache of pages and open archives is doing since the server started.
+ * Sizes are in bytes.
+ */
+export interface CacheStats {
+	/**
+	 * The number of pages served from the cache.
+	 */
+	pageHits: number;
+	/**
+	 * The number of pages which had to be read from disk.
+	 */
+	pageMisses: number;
+	/**
+	 * The number of pages evicted, either to make room or because their file changed.
+	 */
+	pageEvictions: number;
+	/**
+	 * The number of pages currently cached.
+	 */
+	pageCount: number;
+	/**
+	 * The combined size of the pages currently cached.
+	 */
+	size: number;
+	/**
+	 * The most the pages in the cache may add up to.
+	 */
+	maxSize: number;
+	/**
+	 * The number of times an open archive was reused.
+	 */
+	archiveHits: number;
+	/**
+	 * The number of times an archive had to be opened.
+	 */
+	archiveMisses: number;
+	/**
+	 * The number of open archives closed, either to make room or because their file changed.
+	 */
+	archiveEvictions: number;
+	/**
+	 * The number of archives currently open.
+	 */
+	archiveCount: number;
+	/**
+	 * The most archives which may be open at once.
+	 */
+	maxArchives: number;
+}
@@ -14,7 +14,10 @@ export type JobEventKind =
 	| 'JobComplete'
 	| 'JobFailed'
 	| 'CreatedMedia'
-	| 'CreatedSeries';
+	| 'CreatedSeries'
+	| 'UpdatedMedia'
+	| 'UpdatedSeries'
+	| 'UpdatedLibrary';
 
 export type JobEvent = {
 	[kind in JobEventKind]: any;
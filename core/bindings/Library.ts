@@ -1,6 +1,8 @@
 import { Series } from './Series';
 import { Tag } from './Tag';
 
+export type LibraryLayoutMode = 'TOP_LEVEL_FOLDERS' | 'LEAF_FOLDERS';
+
 export interface Library {
 	/**
 	 * The id of the library.
@@ -18,6 +20,26 @@ export interface Library {
 	 * The (optional) description of the library.
 	 */
 	description?: string;
+	/**
+	 * How series are detected within the library.
+	 */
+	layoutMode: LibraryLayoutMode;
+	/**
+	 * Whether files directly in the library root are each treated as a one-shot series.
+	 */
+	rootFilesAsOneShots: boolean;
+	/**
+	 * Whether symbolic links are followed while scanning the library.
+	 */
+	followSymlinks: boolean;
+	/**
+	 * Gitignore style patterns for files and folders the scanner should skip. ex: ["@eaDir", "_extras/"]
+	 */
+	ignorePatterns: string[];
+	/**
+	 * Whether leaf folders holding only images are each treated as a single book.
+	 */
+	folderBooks: boolean;
 	/**
 	 * The date in which the library was last updated. This is usually after a scan. ex: "2022-04-20 04:20:69"
 	 */
@@ -1,3 +1,4 @@
+import { MediaMetadata } from './MediaMetadata';
 import { Tag } from './Tag';
 
 export enum MediaStatus {
@@ -40,14 +41,38 @@ export interface Media {
 	 * The date/time the media was last modified. Extracted from metadata.
 	 */
 	updatedAt?: Date;
+	/**
+	 * The last modified time of the media file on disk, as of the last scan.
+	 */
+	modifiedAt?: Date;
 	/**
 	 * The checksum of the media file.
 	 */
 	checksum: string;
+	/**
+	 * The checksum of the entire media file, once the full checksum job has computed it.
+	 */
+	fullChecksum?: string;
+	/**
+	 * The perceptual hash of the cover of the media, once the cover hash job has computed it.
+	 */
+	coverHash?: string;
 	/**
 	 * The path of the media file on disk.
 	 */
 	path: string;
+	/**
+	 * The status of the media. ex: "READY", "MISSING" or "ERROR"
+	 */
+	status: string;
+	/**
+	 * Why the media has its status, e.g. what is corrupt in media with an "ERROR" status.
+	 */
+	statusReason?: string;
+	/**
+	 * When the verification job last read the media in full.
+	 */
+	verifiedAt?: Date;
 	/**
 	 * The page the viewing user is currently on for the associated media.
 	 */
@@ -57,4 +82,57 @@ export interface Media {
 	 * @see Tag
 	 */
 	tags?: Tag[];
+	/**
+	 * The metadata of the media, derived from its ComicInfo.xml. This will be undefined if the media has no metadata, or if the relation is not loaded.
+	 */
+	metadata?: MediaMetadata;
+}
+
+/**
+ * Media which share a checksum, but not their contents. Since the checksum only covers the
+ * start of each file, these would otherwise look like duplicates.
+ */
+export interface ChecksumConflict {
+	/**
+	 * The checksum the media share.
+	 */
+	checksum: string;
+	/**
+	 * The media, ordered by their full checksum.
+	 */
+	media: Media[];
+}
+
+/**
+ * Media whose covers look alike, i.e. are likely the same book in different files. Every media
+ * is within the requested distance of the first media found for the cluster.
+ */
+export interface SimilarMediaCluster {
+	/**
+	 * The most bits the cover hash of any two media in the cluster differ in.
+	 */
+	maxDistance: number;
+	/**
+	 * The media, ordered by their size.
+	 */
+	media: Media[];
+}
+
+/**
+ * The results of the last verification of each media.
+ */
+export interface MediaVerificationReport {
+	/**
+	 * The number of media which were read in full without problems.
+	 */
+	verified: number;
+	/**
+	 * The number of media which haven't been verified since they were added or changed. Missing
+	 * media aren't counted.
+	 */
+	unverified: number;
+	/**
+	 * The media which could not be read, with the reason in their `statusReason`, ordered by their path.
+	 */
+	errored: Media[];
 }
@@ -0,0 +1,214 @@
+export interface MediaMetadataPage {
+	/**
+	 * The index of the image in the book, starting at 0.
+	 */
+	image?: number;
+	/**
+	 * The kind of page. ex: "FrontCover", "Story", "Advertisement" or "Deleted"
+	 */
+	kind?: string;
+	/**
+	 * Whether the image spans two pages.
+	 */
+	doublePage?: boolean;
+	/**
+	 * The size of the image in bytes.
+	 */
+	imageSize?: number;
+	/**
+	 * The key used to look the page up in the tool that tagged the book.
+	 */
+	key?: string;
+	/**
+	 * The name of the bookmark on the page, if there is one.
+	 */
+	bookmark?: string;
+	/**
+	 * The width of the image in pixels.
+	 */
+	imageWidth?: number;
+	/**
+	 * The height of the image in pixels.
+	 */
+	imageHeight?: number;
+}
+
+export interface MediaMetadata {
+	/**
+	 * The title of the book. ex: "The Chameleon Conspiracy, Part One"
+	 */
+	title?: string;
+	/**
+	 * The name of the series the book belongs to. ex: "The Amazing Spider-Man"
+	 */
+	series?: string;
+	/**
+	 * The number of the book in the series. Not always a whole number. ex: "1.5"
+	 */
+	number?: string;
+	/**
+	 * The total number of books in the series. ex: 12
+	 */
+	count?: number;
+	/**
+	 * The volume of the series. ex: 2018
+	 */
+	volume?: number;
+	/**
+	 * The series the book also belongs to, e.g. a crossover event. ex: "Spider-Verse"
+	 */
+	alternateSeries?: string;
+	/**
+	 * The number of the book in the alternate series. ex: "3"
+	 */
+	alternateNumber?: string;
+	/**
+	 * The total number of books in the alternate series. ex: 6
+	 */
+	alternateCount?: number;
+	/**
+	 * A description of the book.
+	 */
+	summary?: string;
+	/**
+	 * Free form notes about the book, often left by the tool that tagged it.
+	 */
+	notes?: string;
+	/**
+	 * The year the book was released. ex: 2018
+	 */
+	year?: number;
+	/**
+	 * The month the book was released. ex: 12
+	 */
+	month?: number;
+	/**
+	 * The day the book was released. ex: 25
+	 */
+	day?: number;
+	/**
+	 * The writers of the book, comma separated. ex: "Nick Spencer"
+	 */
+	writer?: string;
+	/**
+	 * The pencillers of the book, comma separated.
+	 */
+	penciller?: string;
+	/**
+	 * The inkers of the book, comma separated.
+	 */
+	inker?: string;
+	/**
+	 * The colorists of the book, comma separated.
+	 */
+	colorist?: string;
+	/**
+	 * The letterers of the book, comma separated.
+	 */
+	letterer?: string;
+	/**
+	 * The cover artists of the book, comma separated.
+	 */
+	coverArtist?: string;
+	/**
+	 * The editors of the book, comma separated.
+	 */
+	editor?: string;
+	/**
+	 * The translators of the book, comma separated.
+	 */
+	translator?: string;
+	/**
+	 * The publisher of the book. ex: "Marvel"
+	 */
+	publisher?: string;
+	/**
+	 * The imprint of the publisher the book was released under. ex: "Vertigo"
+	 */
+	imprint?: string;
+	/**
+	 * The genres of the book, comma separated. ex: "Superhero, Action"
+	 */
+	genre?: string;
+	/**
+	 * The tags of the book, comma separated.
+	 */
+	tags?: string;
+	/**
+	 * A link to more information about the book, comma separated if there are several.
+	 */
+	web?: string;
+	/**
+	 * The language of the book, as an ISO code. ex: "en"
+	 */
+	languageIso?: string;
+	/**
+	 * The format of the book. ex: "Trade Paperback"
+	 */
+	format?: string;
+	/**
+	 * Whether the book is in black and white. ex: "Yes", "No" or "Unknown"
+	 */
+	blackAndWhite?: string;
+	/**
+	 * Whether the book is a manga, and if so whether it reads right to left.
+	 * ex: "Yes", "No", "Unknown" or "YesAndRightToLeft"
+	 */
+	manga?: string;
+	/**
+	 * The characters in the book, comma separated.
+	 */
+	characters?: string;
+	/**
+	 * The teams in the book, comma separated.
+	 */
+	teams?: string;
+	/**
+	 * The locations in the book, comma separated.
+	 */
+	locations?: string;
+	/**
+	 * The main character or team of the book. ex: "Spider-Man"
+	 */
+	mainCharacterOrTeam?: string;
+	/**
+	 * Who scanned the book, and how.
+	 */
+	scanInformation?: string;
+	/**
+	 * The story arcs the book is part of, comma separated. ex: "The Chameleon Conspiracy"
+	 */
+	storyArc?: string;
+	/**
+	 * The position of the book in each of the story arcs, comma separated. ex: "1"
+	 */
+	storyArcNumber?: string;
+	/**
+	 * The groups the series belongs to, comma separated. ex: "Spider-Man"
+	 */
+	seriesGroup?: string;
+	/**
+	 * The age rating of the book. ex: "Teen"
+	 */
+	ageRating?: string;
+	/**
+	 * The community rating of the book, from 0 to 5. ex: 4.5
+	 */
+	communityRating?: number;
+	/**
+	 * A review of the book.
+	 */
+	review?: string;
+	/**
+	 * The Global Trade Item Number of the book, e.g. its ISBN.
+	 */
+	gtin?: string;
+	/**
+	 * The number of pages in the book. ex: 22
+	 */
+	pageCount?: number;
+	/**
+	 * Information about the individual pages of the book, e.g. which is the cover.
+	 */
+	pages: MediaMetadataPage[];
+}
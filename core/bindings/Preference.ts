@@ -51,4 +51,9 @@ export interface ServerPreferences {
 	 * Flag indicating whether or not to attempt to convert scanned .cbr files to .cbz files.
 	 */
 	convertCbrToCbz: boolean;
+	/**
+	 * Flag indicating whether or not to delete the original .cbr file once it has been converted to a .cbz file.
+	 * If false, the original is moved out of the library to STUMP_CONFIG_DIR/converted/<media id>/.
+	 */
+	deleteConvertedCbr: boolean;
 }
@@ -0,0 +1,127 @@
+export interface RelinkedFile {
+	/**
+	 * The path the media is currently recorded at.
+	 */
+	from: string;
+	/**
+	 * The path the media was found at.
+	 */
+	to: string;
+}
+
+export interface SkippedFile {
+	path: string;
+	/**
+	 * Why the file was skipped. ex: "Matched an ignore pattern"
+	 */
+	reason: string;
+}
+
+export interface FailedFile {
+	path: string;
+	/**
+	 * The error encountered while processing the file.
+	 */
+	error: string;
+}
+
+export interface ScanReport {
+	/**
+	 * The id of the library the report is for.
+	 */
+	libraryId: string;
+	/**
+	 * When the report was generated. The library may have changed since.
+	 */
+	generatedAt: Date;
+	/**
+	 * The paths of the series that would be created.
+	 */
+	seriesToCreate: string[];
+	/**
+	 * The paths of existing series which no longer exist, and would be marked MISSING.
+	 */
+	seriesMissing: string[];
+	/**
+	 * The paths of the media that would be created.
+	 */
+	mediaToCreate: string[];
+	/**
+	 * The paths of existing media which changed on disk, and would be updated.
+	 */
+	mediaToUpdate: string[];
+	/**
+	 * The paths of MISSING media which were found again, and would be marked READY.
+	 */
+	mediaToRestore: string[];
+	/**
+	 * Existing media which were moved or renamed, and would be relinked to their new path.
+	 */
+	mediaToRelink: RelinkedFile[];
+	/**
+	 * The paths of existing media which were not found, and would be marked MISSING.
+	 */
+	mediaMissing: string[];
+	/**
+	 * Files (and folders) the scanner would skip over, and why.
+	 */
+	skipped: SkippedFile[];
+	/**
+	 * Files the scanner would fail to process, and the error it would fail with.
+	 */
+	failed: FailedFile[];
+}
+
+export interface LibraryScan {
+	id: string;
+	/**
+	 * The id of the library that was scanned.
+	 */
+	libraryId: string;
+	/**
+	 * What started the scan. ex: "MANUAL", "CREATE" or "UPDATE"
+	 */
+	trigger: string;
+	/**
+	 * The path that was scanned. This is the library path, unless only part of the library
+	 * was scanned.
+	 */
+	path: string;
+	/**
+	 * The status of the scan. ex: "RUNNING", "COMPLETED" or "FAILED"
+	 */
+	status: string;
+	/**
+	 * The reason the scan failed, if it did.
+	 */
+	failureReason?: string;
+	/**
+	 * The number of files the scan came across.
+	 */
+	filesSeen: number;
+	/**
+	 * The number of media created by the scan.
+	 */
+	mediaCreated: number;
+	/**
+	 * The number of media updated (changed, restored or relinked) by the scan.
+	 */
+	mediaUpdated: number;
+	/**
+	 * The number of media marked MISSING by the scan.
+	 */
+	mediaMissing: number;
+	/**
+	 * The date in which the scan started. ex: "2022-04-20 04:20:69"
+	 */
+	startedAt: string;
+	/**
+	 * The date in which the scan finished, if it has. ex: "2022-04-20 04:20:69"
+	 */
+	completedAt?: string;
+	/**
+	 * The files which could not be scanned. Will be undefined only if the relation is not
+	 * loaded.
+	 */
+	errors?: FailedFile[];
+}
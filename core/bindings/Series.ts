@@ -10,7 +10,7 @@ export interface Series {
 	 */
 	libraryId: string;
 	/**
-	 * The name of the series. Either the directory name or extracted from the ComicInfo.xml file.
+	 * The name of the series. Either the directory name or, if the server is set to rename series, extracted from a series.json or ComicInfo.xml file.
 	 */
 	name: string;
 	/**
@@ -25,6 +25,22 @@ export interface Series {
 	 * The (optional) description of the series.
 	 */
 	description?: string;
+	/**
+	 * The (optional) publisher of the series, from a series.json file.
+	 */
+	publisher?: string;
+	/**
+	 * The (optional) year the series started, from a series.json file.
+	 */
+	year?: number;
+	/**
+	 * The (optional) publication status of the series, from a series.json file. E.g. 'Continuing' or 'Ended'.
+	 */
+	publicationStatus?: string;
+	/**
+	 * The (optional) total number of issues in the series, from a series.json file.
+	 */
+	totalIssues?: number;
 	/**
 	 * The media files in the series. Will be undefined only if the relation is not loaded.
 	 * @see Media
@@ -23,6 +23,7 @@ export interface Pageable<T> {
 
 export type PageableApiResult<T> = ApiResult<Pageable<T>>;
 
+export * from './Cache';
 export * from './Epub';
 export * from './Job';
 export * from './Library';
@@ -30,8 +31,10 @@ export * from './ListDirectory';
 export * from './Locale';
 export * from './Log';
 export * from './Media';
+export * from './MediaMetadata';
 export * from './Preference';
 export * from './ReadProgress';
+export * from './ScanReport';
 export * from './Series';
 export * from './Tag';
 export * from './User';
@@ -0,0 +1,4 @@
+-- AlterTable
+ALTER TABLE "libraries" ADD COLUMN "layoutMode" TEXT NOT NULL DEFAULT 'TOP_LEVEL_FOLDERS';
+ALTER TABLE "libraries" ADD COLUMN "rootFilesAsOneShots" BOOLEAN NOT NULL DEFAULT false;
+ALTER TABLE "libraries" ADD COLUMN "followSymlinks" BOOLEAN NOT NULL DEFAULT false;
@@ -0,0 +1,2 @@
+-- AlterTable
+ALTER TABLE "libraries" ADD COLUMN "ignorePatterns" TEXT;
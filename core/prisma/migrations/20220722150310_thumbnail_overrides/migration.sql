@@ -0,0 +1,5 @@
+-- AlterTable
+ALTER TABLE "series" ADD COLUMN "thumbnailPath" TEXT;
+
+-- AlterTable
+ALTER TABLE "media" ADD COLUMN "thumbnailPath" TEXT;
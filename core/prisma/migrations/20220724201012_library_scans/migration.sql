@@ -0,0 +1,25 @@
+-- CreateTable
+CREATE TABLE "library_scans" (
+    "id" TEXT NOT NULL PRIMARY KEY,
+    "trigger" TEXT NOT NULL,
+    "path" TEXT NOT NULL,
+    "status" TEXT NOT NULL DEFAULT 'RUNNING',
+    "failureReason" TEXT,
+    "filesSeen" INTEGER NOT NULL DEFAULT 0,
+    "mediaCreated" INTEGER NOT NULL DEFAULT 0,
+    "mediaUpdated" INTEGER NOT NULL DEFAULT 0,
+    "mediaMissing" INTEGER NOT NULL DEFAULT 0,
+    "startedAt" DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    "completedAt" DATETIME,
+    "libraryId" TEXT NOT NULL,
+    CONSTRAINT "library_scans_libraryId_fkey" FOREIGN KEY ("libraryId") REFERENCES "libraries" ("id") ON DELETE CASCADE ON UPDATE CASCADE
+);
+
+-- CreateTable
+CREATE TABLE "library_scan_errors" (
+    "id" TEXT NOT NULL PRIMARY KEY,
+    "path" TEXT NOT NULL,
+    "message" TEXT NOT NULL,
+    "scanId" TEXT NOT NULL,
+    CONSTRAINT "library_scan_errors_scanId_fkey" FOREIGN KEY ("scanId") REFERENCES "library_scans" ("id") ON DELETE CASCADE ON UPDATE CASCADE
+);
@@ -0,0 +1,53 @@
+-- CreateTable
+CREATE TABLE "media_metadata" (
+    "id" TEXT NOT NULL PRIMARY KEY,
+    "title" TEXT,
+    "series" TEXT,
+    "number" TEXT,
+    "count" INTEGER,
+    "volume" INTEGER,
+    "alternateSeries" TEXT,
+    "alternateNumber" TEXT,
+    "alternateCount" INTEGER,
+    "summary" TEXT,
+    "notes" TEXT,
+    "year" INTEGER,
+    "month" INTEGER,
+    "day" INTEGER,
+    "writer" TEXT,
+    "penciller" TEXT,
+    "inker" TEXT,
+    "colorist" TEXT,
+    "letterer" TEXT,
+    "coverArtist" TEXT,
+    "editor" TEXT,
+    "translator" TEXT,
+    "publisher" TEXT,
+    "imprint" TEXT,
+    "genre" TEXT,
+    "tags" TEXT,
+    "web" TEXT,
+    "languageIso" TEXT,
+    "format" TEXT,
+    "blackAndWhite" TEXT,
+    "manga" TEXT,
+    "characters" TEXT,
+    "teams" TEXT,
+    "locations" TEXT,
+    "mainCharacterOrTeam" TEXT,
+    "scanInformation" TEXT,
+    "storyArc" TEXT,
+    "storyArcNumber" TEXT,
+    "seriesGroup" TEXT,
+    "ageRating" TEXT,
+    "communityRating" REAL,
+    "review" TEXT,
+    "gtin" TEXT,
+    "pageCount" INTEGER,
+    "pages" TEXT,
+    "mediaId" TEXT NOT NULL,
+    CONSTRAINT "media_metadata_mediaId_fkey" FOREIGN KEY ("mediaId") REFERENCES "media" ("id") ON DELETE CASCADE ON UPDATE CASCADE
+);
+
+-- CreateIndex
+CREATE UNIQUE INDEX "media_metadata_mediaId_key" ON "media_metadata"("mediaId");
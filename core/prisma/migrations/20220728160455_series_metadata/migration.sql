@@ -0,0 +1,11 @@
+-- AlterTable
+ALTER TABLE "series" ADD COLUMN "publisher" TEXT;
+
+-- AlterTable
+ALTER TABLE "series" ADD COLUMN "year" INTEGER;
+
+-- AlterTable
+ALTER TABLE "series" ADD COLUMN "publicationStatus" TEXT;
+
+-- AlterTable
+ALTER TABLE "series" ADD COLUMN "totalIssues" INTEGER;
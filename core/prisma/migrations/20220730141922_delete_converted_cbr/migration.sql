@@ -0,0 +1,2 @@
+-- AlterTable
+ALTER TABLE "server_preferences" ADD COLUMN "deleteConvertedCbr" BOOLEAN NOT NULL DEFAULT false;
@@ -0,0 +1,15 @@
+-- CreateTable
+CREATE TABLE "media_pages" (
+    "id" TEXT NOT NULL PRIMARY KEY,
+    "number" INTEGER NOT NULL,
+    "name" TEXT NOT NULL,
+    "size" INTEGER NOT NULL,
+    "contentType" TEXT,
+    "width" INTEGER,
+    "height" INTEGER,
+    "mediaId" TEXT NOT NULL,
+    CONSTRAINT "media_pages_mediaId_fkey" FOREIGN KEY ("mediaId") REFERENCES "media" ("id") ON DELETE CASCADE ON UPDATE CASCADE
+);
+
+-- CreateIndex
+CREATE UNIQUE INDEX "media_pages_mediaId_number_key" ON "media_pages"("mediaId", "number");
@@ -0,0 +1,3 @@
+-- AlterTable
+ALTER TABLE "media" ADD COLUMN "statusReason" TEXT;
+ALTER TABLE "media" ADD COLUMN "verifiedAt" DATETIME;
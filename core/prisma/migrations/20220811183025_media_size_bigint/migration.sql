@@ -0,0 +1,28 @@
+-- RedefineTables
+PRAGMA foreign_keys=OFF;
+CREATE TABLE "new_media" (
+    "id" TEXT NOT NULL PRIMARY KEY,
+    "name" TEXT NOT NULL,
+    "description" TEXT,
+    "size" BIGINT NOT NULL,
+    "extension" TEXT NOT NULL,
+    "pages" INTEGER NOT NULL,
+    "updatedAt" DATETIME NOT NULL,
+    "modifiedAt" DATETIME,
+    "thumbnailPath" TEXT,
+    "downloaded" BOOLEAN NOT NULL DEFAULT false,
+    "checksum" TEXT,
+    "fullChecksum" TEXT,
+    "coverHash" TEXT,
+    "path" TEXT NOT NULL,
+    "status" TEXT NOT NULL DEFAULT 'READY',
+    "statusReason" TEXT,
+    "verifiedAt" DATETIME,
+    "seriesId" TEXT,
+    CONSTRAINT "media_seriesId_fkey" FOREIGN KEY ("seriesId") REFERENCES "series" ("id") ON DELETE CASCADE ON UPDATE CASCADE
+);
+INSERT INTO "new_media" ("checksum", "coverHash", "description", "downloaded", "extension", "fullChecksum", "id", "modifiedAt", "name", "pages", "path", "seriesId", "size", "status", "statusReason", "thumbnailPath", "updatedAt", "verifiedAt") SELECT "checksum", "coverHash", "description", "downloaded", "extension", "fullChecksum", "id", "modifiedAt", "name", "pages", "path", "seriesId", "size", "status", "statusReason", "thumbnailPath", "updatedAt", "verifiedAt" FROM "media";
+DROP TABLE "media";
+ALTER TABLE "new_media" RENAME TO "media";
+PRAGMA foreign_key_check;
+PRAGMA foreign_keys=ON;
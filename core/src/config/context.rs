@@ -7,6 +7,7 @@ use rocket::tokio::sync::{
 
 use crate::{
 	db,
+	fs::{cache::MediaCache, scanner::report::ScanReports},
 	job::Job,
 	prisma,
 	types::event::{ClientEvent, InternalEvent, InternalTask, TaskResponder},
@@ -22,6 +23,8 @@ pub struct Context {
 	pub event_sender: Arc<EventSender>,
 	pub task_sender: Arc<TaskSender>,
 	pub client_channel: Arc<ClientChannel>,
+	pub cache: Arc<MediaCache>,
+	pub scan_reports: Arc<ScanReports>,
 }
 
 /// Context each request will be provided with.
@@ -32,6 +35,8 @@ impl Context {
 			event_sender: Arc::new(event_sender),
 			task_sender: Arc::new(task_sender),
 			client_channel: Arc::new(channel::<ClientEvent>(1024)),
+			cache: Arc::new(MediaCache::from_env()),
+			scan_reports: Arc::new(ScanReports::default()),
 		}
 	}
 
@@ -41,6 +46,8 @@ impl Context {
 			event_sender: Arc::new(unbounded_channel::<InternalEvent>().0),
 			task_sender: Arc::new(unbounded_channel::<TaskResponder<InternalTask>>().0),
 			client_channel: Arc::new(channel::<ClientEvent>(1024)),
+			cache: Arc::new(MediaCache::from_env()),
+			scan_reports: Arc::new(ScanReports::default()),
 		}
 	}
 
@@ -54,6 +61,8 @@ impl Context {
 			event_sender: self.event_sender.clone(),
 			task_sender: self.task_sender.clone(),
 			client_channel: self.client_channel.clone(),
+			cache: self.cache.clone(),
+			scan_reports: self.scan_reports.clone(),
 		}
 	}
 
@@ -2,7 +2,10 @@ use std::path::Path;
 
 use serde::{Deserialize, Serialize};
 
-use crate::config::get_config_dir;
+use crate::{
+	config::get_config_dir,
+	fs::cache::{DEFAULT_CACHE_ARCHIVES, DEFAULT_CACHE_SIZE},
+};
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Env {
@@ -15,6 +18,10 @@ pub struct Env {
 	pub stump_client_dir: Option<String>,
 	pub stump_config_dir: Option<String>,
 	pub stump_allowed_origins: Option<Vec<String>>,
+	/// The most memory recently served pages may take up, in MiB. 0 disables the page cache.
+	pub stump_cache_size: Option<u64>,
+	/// The most archives kept open between page requests. 0 disables the archive cache.
+	pub stump_cache_archives: Option<u64>,
 }
 
 impl Default for Env {
@@ -27,6 +34,8 @@ impl Default for Env {
 			stump_client_dir: Some(String::from("client")),
 			stump_config_dir: None,
 			stump_allowed_origins: None,
+			stump_cache_size: Some(DEFAULT_CACHE_SIZE),
+			stump_cache_archives: Some(DEFAULT_CACHE_ARCHIVES),
 		}
 	}
 }
@@ -79,6 +88,22 @@ impl Env {
 			}
 		}
 
+		if let Ok(cache_size) = std::env::var("STUMP_CACHE_SIZE") {
+			match cache_size.parse() {
+				Ok(cache_size) => env.stump_cache_size = Some(cache_size),
+				Err(_) => log::debug!("Invalid STUMP_CACHE_SIZE value: {}", cache_size),
+			}
+		}
+
+		if let Ok(cache_archives) = std::env::var("STUMP_CACHE_ARCHIVES") {
+			match cache_archives.parse() {
+				Ok(cache_archives) => env.stump_cache_archives = Some(cache_archives),
+				Err(_) => {
+					log::debug!("Invalid STUMP_CACHE_ARCHIVES value: {}", cache_archives)
+				},
+			}
+		}
+
 		env.stump_config_dir = Some(get_config_dir().to_string_lossy().to_string());
 
 		env.write()?;
@@ -139,6 +164,14 @@ impl Env {
 			}
 		}
 
+		if let Some(cache_size) = env.stump_cache_size {
+			std::env::set_var("STUMP_CACHE_SIZE", cache_size.to_string());
+		}
+
+		if let Some(cache_archives) = env.stump_cache_archives {
+			std::env::set_var("STUMP_CACHE_ARCHIVES", cache_archives.to_string());
+		}
+
 		Ok(())
 	}
 
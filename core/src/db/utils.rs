@@ -7,12 +7,23 @@ use rocket::serde::DeserializeOwned;
 use serde::Deserialize;
 
 use crate::{
-	prisma::PrismaClient,
-	types::{alias::ApiResult, pageable::PageParams},
+	prisma::{media, server_preferences, PrismaClient},
+	types::{
+		alias::ApiResult,
+		models::media::{ChecksumConflict, Media},
+		pageable::PageParams,
+		query::ChecksumKind,
+	},
 };
 
 use super::migration::CountQueryReturn;
 
+/// A checksum shared by more than one media, see `PrismaClientTrait::shared_checksums`.
+#[derive(Deserialize, Debug)]
+pub struct ChecksumQueryReturn {
+	pub checksum: String,
+}
+
 #[derive(Deserialize, Debug)]
 pub struct SeriesMediaCountQueryReturn {
 	pub series_id: String,
@@ -21,20 +32,37 @@ pub struct SeriesMediaCountQueryReturn {
 
 #[async_trait::async_trait]
 pub trait PrismaClientTrait {
-	async fn media_count(&self) -> ApiResult<u32>;
-	async fn series_count(&self, library_id: String) -> ApiResult<u32>;
+	async fn media_count(&self, status: Option<String>) -> ApiResult<u32>;
+	async fn series_count(
+		&self,
+		library_id: String,
+		status: Option<String>,
+	) -> ApiResult<u32>;
 	async fn series_media_count(
 		&self,
 		series_ids: Vec<String>,
 	) -> ApiResult<HashMap<String, u32>>;
+	async fn get_server_preferences(&self) -> ApiResult<server_preferences::Data>;
+	async fn shared_checksums(&self, kind: ChecksumKind) -> ApiResult<Vec<String>>;
+	async fn checksum_conflicts(&self) -> ApiResult<Vec<ChecksumConflict>>;
 }
 
 #[async_trait::async_trait]
 impl PrismaClientTrait for PrismaClient {
-	async fn media_count(&self) -> ApiResult<u32> {
-		let count_res: Vec<CountQueryReturn> = self
-			._query_raw(raw!("SELECT COUNT(*) as count FROM media"))
-			.await?;
+	async fn media_count(&self, status: Option<String>) -> ApiResult<u32> {
+		let count_res: Vec<CountQueryReturn> = match status {
+			Some(status) => {
+				self._query_raw(raw!(
+					"SELECT COUNT(*) as count FROM media WHERE status={}",
+					PrismaValue::String(status)
+				))
+				.await?
+			},
+			None => {
+				self._query_raw(raw!("SELECT COUNT(*) as count FROM media"))
+					.await?
+			},
+		};
 
 		Ok(match count_res.get(0) {
 			Some(val) => val.count,
@@ -42,13 +70,28 @@ impl PrismaClientTrait for PrismaClient {
 		})
 	}
 
-	async fn series_count(&self, library_id: String) -> ApiResult<u32> {
-		let count_res: Vec<CountQueryReturn> = self
-			._query_raw(raw!(
-				"SELECT COUNT(*) as count FROM series WHERE libraryId={}",
-				PrismaValue::String(library_id)
-			))
-			.await?;
+	async fn series_count(
+		&self,
+		library_id: String,
+		status: Option<String>,
+	) -> ApiResult<u32> {
+		let count_res: Vec<CountQueryReturn> = match status {
+			Some(status) => {
+				self._query_raw(raw!(
+					"SELECT COUNT(*) as count FROM series WHERE libraryId={} AND status={}",
+					PrismaValue::String(library_id),
+					PrismaValue::String(status)
+				))
+				.await?
+			},
+			None => {
+				self._query_raw(raw!(
+					"SELECT COUNT(*) as count FROM series WHERE libraryId={}",
+					PrismaValue::String(library_id)
+				))
+				.await?
+			},
+		};
 
 		Ok(match count_res.get(0) {
 			Some(val) => val.count,
@@ -76,6 +119,78 @@ impl PrismaClientTrait for PrismaClient {
 			.map(|data| (data.series_id.to_owned(), data.count))
 			.collect())
 	}
+
+	/// There is only ever one row of server preferences, which is created with the defaults
+	/// the first time it is needed.
+	async fn get_server_preferences(&self) -> ApiResult<server_preferences::Data> {
+		let preferences = self.server_preferences().find_first(vec![]).exec().await?;
+
+		match preferences {
+			Some(preferences) => Ok(preferences),
+			None => Ok(self.server_preferences().create(vec![]).exec().await?),
+		}
+	}
+
+	/// The checksums of the given kind which more than one media share. Only the checksums
+	/// are read raw, the media themselves are read with typed queries.
+	async fn shared_checksums(&self, kind: ChecksumKind) -> ApiResult<Vec<String>> {
+		let checksums: Vec<ChecksumQueryReturn> = match kind {
+			ChecksumKind::Quick => {
+				self._query_raw(raw!("SELECT checksum FROM media WHERE checksum IS NOT NULL GROUP BY checksum HAVING COUNT(*) > 1"))
+					.await?
+			},
+			ChecksumKind::Full => {
+				self._query_raw(raw!("SELECT fullChecksum as checksum FROM media WHERE fullChecksum IS NOT NULL GROUP BY fullChecksum HAVING COUNT(*) > 1"))
+					.await?
+			},
+		};
+
+		Ok(checksums.into_iter().map(|row| row.checksum).collect())
+	}
+
+	/// Media which share a checksum, but whose full checksums show they aren't the same
+	/// file. Only media whose full checksum has been computed are compared.
+	async fn checksum_conflicts(&self) -> ApiResult<Vec<ChecksumConflict>> {
+		let checksums: Vec<ChecksumQueryReturn> = self
+			._query_raw(raw!("SELECT checksum FROM media WHERE fullChecksum IS NOT NULL AND checksum IS NOT NULL GROUP BY checksum HAVING COUNT(DISTINCT fullChecksum) > 1"))
+			.await?;
+
+		let mut media: Vec<Media> = self
+			.media()
+			.find_many(vec![
+				media::checksum::in_vec(
+					checksums.into_iter().map(|row| row.checksum).collect(),
+				),
+				media::full_checksum::not(None),
+			])
+			.exec()
+			.await?
+			.into_iter()
+			.map(|m| m.into())
+			.collect();
+
+		media.sort_by(|a, b| {
+			(&a.checksum, &a.full_checksum).cmp(&(&b.checksum, &b.full_checksum))
+		});
+
+		let mut conflicts: Vec<ChecksumConflict> = vec![];
+
+		for media in media {
+			let checksum = media.checksum.clone().unwrap_or_default();
+
+			match conflicts.last_mut() {
+				Some(conflict) if conflict.checksum == checksum => {
+					conflict.media.push(media)
+				},
+				_ => conflicts.push(ChecksumConflict {
+					checksum,
+					media: vec![media],
+				}),
+			}
+		}
+
+		Ok(conflicts)
+	}
 }
 
 pub trait FindManyTrait {
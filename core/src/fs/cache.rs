@@ -0,0 +1,363 @@
+use lru::LruCache;
+use rocket::http::ContentType;
+use std::{
+	fs::File,
+	io::Read,
+	path::Path,
+	sync::{
+		atomic::{AtomicU64, Ordering},
+		Arc, Mutex, MutexGuard,
+	},
+	time::SystemTime,
+};
+use zip::ZipArchive;
+
+use crate::{
+	fs::{
+		folder,
+		media_file::{self, GetPageResult},
+		page_index::PageImage,
+	},
+	types::{errors::ProcessFileError, models::cache::CacheStats},
+};
+
+/// The default size of the page cache, in MiB. Set with `stump_cache_size` in Stump.toml.
+pub const DEFAULT_CACHE_SIZE: u64 = 128;
+
+/// The default number of archives kept open. Set with `stump_cache_archives` in Stump.toml.
+pub const DEFAULT_CACHE_ARCHIVES: u64 = 16;
+
+/// The size and last modified time of a file when it was cached. An entry whose file no
+/// longer matches is stale, and is evicted instead of served.
+type Fingerprint = (u64, SystemTime);
+
+type PageKey = (String, i32);
+
+struct CachedPage {
+	content_type: ContentType,
+	bytes: Vec<u8>,
+	fingerprint: Fingerprint,
+}
+
+struct CachedArchive {
+	archive: Arc<Mutex<ZipArchive<File>>>,
+	fingerprint: Fingerprint,
+}
+
+#[derive(Default)]
+struct Counters {
+	hits: AtomicU64,
+	misses: AtomicU64,
+	evictions: AtomicU64,
+}
+
+impl Counters {
+	fn hit(&self) {
+		self.hits.fetch_add(1, Ordering::Relaxed);
+	}
+
+	fn miss(&self) {
+		self.misses.fetch_add(1, Ordering::Relaxed);
+	}
+
+	fn evict(&self) {
+		self.evictions.fetch_add(1, Ordering::Relaxed);
+	}
+}
+
+/// An in-memory LRU cache of recently served pages, and of open zip archives. Several people
+/// reading the same new issue will request the same pages within minutes of each other, and
+/// each of those would otherwise go to disk (and for archives, decompress the page again).
+///
+/// The page cache is bounded by the total size of the pages in it, while the archive cache
+/// is bounded by the number of archives. Only zips are kept open, since they are the only
+/// archives which can read an entry without walking the ones before it.
+pub struct MediaCache {
+	max_size: u64,
+	max_archives: u64,
+	pages: Mutex<(LruCache<PageKey, CachedPage>, u64)>,
+	archives: Mutex<LruCache<String, CachedArchive>>,
+	page_counters: Counters,
+	archive_counters: Counters,
+}
+
+impl MediaCache {
+	/// Creates a cache holding at most `max_size` bytes of pages, and `max_archives` open
+	/// archives. Either may be 0 to disable that part of the cache.
+	pub fn new(max_size: u64, max_archives: u64) -> Self {
+		MediaCache {
+			max_size,
+			max_archives,
+			pages: Mutex::new((LruCache::unbounded(), 0)),
+			archives: Mutex::new(LruCache::unbounded()),
+			page_counters: Counters::default(),
+			archive_counters: Counters::default(),
+		}
+	}
+
+	/// Creates a cache configured by STUMP_CACHE_SIZE (in MiB) and STUMP_CACHE_ARCHIVES,
+	/// which are set from Stump.toml when the server starts.
+	pub fn from_env() -> Self {
+		let max_size = std::env::var("STUMP_CACHE_SIZE")
+			.ok()
+			.and_then(|size| size.parse::<u64>().ok())
+			.unwrap_or(DEFAULT_CACHE_SIZE);
+
+		let max_archives = std::env::var("STUMP_CACHE_ARCHIVES")
+			.ok()
+			.and_then(|archives| archives.parse::<u64>().ok())
+			.unwrap_or(DEFAULT_CACHE_ARCHIVES);
+
+		MediaCache::new(max_size.saturating_mul(1024 * 1024), max_archives)
+	}
+
+	/// Gets a page of a book (see `media_file::get_indexed_page`), serving it from the cache
+	/// when it was recently served and the file hasn't changed since.
+	pub fn get_page(
+		&self,
+		file: &str,
+		page: i32,
+		image: Option<&PageImage>,
+	) -> GetPageResult {
+		let fingerprint = match fingerprint(Path::new(file)) {
+			Some(fingerprint) => fingerprint,
+			None => return media_file::get_indexed_page(file, page, image),
+		};
+
+		let key = (file.to_string(), page);
+
+		if let Some(response) = self.cached_page(&key, fingerprint) {
+			return Ok(response);
+		}
+
+		let response = match image {
+			Some(image) if !Path::new(file).is_dir() && media_file::is_zip(file) => {
+				self.read_zip_page(file, page, image, fingerprint)?
+			},
+			_ => media_file::get_indexed_page(file, page, image)?,
+		};
+
+		self.insert_page(key, &response, fingerprint);
+
+		Ok(response)
+	}
+
+	/// Evicts everything cached for the file, or for the files of the folder book it is
+	/// (or is in).
+	pub fn invalidate(&self, path: &Path) {
+		let is_stale = |file: &str| {
+			let file = Path::new(file);
+			file == path || path.starts_with(file) || file.starts_with(path)
+		};
+
+		let mut pages = lock(&self.pages);
+		let stale_pages = pages
+			.0
+			.iter()
+			.filter(|((file, _), _)| is_stale(file))
+			.map(|(key, _)| key.clone())
+			.collect::<Vec<PageKey>>();
+
+		for key in stale_pages {
+			if let Some(page) = pages.0.pop(&key) {
+				pages.1 -= page.bytes.len() as u64;
+				self.page_counters.evict();
+			}
+		}
+
+		drop(pages);
+
+		let mut archives = lock(&self.archives);
+		let stale_archives = archives
+			.iter()
+			.filter(|(file, _)| is_stale(file))
+			.map(|(file, _)| file.clone())
+			.collect::<Vec<String>>();
+
+		for file in stale_archives {
+			archives.pop(&file);
+			self.archive_counters.evict();
+		}
+	}
+
+	pub fn stats(&self) -> CacheStats {
+		let (page_count, size) = {
+			let pages = lock(&self.pages);
+			(pages.0.len() as u64, pages.1)
+		};
+
+		let archive_count = lock(&self.archives).len() as u64;
+
+		CacheStats {
+			page_hits: self.page_counters.hits.load(Ordering::Relaxed),
+			page_misses: self.page_counters.misses.load(Ordering::Relaxed),
+			page_evictions: self.page_counters.evictions.load(Ordering::Relaxed),
+			page_count,
+			size,
+			max_size: self.max_size,
+			archive_hits: self.archive_counters.hits.load(Ordering::Relaxed),
+			archive_misses: self.archive_counters.misses.load(Ordering::Relaxed),
+			archive_evictions: self.archive_counters.evictions.load(Ordering::Relaxed),
+			archive_count,
+			max_archives: self.max_archives,
+		}
+	}
+
+	fn cached_page(
+		&self,
+		key: &PageKey,
+		fingerprint: Fingerprint,
+	) -> Option<(ContentType, Vec<u8>)> {
+		let mut pages = lock(&self.pages);
+
+		let is_stale = match pages.0.get(key) {
+			Some(page) if page.fingerprint == fingerprint => {
+				self.page_counters.hit();
+				return Some((page.content_type.clone(), page.bytes.clone()));
+			},
+			Some(_) => true,
+			None => false,
+		};
+
+		self.page_counters.miss();
+
+		if is_stale {
+			if let Some(page) = pages.0.pop(key) {
+				pages.1 -= page.bytes.len() as u64;
+				self.page_counters.evict();
+			}
+		}
+
+		None
+	}
+
+	fn insert_page(
+		&self,
+		key: PageKey,
+		(content_type, bytes): &(ContentType, Vec<u8>),
+		fingerprint: Fingerprint,
+	) {
+		let size = bytes.len() as u64;
+
+		// A page which would take up more than a quarter of the cache would mostly just push
+		// out the pages around it.
+		if size > self.max_size / 4 {
+			return;
+		}
+
+		let mut pages = lock(&self.pages);
+
+		let replaced = pages.0.put(
+			key,
+			CachedPage {
+				content_type: content_type.clone(),
+				bytes: bytes.clone(),
+				fingerprint,
+			},
+		);
+
+		pages.1 += size;
+
+		if let Some(replaced) = replaced {
+			pages.1 -= replaced.bytes.len() as u64;
+		}
+
+		while pages.1 > self.max_size {
+			match pages.0.pop_lru() {
+				Some((_, page)) => {
+					pages.1 -= page.bytes.len() as u64;
+					self.page_counters.evict();
+				},
+				None => break,
+			}
+		}
+	}
+
+	/// Reads a page from a zip using an archive kept open from a previous request, so the
+	/// central directory of the zip isn't read again.
+	fn read_zip_page(
+		&self,
+		file: &str,
+		page: i32,
+		image: &PageImage,
+		fingerprint: Fingerprint,
+	) -> GetPageResult {
+		let archive = self.zip_archive(file, fingerprint)?;
+
+		let bytes = {
+			let mut archive = lock(archive.as_ref());
+
+			let mut bytes = Vec::new();
+			match archive.by_name(&image.name) {
+				Ok(mut entry) => entry.read_to_end(&mut bytes).map(|_| bytes).ok(),
+				Err(_) => None,
+			}
+		};
+
+		match bytes.map(|bytes| media_file::page_image_response(image, bytes)) {
+			Some(Ok(response)) => Ok(response),
+			// The page table is stale, so the pages are listed as usual.
+			_ => media_file::get_page(file, page),
+		}
+	}
+
+	fn zip_archive(
+		&self,
+		file: &str,
+		fingerprint: Fingerprint,
+	) -> Result<Arc<Mutex<ZipArchive<File>>>, ProcessFileError> {
+		let mut archives = lock(&self.archives);
+
+		match archives.get(file) {
+			Some(cached) if cached.fingerprint == fingerprint => {
+				self.archive_counters.hit();
+				return Ok(cached.archive.clone());
+			},
+			Some(_) => {
+				archives.pop(file);
+				self.archive_counters.evict();
+			},
+			None => {},
+		}
+
+		self.archive_counters.miss();
+
+		let archive = Arc::new(Mutex::new(ZipArchive::new(File::open(file)?)?));
+
+		if self.max_archives > 0 {
+			archives.put(
+				file.to_string(),
+				CachedArchive {
+					archive: archive.clone(),
+					fingerprint,
+				},
+			);
+
+			while archives.len() as u64 > self.max_archives {
+				archives.pop_lru();
+				self.archive_counters.evict();
+			}
+		}
+
+		Ok(archive)
+	}
+}
+
+/// Locks the mutex, even if a thread panicked while holding it. Nothing in the cache is left
+/// half updated by a panic, so the data is still good.
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<T> {
+	mutex
+		.lock()
+		.unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+fn fingerprint(path: &Path) -> Option<Fingerprint> {
+	if path.is_dir() {
+		let (size, modified) = folder::folder_stats(path);
+		return modified.map(|modified| (size, modified));
+	}
+
+	let metadata = std::fs::metadata(path).ok()?;
+
+	Some((metadata.len(), metadata.modified().ok()?))
+}
@@ -1,7 +1,7 @@
 use anyhow::Result;
 use data_encoding::HEXLOWER;
 use ring::digest::{Context, SHA256};
-use std::io::Read;
+use std::{io::Read, path::Path};
 
 use std::fs::File;
 #[cfg(target_family = "unix")]
@@ -11,7 +11,7 @@ use std::os::unix::prelude::FileExt;
 use std::os::windows::prelude::*;
 
 pub fn digest(path: &str, byte_offset: u64) -> Result<String> {
-	let file = File::open(path).unwrap();
+	let file = File::open(path)?;
 
 	let mut ring_context = Context::new(&SHA256);
 
@@ -56,3 +56,29 @@ pub fn digest_from_reader<R: Read>(mut reader: R) -> Result<String> {
 
 	Ok(HEXLOWER.encode(digest.as_ref()))
 }
+
+/// Hashes the entire contents of the files, one after another, as if they were one file.
+/// Unlike `digest`, which only reads enough of a file to tell most files apart quickly.
+pub fn full_digest<P: AsRef<Path>>(paths: &[P]) -> Result<String> {
+	let mut ring_context = Context::new(&SHA256);
+
+	let mut buffer = vec![0; 64 * 1024];
+
+	for path in paths {
+		let mut file = File::open(path)?;
+
+		loop {
+			let count = file.read(&mut buffer)?;
+
+			if count == 0 {
+				break;
+			}
+
+			ring_context.update(&buffer[..count]);
+		}
+	}
+
+	let digest = ring_context.finish();
+
+	Ok(HEXLOWER.encode(digest.as_ref()))
+}
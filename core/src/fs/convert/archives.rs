@@ -0,0 +1,214 @@
+use std::{
+	collections::HashSet,
+	fs::File,
+	io::{self, BufWriter, Read},
+	path::{Path, PathBuf},
+};
+use walkdir::WalkDir;
+use zip::{write::FileOptions, CompressionMethod, ZipArchive, ZipWriter};
+
+use crate::{
+	fs::{
+		media_file,
+		page_index::{PageImage, PageIndex},
+	},
+	types::errors::ProcessFileError,
+};
+
+const COMIC_INFO_FILE: &str = "ComicInfo.xml";
+
+/// Repacks a RAR based comic (.cbr) into a ZIP based comic (.cbz) next to it, keeping every
+/// file in the archive (including ComicInfo.xml) and the reading order of the pages. The new
+/// archive is written under a temporary name and verified before it is moved into place, so
+/// a failed conversion never leaves a partial .cbz behind. The original file is not touched.
+///
+/// Returns the path of the new .cbz file.
+pub fn rar_to_zip(path: &Path) -> Result<PathBuf, ProcessFileError> {
+	let target = path.with_extension("cbz");
+
+	if target.exists() {
+		return Err(ProcessFileError::ConversionError(format!(
+			"{:?} already exists",
+			target
+		)));
+	}
+
+	let extract_dir = std::env::temp_dir().join(format!(
+		"stump-convert-{}",
+		cuid::cuid().map_err(|e| ProcessFileError::Unknown(e.to_string()))?
+	));
+
+	let result = extract_rar(path, &extract_dir)
+		.and_then(|files| write_zip(&extract_dir, files, path, &target));
+
+	if let Err(e) = std::fs::remove_dir_all(&extract_dir) {
+		log::warn!("Failed to remove {:?}: {}", extract_dir, e);
+	}
+
+	result.map(|_| target)
+}
+
+/// Extracts the rar into `dest`, returning the paths of the extracted files relative to
+/// `dest`. The pages come first, in reading order (see `PageIndex`), followed by every other
+/// file.
+fn extract_rar(path: &Path, dest: &Path) -> Result<Vec<PathBuf>, ProcessFileError> {
+	std::fs::create_dir_all(dest)?;
+
+	let path = path.to_string_lossy().to_string();
+
+	let mut archive = unrar::Archive::new(&path)
+		.map_err(|_| ProcessFileError::RarOpenError)?
+		.extract_to(dest.to_string_lossy().to_string())
+		.map_err(|_| ProcessFileError::RarOpenError)?;
+
+	archive
+		.process()
+		.map_err(|_| ProcessFileError::RarReadError)?;
+
+	let mut files = WalkDir::new(dest)
+		.into_iter()
+		.filter_map(|entry| entry.ok())
+		.filter(|entry| entry.file_type().is_file())
+		.filter_map(|entry| entry.path().strip_prefix(dest).ok().map(PathBuf::from))
+		.collect::<Vec<PathBuf>>();
+
+	files.sort();
+
+	if files.is_empty() {
+		return Err(ProcessFileError::ArchiveEmptyError);
+	}
+
+	order_pages(dest, files)
+}
+
+/// Moves the pages among the files to the front, in the order they are read in.
+fn order_pages(
+	dest: &Path,
+	files: Vec<PathBuf>,
+) -> Result<Vec<PathBuf>, ProcessFileError> {
+	let mut images = Vec::new();
+	let mut image_files = Vec::new();
+	let mut others = Vec::new();
+
+	for file in files {
+		let name = entry_name(&file);
+
+		let mut header = Vec::new();
+		File::open(dest.join(&file))?
+			.take(media_file::IMAGE_HEADER_LEN)
+			.read_to_end(&mut header)?;
+
+		if name != COMIC_INFO_FILE && media_file::is_image_content(&name, &header) {
+			let size = std::fs::metadata(dest.join(&file))?.len();
+			images.push(PageImage::new(name, size, &header));
+			image_files.push(file);
+		} else {
+			others.push(file);
+		}
+	}
+
+	let comic_info = std::fs::read_to_string(dest.join(COMIC_INFO_FILE))
+		.ok()
+		.and_then(media_file::process_comic_info);
+
+	let mut ordered = PageIndex::new(images, comic_info.as_ref())
+		.iter()
+		.map(PathBuf::from)
+		.collect::<Vec<PathBuf>>();
+
+	// Pages deleted in the ComicInfo.xml are left out of the index, but are still kept.
+	let listed = ordered.iter().cloned().collect::<HashSet<PathBuf>>();
+	ordered.extend(
+		image_files
+			.into_iter()
+			.filter(|file| !listed.contains(file)),
+	);
+	ordered.extend(others);
+
+	Ok(ordered)
+}
+
+/// The name of a file in the zip. Zip entries always use forward slashes.
+fn entry_name(file: &Path) -> String {
+	file.components()
+		.map(|c| c.as_os_str().to_string_lossy().to_string())
+		.collect::<Vec<String>>()
+		.join("/")
+}
+
+/// Writes the files into a zip at `target`, by way of a hidden temporary file next to the
+/// original.
+fn write_zip(
+	source_dir: &Path,
+	files: Vec<PathBuf>,
+	original: &Path,
+	target: &Path,
+) -> Result<(), ProcessFileError> {
+	let file_name = target
+		.file_name()
+		.map(|name| name.to_string_lossy().to_string())
+		.unwrap_or_default();
+
+	let partial = original.with_file_name(format!(".{}.part", file_name));
+
+	let result = write_zip_entries(source_dir, &files, &partial)
+		.and_then(|_| verify_zip(source_dir, &files, &partial))
+		.and_then(|_| Ok(std::fs::rename(&partial, target)?));
+
+	if result.is_err() && partial.exists() {
+		let _ = std::fs::remove_file(&partial);
+	}
+
+	result
+}
+
+fn write_zip_entries(
+	source_dir: &Path,
+	files: &[PathBuf],
+	dest: &Path,
+) -> Result<(), ProcessFileError> {
+	let mut zip = ZipWriter::new(BufWriter::new(File::create(dest)?));
+
+	// Pages are images, which are already compressed.
+	let options = FileOptions::default().compression_method(CompressionMethod::Stored);
+
+	for file in files {
+		zip.start_file(entry_name(file), options)?;
+		io::copy(&mut File::open(source_dir.join(file))?, &mut zip)?;
+	}
+
+	zip.finish()?;
+
+	Ok(())
+}
+
+/// Makes sure every file made it into the zip, in full.
+fn verify_zip(
+	source_dir: &Path,
+	files: &[PathBuf],
+	zip_path: &Path,
+) -> Result<(), ProcessFileError> {
+	let mut archive = ZipArchive::new(File::open(zip_path)?)?;
+
+	if archive.len() != files.len() {
+		return Err(ProcessFileError::ConversionError(format!(
+			"Expected {} files in the converted archive, found {}",
+			files.len(),
+			archive.len()
+		)));
+	}
+
+	for (index, file) in files.iter().enumerate() {
+		let entry = archive.by_index(index)?;
+		let expected_size = std::fs::metadata(source_dir.join(file))?.len();
+
+		if entry.size() != expected_size {
+			return Err(ProcessFileError::ConversionError(format!(
+				"{} was not written in full to the converted archive",
+				entry.name()
+			)));
+		}
+	}
+
+	Ok(())
+}
@@ -1 +1,172 @@
 pub mod archives;
+
+use rocket::tokio;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+use crate::{
+	config::{context::Context, get_config_dir},
+	fs::scanner::utils::{set_media_metadata, set_media_pages, ProcessedMedia},
+	prisma::media,
+	types::{errors::ApiError, event::ClientEvent},
+};
+
+/// Converts the .cbr file of a media to a .cbz, and points the media at the new file. The
+/// media entity itself is kept, and with it every user's read progress and the assigned
+/// tags. The original file is removed afterwards if `delete_original` is set, and otherwise
+/// moved out of the library (see `keep_original`), so it isn't scanned as a media of its own.
+pub async fn convert_media_to_cbz(
+	ctx: &Context,
+	media_id: String,
+	delete_original: bool,
+) -> Result<media::Data, ApiError> {
+	let media = ctx
+		.db
+		.media()
+		.find_unique(media::id::equals(media_id.clone()))
+		.exec()
+		.await?;
+
+	let media = match media {
+		Some(media) => media,
+		None => {
+			return Err(ApiError::NotFound(format!(
+				"Media with id {} not found",
+				media_id
+			)))
+		},
+	};
+
+	if !media.extension.eq_ignore_ascii_case("cbr") {
+		return Err(ApiError::BadRequest(format!(
+			"Media with id {} is not a cbr file. Only cbr files can be converted to cbz",
+			media_id
+		)));
+	}
+
+	let original = PathBuf::from(&media.path);
+
+	log::info!("Converting {:?} to cbz", original);
+
+	// Repacking and processing the new archive are both blocking.
+	let converted_from = original.clone();
+	let (converted, processed) = tokio::task::spawn_blocking(move || {
+		let converted = archives::rar_to_zip(&converted_from)?;
+
+		let processed = match dir_entry(&converted) {
+			Some(entry) => ProcessedMedia::from_entry(&entry),
+			None => Err(std::io::Error::new(
+				std::io::ErrorKind::NotFound,
+				format!("{:?} could not be read", converted),
+			)
+			.into()),
+		};
+
+		match processed {
+			Ok(processed) => Ok((converted, processed)),
+			Err(e) => {
+				let _ = std::fs::remove_file(&converted);
+				Err(e)
+			},
+		}
+	})
+	.await
+	.map_err(|e| ApiError::InternalServerError(e.to_string()))??;
+
+	let updated = match update_converted_media(ctx, media_id, processed).await {
+		Ok(updated) => updated,
+		Err(e) => {
+			// The media still points at the original, so the new file would only be picked up
+			// as a duplicate by the next scan.
+			let _ = std::fs::remove_file(&converted);
+			return Err(e);
+		},
+	};
+
+	if delete_original {
+		if let Err(e) = std::fs::remove_file(&original) {
+			log::error!("Failed to remove {:?} after converting it: {}", original, e);
+		}
+	} else {
+		match keep_original(&original, &updated.id) {
+			Ok(kept) => {
+				log::info!("Moved {:?} to {:?} after converting it", original, kept)
+			},
+			Err(e) => {
+				log::error!("Failed to move {:?} after converting it: {}", original, e)
+			},
+		}
+	}
+
+	let _ = ctx.emit_client_event(ClientEvent::UpdatedMedia(updated.clone()));
+
+	Ok(updated)
+}
+
+async fn update_converted_media(
+	ctx: &Context,
+	media_id: String,
+	processed: ProcessedMedia,
+) -> Result<media::Data, ApiError> {
+	let media = ctx
+		.db
+		.media()
+		.find_unique(media::id::equals(media_id.clone()))
+		.update(vec![
+			media::path::set(processed.path),
+			media::extension::set(processed.extension),
+			media::size::set(processed.size),
+			media::pages::set(processed.pages),
+			media::checksum::set(processed.checksum),
+			media::full_checksum::set(None),
+			media::cover_hash::set(None),
+			media::verified_at::set(None),
+			media::description::set(processed.description),
+			media::modified_at::set(processed.modified_at),
+			media::thumbnail_path::set(processed.thumbnail_path),
+			media::status::set("READY".to_string()),
+			media::status_reason::set(None),
+		])
+		.exec()
+		.await?;
+
+	let media = match media {
+		Some(media) => media,
+		None => {
+			return Err(ApiError::NotFound(format!(
+				"Media with id {} not found",
+				media_id
+			)))
+		},
+	};
+
+	set_media_metadata(ctx, media.id.clone(), processed.metadata).await?;
+	set_media_pages(ctx, media.id.clone(), processed.page_table).await?;
+
+	Ok(media)
+}
+
+/// Moves a converted .cbr file to STUMP_CONFIG_DIR/converted/<media id>/, keeping its name.
+/// Returns the new path of the file.
+fn keep_original(original: &Path, media_id: &str) -> std::io::Result<PathBuf> {
+	let dir = get_config_dir().join("converted").join(media_id);
+	std::fs::create_dir_all(&dir)?;
+
+	let kept = dir.join(original.file_name().unwrap_or_default());
+
+	// Renaming fails when the config directory is on another file system than the library.
+	if std::fs::rename(original, &kept).is_err() {
+		std::fs::copy(original, &kept)?;
+		std::fs::remove_file(original)?;
+	}
+
+	Ok(kept)
+}
+
+fn dir_entry(path: &Path) -> Option<walkdir::DirEntry> {
+	WalkDir::new(path)
+		.max_depth(0)
+		.into_iter()
+		.next()
+		.and_then(|e| e.ok())
+}
@@ -0,0 +1,153 @@
+use image::{imageops::FilterType, DynamicImage};
+use std::collections::HashMap;
+
+use crate::{fs::media_file, types::errors::ProcessFileError};
+
+/// The width and height the cover is shrunk to before hashing. One extra column is needed,
+/// since each bit compares a pixel with the one to its right.
+const HASH_WIDTH: u32 = 9;
+const HASH_HEIGHT: u32 = 8;
+
+/// The default number of bits the cover hashes of two media may differ in for them to be
+/// considered the same book. Re-encoded covers usually differ in a bit or two, while
+/// different issues of the same series usually differ in 15 or more.
+pub const DEFAULT_MAX_DISTANCE: u32 = 6;
+
+/// Computes the difference hash (dHash) of the cover (first page) of a book. Unlike a
+/// checksum, covers which look alike get hashes which differ in only a few bits, even when
+/// the images were re-encoded, resized or slightly recolored.
+///
+/// The hash is stored as 16 hex characters.
+pub fn cover_hash(file: &str) -> Result<String, ProcessFileError> {
+	let (_, bytes) = media_file::get_page(file, 1)?;
+
+	let image = image::load_from_memory(&bytes)
+		.map_err(|e| ProcessFileError::Unknown(e.to_string()))?;
+
+	Ok(format!("{:016x}", difference_hash(&image)))
+}
+
+fn difference_hash(image: &DynamicImage) -> u64 {
+	let small = image
+		.resize_exact(HASH_WIDTH, HASH_HEIGHT, FilterType::Triangle)
+		.to_luma8();
+
+	let mut hash = 0u64;
+
+	for y in 0..HASH_HEIGHT {
+		for x in 0..HASH_WIDTH - 1 {
+			let left = small.get_pixel(x, y)[0];
+			let right = small.get_pixel(x + 1, y)[0];
+
+			hash = (hash << 1) | (left > right) as u64;
+		}
+	}
+
+	hash
+}
+
+/// Parses a cover hash, as stored by `cover_hash`.
+pub fn parse_hash(hash: &str) -> Option<u64> {
+	u64::from_str_radix(hash, 16).ok()
+}
+
+/// Groups the hashes into clusters, where every hash is within `max_distance` bits of the
+/// first hash in its cluster. Unlike chaining hashes which are merely close to one another,
+/// a cluster can't drift away from where it started, so any two of its hashes differ in at
+/// most twice `max_distance` bits. Returns the indexes of the hashes in each cluster of two
+/// or more, in the order they were given.
+///
+/// Rather than comparing every pair of hashes, each hash is split into `max_distance + 1`
+/// segments. Two hashes within `max_distance` bits can't differ in every segment, so only
+/// hashes which share a segment exactly need to be compared.
+pub fn cluster_hashes(hashes: &[u64], max_distance: u32) -> Vec<Vec<usize>> {
+	let segments = hash_segments(max_distance);
+
+	let mut buckets = HashMap::<(usize, u64), Vec<usize>>::new();
+
+	for (index, hash) in hashes.iter().enumerate() {
+		for (segment, mask) in segments.iter().enumerate() {
+			buckets
+				.entry((segment, hash & mask))
+				.or_default()
+				.push(index);
+		}
+	}
+
+	let mut clustered = vec![false; hashes.len()];
+	let mut clusters = Vec::new();
+
+	for first in 0..hashes.len() {
+		if clustered[first] {
+			continue;
+		}
+
+		let mut cluster = segments
+			.iter()
+			.enumerate()
+			.flat_map(|(segment, mask)| &buckets[&(segment, hashes[first] & mask)])
+			.copied()
+			.filter(|index| {
+				*index >= first
+					&& !clustered[*index]
+					&& (hashes[first] ^ hashes[*index]).count_ones() <= max_distance
+			})
+			.collect::<Vec<usize>>();
+
+		cluster.sort_unstable();
+		cluster.dedup();
+
+		if cluster.len() > 1 {
+			for index in &cluster {
+				clustered[*index] = true;
+			}
+
+			clusters.push(cluster);
+		}
+	}
+
+	clusters
+}
+
+/// Splits the 64 bits of a hash into `max_distance + 1` masks of (nearly) equal width.
+/// When any two hashes are within `max_distance`, a single empty mask puts them all in the
+/// same bucket.
+fn hash_segments(max_distance: u32) -> Vec<u64> {
+	if max_distance >= 64 {
+		return vec![0];
+	}
+
+	let count = max_distance + 1;
+
+	(0..count)
+		.map(|segment| {
+			let (start, end) = (segment * 64 / count, (segment + 1) * 64 / count);
+
+			(start..end).fold(0u64, |mask, bit| mask | (1 << bit))
+		})
+		.collect()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn clusters_hashes_within_distance() {
+		let hashes = [
+			0b0000_0000,
+			0b1111_0000_0000,
+			0b0000_0011,
+			0b0000_0111,
+			u64::MAX,
+		];
+
+		// 0 and 2 differ by 2 bits, while 3 is 3 bits from 0, so it isn't pulled in through 2.
+		assert_eq!(cluster_hashes(&hashes, 2), vec![vec![0, 2]]);
+		assert_eq!(cluster_hashes(&hashes, 3), vec![vec![0, 2, 3]]);
+		assert_eq!(cluster_hashes(&hashes, 64), vec![vec![0, 1, 2, 3, 4]]);
+		assert_eq!(cluster_hashes(&hashes, 0), Vec::<Vec<usize>>::new());
+		assert_eq!(parse_hash("00000000000000ff"), Some(0xff));
+		assert_eq!(parse_hash("not a hash"), None);
+	}
+}
@@ -1,4 +1,5 @@
 use std::{
+	collections::HashMap,
 	fs::File,
 	os::unix::prelude::MetadataExt,
 	path::{Path, PathBuf},
@@ -9,7 +10,7 @@ use crate::{
 	types::{
 		alias::ProcessResult,
 		errors::ProcessFileError,
-		models::{MediaMetadata, ProcessedMediaFile},
+		models::{metadata::MediaMetadata, ProcessedMediaFile},
 	},
 };
 use epub::doc::EpubDoc;
@@ -57,7 +58,7 @@ pub fn process_epub(file: &DirEntry) -> ProcessResult {
 
 	let pages = epub_file.get_num_pages() as i32;
 
-	let metadata: Option<MediaMetadata> = None;
+	let metadata = metadata_from_opf(&epub_file.metadata);
 
 	Ok(ProcessedMediaFile {
 		checksum: digest_epub(
@@ -74,6 +75,114 @@ pub fn process_epub(file: &DirEntry) -> ProcessResult {
 		),
 		metadata,
 		pages,
+		page_table: vec![],
+	})
+}
+
+/// Maps the OPF metadata of an epub into media metadata. Dublin Core elements are keyed by
+/// their name without the namespace (ex: "creator"), while `<meta>` elements are keyed by
+/// their `name` (EPUB 2, ex: "calibre:series") or `property` (EPUB 3, ex:
+/// "belongs-to-collection"). Returns `None` when none of the fields Stump uses are present.
+fn metadata_from_opf(opf: &HashMap<String, Vec<String>>) -> Option<MediaMetadata> {
+	let first = |keys: &[&str]| {
+		keys.iter()
+			.filter_map(|key| opf.get(*key))
+			.flatten()
+			.map(|value| value.trim())
+			.find(|value| !value.is_empty())
+			.map(|value| value.to_string())
+	};
+
+	let joined = |key: &str| {
+		opf.get(key)
+			.map(|values| {
+				values
+					.iter()
+					.map(|value| value.trim())
+					.filter(|value| !value.is_empty())
+					.collect::<Vec<&str>>()
+					.join(", ")
+			})
+			.filter(|value| !value.is_empty())
+	};
+
+	let (year, month, day) = match first(&["date"]) {
+		Some(date) => parse_opf_date(&date),
+		None => (None, None, None),
+	};
+
+	let metadata = MediaMetadata {
+		title: first(&["title"]),
+		series: first(&["calibre:series", "belongs-to-collection"]),
+		number: first(&["calibre:series_index", "group-position"])
+			.map(|number| normalize_series_index(&number)),
+		summary: first(&["description"]),
+		writer: joined("creator"),
+		publisher: first(&["publisher"]),
+		genre: joined("subject"),
+		language_iso: first(&["language"]),
+		gtin: opf.get("identifier").and_then(|ids| find_isbn(ids)),
+		web: first(&["source"]),
+		year,
+		month,
+		day,
+		..Default::default()
+	};
+
+	match metadata == MediaMetadata::default() {
+		true => None,
+		false => Some(metadata),
+	}
+}
+
+/// OPF dates are usually ISO 8601, but are often just a year. ex: "2018-05-01T00:00:00+00:00"
+fn parse_opf_date(date: &str) -> (Option<i32>, Option<i32>, Option<i32>) {
+	let date = date.split('T').next().unwrap_or_default();
+
+	let mut parts = date.split('-').map(|part| part.trim().parse::<i32>().ok());
+
+	let year = parts.next().flatten();
+	let month = parts
+		.next()
+		.flatten()
+		.filter(|month| (1..=12).contains(month));
+	let day = parts.next().flatten().filter(|day| (1..=31).contains(day));
+
+	(year, month, day)
+}
+
+/// calibre writes series indexes as floats, ex: "3.0". Whole numbers drop the fraction.
+fn normalize_series_index(index: &str) -> String {
+	match index.parse::<f64>() {
+		Ok(number) if number.fract() == 0.0 => (number as i64).to_string(),
+		_ => index.to_string(),
+	}
+}
+
+/// Finds the ISBN among the identifiers of an epub, which may also be UUIDs or URLs.
+/// ex: "urn:isbn:9780316769488" or "978-0-316-76948-8"
+fn find_isbn(identifiers: &[String]) -> Option<String> {
+	identifiers.iter().find_map(|identifier| {
+		let identifier = identifier.trim();
+		let identifier = identifier
+			.strip_prefix("urn:isbn:")
+			.or_else(|| identifier.strip_prefix("isbn:"))
+			.unwrap_or(identifier);
+
+		let isbn = identifier
+			.chars()
+			.filter(|c| *c != '-' && *c != ' ')
+			.collect::<String>();
+
+		let is_isbn = (isbn.len() == 10 || isbn.len() == 13)
+			&& isbn.chars().enumerate().all(|(i, c)| {
+				c.is_ascii_digit() || (i == 9 && c.to_ascii_uppercase() == 'X')
+			});
+
+		match is_isbn {
+			true => Some(isbn),
+			false => None,
+		}
 	})
 }
 
@@ -90,8 +199,8 @@ pub fn get_epub_cover(file: &str) -> GetPageResult {
 		ProcessFileError::EpubReadError(e.to_string())
 	})?;
 
-	// FIXME: mime type
-	Ok((media_file::get_content_type_from_mime("image/png"), cover))
+	// The cover has no name to go by, so its bytes have to be enough.
+	Ok((media_file::image_content_type("", &cover), cover))
 }
 
 pub fn get_epub_chapter(
@@ -292,6 +401,41 @@ mod tests {
 		Ok(())
 	}
 
+	#[test]
+	fn maps_opf_metadata() {
+		let opf = [
+			("title", vec!["Dune"]),
+			("creator", vec!["Frank Herbert"]),
+			(
+				"identifier",
+				vec!["b54a2c1e-1b2c-4c1a", "urn:isbn:978-0-441-17271-9"],
+			),
+			("date", vec!["1965-08-01T00:00:00+00:00"]),
+			("calibre:series", vec!["Dune"]),
+			("calibre:series_index", vec!["1.0"]),
+		]
+		.into_iter()
+		.map(|(key, values)| {
+			(
+				key.to_string(),
+				values.into_iter().map(|v| v.to_string()).collect(),
+			)
+		})
+		.collect();
+
+		let metadata = super::metadata_from_opf(&opf).unwrap();
+
+		assert_eq!(metadata.title.as_deref(), Some("Dune"));
+		assert_eq!(metadata.writer.as_deref(), Some("Frank Herbert"));
+		assert_eq!(metadata.gtin.as_deref(), Some("9780441172719"));
+		assert_eq!(metadata.series.as_deref(), Some("Dune"));
+		assert_eq!(metadata.number.as_deref(), Some("1"));
+		assert_eq!(
+			(metadata.year, metadata.month, metadata.day),
+			(Some(1965), Some(8), Some(1))
+		);
+	}
+
 	#[test]
 	fn canonical_correction() {
 		let invalid = PathBuf::from("OEBPS/../Styles/style.css");
@@ -330,3 +474,24 @@ mod tests {
 		Ok(())
 	}
 }
+
+/// Reads every item in the spine of an epub, i.e. every chapter.
+pub fn verify_epub(file: &str) -> Result<(), ProcessFileError> {
+	let mut epub_file = load_epub(file)?;
+
+	let spine = epub_file.spine.clone();
+
+	if spine.is_empty() {
+		return Err(ProcessFileError::EpubReadError(
+			"The epub has no spine".to_string(),
+		));
+	}
+
+	for id in spine {
+		epub_file
+			.get_resource(&id)
+			.map_err(|e| ProcessFileError::CorruptPageError(id.clone(), e.to_string()))?;
+	}
+
+	Ok(())
+}
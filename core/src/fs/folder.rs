@@ -0,0 +1,222 @@
+use std::{
+	fs::File,
+	io::Read,
+	path::{Path, PathBuf},
+	time::SystemTime,
+};
+use walkdir::DirEntry;
+
+use crate::{
+	fs::media_file::{self, GetPageResult},
+	types::{
+		alias::ProcessResult,
+		errors::ProcessFileError,
+		models::{metadata::MediaMetadata, ProcessedMediaFile},
+	},
+};
+
+use super::{
+	checksum,
+	page_index::{PageImage, PageIndex},
+};
+
+/// The extension recorded for folder books, which don't have one of their own.
+pub const FOLDER_BOOK_EXTENSION: &str = "folder";
+
+const COMIC_INFO_FILE: &str = "ComicInfo.xml";
+
+fn is_hidden(path: &Path) -> bool {
+	path.file_name()
+		.map(|name| name.to_string_lossy().starts_with('.'))
+		.unwrap_or(false)
+}
+
+/// Whether the directory is a folder book, i.e. a leaf folder holding nothing but images
+/// (and optionally a ComicInfo.xml). Hidden files, e.g. .DS_Store, don't count.
+pub fn is_folder_book(path: &Path) -> bool {
+	let items = match std::fs::read_dir(path) {
+		Ok(items) => items,
+		Err(_) => return false,
+	};
+
+	let mut has_images = false;
+
+	for item in items.filter_map(|item| item.ok()) {
+		let item_path = item.path();
+
+		if is_hidden(&item_path) {
+			continue;
+		} else if item_path.is_dir() {
+			return false;
+		}
+
+		let name = item.file_name().to_string_lossy().to_string();
+
+		if media_file::is_image_file_name(&name) {
+			has_images = true;
+		} else if name != COMIC_INFO_FILE {
+			return false;
+		}
+	}
+
+	has_images
+}
+
+/// Reads the ComicInfo.xml of a folder book, and builds the page index from its images.
+fn read_folder_pages(
+	path: &Path,
+) -> Result<(Option<MediaMetadata>, PageIndex), ProcessFileError> {
+	let images = std::fs::read_dir(path)?
+		.filter_map(|item| item.ok())
+		.filter(|item| !is_hidden(&item.path()))
+		.filter_map(|item| {
+			// Follows symlinks, unlike the metadata of the item itself.
+			let metadata = std::fs::metadata(item.path())
+				.ok()
+				.filter(|metadata| metadata.is_file())?;
+			let name = item.file_name().to_string_lossy().to_string();
+
+			Some((name, metadata.len()))
+		})
+		.filter(|(name, _)| media_file::is_image_file_name(name))
+		.map(|(name, size)| PageImage::new(name, size, &[]))
+		.collect::<Vec<PageImage>>();
+
+	let comic_info = std::fs::read_to_string(path.join(COMIC_INFO_FILE))
+		.ok()
+		.and_then(media_file::process_comic_info);
+
+	let pages = PageIndex::new(images, comic_info.as_ref());
+
+	Ok((comic_info, pages))
+}
+
+/// The combined size of the files in a folder book, and when the most recent of them was
+/// modified. The folder's own modified time only changes when files are added or removed,
+/// not when one of them is replaced in place.
+pub fn folder_stats(path: &Path) -> (u64, Option<SystemTime>) {
+	let items = match std::fs::read_dir(path) {
+		Ok(items) => items,
+		Err(_) => return (0, None),
+	};
+
+	items
+		.filter_map(|item| item.ok())
+		.filter(|item| !is_hidden(&item.path()))
+		.filter_map(|item| item.metadata().ok())
+		.filter(|metadata| metadata.is_file())
+		.fold((0, None), |(size, modified), metadata| {
+			(
+				size + metadata.len(),
+				modified.max(metadata.modified().ok()),
+			)
+		})
+}
+
+pub fn digest_folder(images: &[PathBuf]) -> Option<String> {
+	// Same as zips, the first few images are enough to tell two books apart.
+	let mut reader: Box<dyn Read> = Box::new(std::io::empty());
+
+	for image in images.iter().take(6) {
+		match File::open(image) {
+			Ok(file) => reader = Box::new(reader.chain(file)),
+			Err(e) => {
+				log::error!(
+					"Failed to open {:?}, unable to create checksum: {}",
+					image,
+					e
+				);
+				return None;
+			},
+		}
+	}
+
+	match checksum::digest_from_reader(reader) {
+		Ok(digest) => Some(digest),
+		Err(e) => {
+			log::error!(
+				"Failed to digest folder book, unable to create checksum: {}",
+				e
+			);
+			None
+		},
+	}
+}
+
+/// Hashes every image of a folder book in reading order, see `checksum::full_digest`.
+pub fn full_digest_folder(path: &Path) -> Result<String, ProcessFileError> {
+	let (_, pages) = read_folder_pages(path)?;
+
+	let images = pages
+		.iter()
+		.map(|name| path.join(name))
+		.collect::<Vec<PathBuf>>();
+
+	checksum::full_digest(&images).map_err(|e| ProcessFileError::Unknown(e.to_string()))
+}
+
+/// Reads every page of a folder book, and decodes the header of each image.
+pub fn verify_folder(file: &str) -> Result<(), ProcessFileError> {
+	let path = Path::new(file);
+	let (_, pages) = read_folder_pages(path)?;
+
+	if pages.is_empty() {
+		return Err(ProcessFileError::NoImageError);
+	}
+
+	for name in pages.iter() {
+		let contents = std::fs::read(path.join(name))?;
+
+		media_file::verify_image(name, &contents)?;
+	}
+
+	Ok(())
+}
+
+/// Processes a folder book, reading its ComicInfo.xml and counting the pages.
+pub fn process_folder(file: &DirEntry) -> ProcessResult {
+	log::info!("Processing folder book: {}", file.path().display());
+
+	let path = file.path();
+	let (comic_info, pages) = read_folder_pages(path)?;
+
+	if pages.is_empty() {
+		return Err(ProcessFileError::NoImageError);
+	}
+
+	let images = pages
+		.iter()
+		.map(|name| path.join(name))
+		.collect::<Vec<PathBuf>>();
+
+	Ok(ProcessedMediaFile {
+		checksum: digest_folder(&images),
+		metadata: comic_info,
+		pages: pages.len(),
+		page_table: pages.into_images(),
+	})
+}
+
+/// Get an image from a folder book by index (page).
+pub fn get_folder_image(file: &str, page: i32) -> GetPageResult {
+	let (_, pages) = read_folder_pages(Path::new(file))?;
+
+	let image = match pages.get(page) {
+		Some(name) => Path::new(file).join(name),
+		None => {
+			log::error!("Could not find image for page {} in folder {}", page, file);
+			return Err(ProcessFileError::NoImageError);
+		},
+	};
+
+	let image = image.to_string_lossy();
+
+	let contents = std::fs::read(image.as_ref())?;
+
+	Ok((media_file::image_content_type(&image, &contents), contents))
+}
+
+/// Reads an image from a folder book by its name, as stored in the page table of the book.
+pub fn read_folder_image(file: &str, name: &str) -> Result<Vec<u8>, ProcessFileError> {
+	Ok(std::fs::read(Path::new(file).join(name))?)
+}
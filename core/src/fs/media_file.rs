@@ -1,17 +1,26 @@
 use rocket::http::ContentType;
-use std::{path::Path, str::FromStr};
+use std::{io::Cursor, path::Path, str::FromStr};
 use walkdir::DirEntry;
 
 use crate::types::{
 	alias::ProcessResult, errors::ProcessFileError, http::ImageResponse,
-	models::MediaMetadata,
+	models::metadata::MediaMetadata,
 };
 
 use super::{
-	epub::{get_epub_cover, process_epub},
+	checksum,
+	epub::{get_epub_cover, process_epub, verify_epub},
+	folder::{
+		full_digest_folder, get_folder_image, process_folder, read_folder_image,
+		verify_folder,
+	},
+	page_index::PageImage,
 	// epub::get_epub_page,
-	rar::{get_rar_image, process_rar},
-	zip::{get_zip_image, process_zip},
+	pdf::{get_pdf_page, process_pdf, verify_pdf},
+	rar::{get_rar_image, process_rar, read_rar_image, verify_rar},
+	seven_zip::{get_7z_image, process_7z, read_7z_image, verify_7z},
+	tar::{get_tar_image, process_tar, read_tar_image, verify_tar},
+	zip::{get_zip_image, process_zip, read_zip_image, verify_zip},
 };
 
 // FIXME: this module does way too much. It should be cleaned up, way too many vaguely
@@ -29,6 +38,86 @@ pub trait IsImage {
 	fn is_image(&self) -> bool;
 }
 
+/// The extensions of the image formats which may be pages of a book.
+const IMAGE_EXTENSIONS: [&str; 13] = [
+	"jpg", "jpeg", "png", "gif", "webp", "avif", "jxl", "bmp", "tif", "tiff", "svg",
+	"heic", "heif",
+];
+
+/// The number of bytes read from the start of a file to detect whether it is an image. This
+/// covers the signatures of every image format `infer` knows about.
+pub const IMAGE_HEADER_LEN: u64 = 64;
+
+/// Whether a file inside an archive is an image, going by its name.
+pub fn is_image_file_name(name: &str) -> bool {
+	Path::new(name)
+		.extension()
+		.map(|extension| {
+			let extension = extension.to_string_lossy().to_lowercase();
+
+			IMAGE_EXTENSIONS.contains(&extension.as_str())
+		})
+		.unwrap_or(false)
+}
+
+/// Whether a file inside an archive is an image, going by its first bytes (see
+/// `IMAGE_HEADER_LEN`). When the bytes don't match any known signature (e.g. an svg, which
+/// is just text) the name of the file is used instead.
+pub fn is_image_content(name: &str, header: &[u8]) -> bool {
+	match infer::get(header) {
+		Some(kind) => kind.matcher_type() == infer::MatcherType::Image,
+		None => is_image_file_name(name),
+	}
+}
+
+/// The content type of an image read from a book, going by its bytes so that a page with the
+/// wrong extension (e.g. a webp named .jpg) is still served correctly.
+pub fn image_content_type(name: &str, bytes: &[u8]) -> ContentType {
+	match infer::get(bytes) {
+		Some(kind) if kind.matcher_type() == infer::MatcherType::Image => {
+			get_content_type_from_mime(kind.mime_type())
+		},
+		_ => guess_content_type(name),
+	}
+}
+
+/// The mime type of an image read from a book, going by its first bytes and falling back to
+/// its name. ex: "image/webp"
+pub fn image_mime(name: &str, header: &[u8]) -> Option<String> {
+	match infer::get(header) {
+		Some(kind) if kind.matcher_type() == infer::MatcherType::Image => {
+			Some(kind.mime_type().to_string())
+		},
+		_ => guess_mime(Path::new(name)).filter(|mime| mime.starts_with("image/")),
+	}
+}
+
+/// Checks that an image read from a book is intact, by decoding its header. Images in formats
+/// the image crate can't decode (e.g. jxl or heic) are only checked by their signature, and
+/// svgs (which are just text) by their name.
+pub fn verify_image(name: &str, bytes: &[u8]) -> Result<(), ProcessFileError> {
+	let corrupt = |reason: &str| {
+		ProcessFileError::CorruptPageError(name.to_string(), reason.to_string())
+	};
+
+	if bytes.is_empty() {
+		return Err(corrupt("The image is empty"));
+	}
+
+	if let Ok(format) = image::guess_format(bytes) {
+		return image::io::Reader::with_format(Cursor::new(bytes), format)
+			.into_dimensions()
+			.map(|_| ())
+			.map_err(|e| corrupt(&e.to_string()));
+	}
+
+	match infer::get(bytes) {
+		Some(kind) if kind.matcher_type() == infer::MatcherType::Image => Ok(()),
+		None if guess_content_type(name) == ContentType::SVG => Ok(()),
+		_ => Err(corrupt("Not a known image format")),
+	}
+}
+
 pub fn process_comic_info(buffer: String) -> Option<MediaMetadata> {
 	if buffer.is_empty() {
 		return None;
@@ -50,6 +139,13 @@ fn temporary_content_workarounds(extension: &str) -> ContentType {
 		return ContentType::XML;
 	}
 
+	match extension.to_lowercase().as_str() {
+		"jxl" => return ContentType::new("image", "jxl"),
+		"heic" => return ContentType::new("image", "heic"),
+		"heif" => return ContentType::new("image", "heif"),
+		_ => {},
+	}
+
 	ContentType::Any
 }
 
@@ -115,11 +211,20 @@ pub fn guess_mime(path: &Path) -> Option<String> {
 		"cbz" => Some("application/vnd.comicbook+zip".to_string()),
 		"rar" => Some("application/vnd.rar".to_string()),
 		"cbr" => Some("application/vnd.comicbook-rar".to_string()),
+		"7z" => Some("application/x-7z-compressed".to_string()),
+		"cb7" => Some("application/x-cb7".to_string()),
+		"tar" => Some("application/x-tar".to_string()),
+		"cbt" => Some("application/x-cbt".to_string()),
 		"png" => Some("image/png".to_string()),
 		"jpg" => Some("image/jpeg".to_string()),
 		"jpeg" => Some("image/jpeg".to_string()),
 		"webp" => Some("image/webp".to_string()),
 		"gif" => Some("image/gif".to_string()),
+		"avif" => Some("image/avif".to_string()),
+		"jxl" => Some("image/jxl".to_string()),
+		"bmp" => Some("image/bmp".to_string()),
+		"heic" => Some("image/heic".to_string()),
+		"heif" => Some("image/heif".to_string()),
 		_ => None,
 	}
 }
@@ -145,6 +250,11 @@ pub fn infer_mime_from_path(path: &Path) -> Option<String> {
 }
 
 pub fn get_page(file: &str, page: i32) -> GetPageResult {
+	// Folder books are the only media without a file of their own.
+	if Path::new(file).is_dir() {
+		return get_folder_image(file, page);
+	}
+
 	let mime = guess_mime(Path::new(file));
 
 	match mime.as_deref() {
@@ -152,6 +262,11 @@ pub fn get_page(file: &str, page: i32) -> GetPageResult {
 		Some("application/vnd.comicbook+zip") => get_zip_image(file, page),
 		Some("application/vnd.rar") => get_rar_image(file, page),
 		Some("application/vnd.comicbook-rar") => get_rar_image(file, page),
+		Some("application/x-7z-compressed") => get_7z_image(file, page),
+		Some("application/x-cb7") => get_7z_image(file, page),
+		Some("application/x-tar") => get_tar_image(file, page),
+		Some("application/x-cbt") => get_tar_image(file, page),
+		Some("application/pdf") => get_pdf_page(file, page),
 		Some("application/epub+zip") => {
 			if page == 1 {
 				get_epub_cover(file)
@@ -169,9 +284,152 @@ pub fn get_page(file: &str, page: i32) -> GetPageResult {
 	}
 }
 
+/// Gets a page using the image stored for it in the page table of the book, which is read
+/// directly instead of listing the pages of the book first. When the image can't be read
+/// from the book as stored (e.g. the file changed since it was last scanned), the pages are
+/// listed as usual.
+pub fn get_indexed_page(
+	file: &str,
+	page: i32,
+	image: Option<&PageImage>,
+) -> GetPageResult {
+	let image = match image {
+		Some(image) => image,
+		None => return get_page(file, page),
+	};
+
+	match read_page_image(file, image) {
+		Ok(response) => Ok(response),
+		Err(e) => {
+			log::warn!(
+				"Failed to read page {} of {} from its page table, listing its pages instead: {}",
+				page,
+				file,
+				e
+			);
+
+			get_page(file, page)
+		},
+	}
+}
+
+fn read_page_image(file: &str, image: &PageImage) -> GetPageResult {
+	let bytes = if Path::new(file).is_dir() {
+		read_folder_image(file, &image.name)?
+	} else {
+		match guess_mime(Path::new(file)).as_deref() {
+			Some("application/zip") => read_zip_image(file, &image.name)?,
+			Some("application/vnd.comicbook+zip") => read_zip_image(file, &image.name)?,
+			Some("application/vnd.rar") => read_rar_image(file, &image.name)?,
+			Some("application/vnd.comicbook-rar") => read_rar_image(file, &image.name)?,
+			Some("application/x-7z-compressed") => read_7z_image(file, &image.name)?,
+			Some("application/x-cb7") => read_7z_image(file, &image.name)?,
+			Some("application/x-tar") => read_tar_image(file, &image.name)?,
+			Some("application/x-cbt") => read_tar_image(file, &image.name)?,
+			_ => return Err(ProcessFileError::UnsupportedFileType(file.to_string())),
+		}
+	};
+
+	page_image_response(image, bytes)
+}
+
+/// Checks the bytes read for an image from the page table of a book, and detects their
+/// content type.
+pub fn page_image_response(image: &PageImage, bytes: Vec<u8>) -> GetPageResult {
+	// An image replaced in place keeps its name, but rarely its size.
+	if bytes.len() as u64 != image.size {
+		return Err(ProcessFileError::NoImageError);
+	}
+
+	// The stored content type isn't used, since images in a rar are only sniffed when their
+	// name doesn't say what they are. Sniffing the bytes is cheap next to reading them.
+	Ok((image_content_type(&image.name, &bytes), bytes))
+}
+
+/// Whether the file is a zip (or cbz), going by its extension.
+pub fn is_zip(file: &str) -> bool {
+	matches!(
+		guess_mime(Path::new(file)).as_deref(),
+		Some("application/zip") | Some("application/vnd.comicbook+zip")
+	)
+}
+
+/// Reads an image file from disk, e.g. a cover image sitting next to a book or in a series
+/// folder.
+pub fn get_image(file: &str) -> GetPageResult {
+	let mime = infer_mime_from_path(Path::new(file));
+
+	match mime.as_deref() {
+		Some(mime) if mime.starts_with("image/") => {
+			Ok((get_content_type_from_mime(mime), std::fs::read(file)?))
+		},
+		_ => Err(ProcessFileError::UnsupportedFileType(file.to_string())),
+	}
+}
+
+/// Gets the thumbnail for a book, preferring the thumbnail override recorded during a scan
+/// over the first page of the book. A thumbnail override that can no longer be read falls
+/// back to the first page.
+pub fn get_thumbnail(file: &str, thumbnail_path: Option<&str>) -> GetPageResult {
+	if let Some(thumbnail_path) = thumbnail_path {
+		match get_image(thumbnail_path) {
+			Ok(image) => return Ok(image),
+			Err(e) => {
+				log::warn!("Failed to read thumbnail {}: {}", thumbnail_path, e);
+			},
+		}
+	}
+
+	get_page(file, 1)
+}
+
+/// Checks that a book can be read in full: every page of an archive (or folder book) is read
+/// and its image header decoded, every page of a pdf is loaded, and every item in the spine of
+/// an epub is read. Returns the first problem found.
+pub fn verify_media(file: &str) -> Result<(), ProcessFileError> {
+	if Path::new(file).is_dir() {
+		return verify_folder(file);
+	}
+
+	let mime = guess_mime(Path::new(file));
+
+	match mime.as_deref() {
+		Some("application/zip") => verify_zip(file),
+		Some("application/vnd.comicbook+zip") => verify_zip(file),
+		Some("application/vnd.rar") => verify_rar(file),
+		Some("application/vnd.comicbook-rar") => verify_rar(file),
+		Some("application/x-7z-compressed") => verify_7z(file),
+		Some("application/x-cb7") => verify_7z(file),
+		Some("application/x-tar") => verify_tar(file),
+		Some("application/x-cbt") => verify_tar(file),
+		Some("application/pdf") => verify_pdf(file),
+		Some("application/epub+zip") => verify_epub(file),
+		None => Err(ProcessFileError::Unknown(format!(
+			"Unable to determine mime type for file: {:?}",
+			file
+		))),
+		_ => Err(ProcessFileError::UnsupportedFileType(file.to_string())),
+	}
+}
+
+/// Hashes the entire contents of a book. For folder books, that is every page in order.
+pub fn full_checksum(file: &str) -> Result<String, ProcessFileError> {
+	let path = Path::new(file);
+
+	if path.is_dir() {
+		return full_digest_folder(path);
+	}
+
+	checksum::full_digest(&[path]).map_err(|e| ProcessFileError::Unknown(e.to_string()))
+}
+
 pub fn process_entry(entry: &DirEntry) -> ProcessResult {
 	log::debug!("Processing entry: {:?}", entry);
 
+	if entry.file_type().is_dir() {
+		return process_folder(entry);
+	}
+
 	let mime = infer_mime_from_path(entry.path());
 
 	// TODO: improve this? kinda verbose
@@ -180,7 +438,12 @@ pub fn process_entry(entry: &DirEntry) -> ProcessResult {
 		Some("application/vnd.comicbook+zip") => process_zip(entry),
 		Some("application/vnd.rar") => process_rar(entry),
 		Some("application/vnd.comicbook-rar") => process_rar(entry),
+		Some("application/x-7z-compressed") => process_7z(entry),
+		Some("application/x-cb7") => process_7z(entry),
+		Some("application/x-tar") => process_tar(entry),
+		Some("application/x-cbt") => process_tar(entry),
 		Some("application/epub+zip") => process_epub(entry),
+		Some("application/pdf") => process_pdf(entry),
 		None => Err(ProcessFileError::Unknown(format!(
 			"Unable to determine mime type for file: {:?}",
 			entry.path()
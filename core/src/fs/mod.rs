@@ -1,7 +1,14 @@
+pub mod cache;
 pub mod checksum;
+pub mod convert;
+pub mod cover_hash;
 pub mod epub;
+pub mod folder;
 pub mod media_file;
+pub mod page_index;
 pub mod pdf;
 pub mod rar;
 pub mod scanner;
+pub mod seven_zip;
+pub mod tar;
 pub mod zip;
@@ -0,0 +1,248 @@
+use std::{cmp::Ordering, collections::HashSet};
+
+use crate::{fs::media_file, prisma::media_page, types::models::metadata::MediaMetadata};
+
+/// An image of a book, as found while reading its pages. These are stored as the page table
+/// of the book when it is scanned, so a page can be served without listing the book again.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PageImage {
+	/// The name of the image in the book. For archives, this is the path of the entry.
+	pub name: String,
+	/// The size of the image in bytes.
+	pub size: u64,
+	/// The mime type of the image, if it could be detected.
+	pub content_type: Option<String>,
+	/// The width of the image in pixels, when the ComicInfo.xml has it.
+	pub width: Option<i32>,
+	/// The height of the image in pixels, when the ComicInfo.xml has it.
+	pub height: Option<i32>,
+}
+
+impl PageImage {
+	/// Creates an image from its name and first few bytes, which are used to detect its
+	/// content type. The bytes may be empty when they weren't read, in which case the name
+	/// is used instead.
+	pub fn new(name: String, size: u64, header: &[u8]) -> Self {
+		let content_type = media_file::image_mime(&name, header);
+
+		PageImage {
+			name,
+			size,
+			content_type,
+			width: None,
+			height: None,
+		}
+	}
+}
+
+impl From<media_page::Data> for PageImage {
+	fn from(data: media_page::Data) -> Self {
+		PageImage {
+			name: data.name,
+			size: data.size as u64,
+			content_type: data.content_type,
+			width: data.width,
+			height: data.height,
+		}
+	}
+}
+
+/// The pages of a book, i.e. its images in reading order. Every format builds one from the
+/// images it finds, both when a book is scanned and when a page is served, so the page count
+/// stored for a book is always what `get_page` can serve.
+///
+/// Images are naturally sorted (ex: "page2.jpg" before "page10.jpg"), folder by folder. When
+/// the ComicInfo.xml of the book lists its `Pages`, that order is used instead, since the
+/// `Image` of each page is its index in the naturally sorted images.
+#[derive(Debug, Default)]
+pub struct PageIndex {
+	pages: Vec<PageImage>,
+}
+
+impl PageIndex {
+	pub fn new(mut images: Vec<PageImage>, metadata: Option<&MediaMetadata>) -> Self {
+		images.sort_by(|a, b| compare_page_names(&a.name, &b.name));
+
+		let pages = match metadata {
+			Some(metadata) if !metadata.pages.is_empty() => {
+				order_by_comic_info(images, metadata)
+			},
+			_ => images,
+		};
+
+		PageIndex { pages }
+	}
+
+	/// The number of pages in the book.
+	pub fn len(&self) -> i32 {
+		self.pages.len() as i32
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.pages.is_empty()
+	}
+
+	/// The names of the images, in reading order.
+	pub fn iter(&self) -> impl Iterator<Item = &str> {
+		self.pages.iter().map(|image| image.name.as_str())
+	}
+
+	/// The name of the image for a page, starting at 1.
+	pub fn get(&self, page: i32) -> Option<&str> {
+		usize::try_from(page - 1)
+			.ok()
+			.and_then(|index| self.pages.get(index))
+			.map(|image| image.name.as_str())
+	}
+
+	/// The images, in reading order. The first image is page 1.
+	pub fn into_images(self) -> Vec<PageImage> {
+		self.pages
+	}
+}
+
+/// Orders the images as listed in the `Pages` of the ComicInfo.xml. Deleted pages are left
+/// out, and images which aren't listed (or are listed with an unknown index) keep their
+/// natural order after the listed ones. The dimensions of listed images are kept as well.
+fn order_by_comic_info(
+	mut images: Vec<PageImage>,
+	metadata: &MediaMetadata,
+) -> Vec<PageImage> {
+	let mut seen = HashSet::new();
+	let mut deleted = HashSet::new();
+	let mut listed = Vec::new();
+
+	for page in &metadata.pages {
+		let index = match page.image.and_then(|image| usize::try_from(image).ok()) {
+			Some(index) if index < images.len() => index,
+			_ => continue,
+		};
+
+		if !seen.insert(index) {
+			continue;
+		}
+
+		images[index].width = page.image_width;
+		images[index].height = page.image_height;
+
+		match page.kind.as_deref() {
+			Some(kind) if kind.eq_ignore_ascii_case("Deleted") => {
+				deleted.insert(index);
+			},
+			_ => listed.push(index),
+		}
+	}
+
+	let unlisted = (0..images.len())
+		.filter(|index| !seen.contains(index) && !deleted.contains(index))
+		.collect::<Vec<usize>>();
+
+	let mut images = images
+		.into_iter()
+		.map(Some)
+		.collect::<Vec<Option<PageImage>>>();
+
+	listed
+		.into_iter()
+		.chain(unlisted.into_iter())
+		.filter_map(|index| images[index].take())
+		.collect()
+}
+
+/// Compares the names of two images in an archive naturally, one path component at a time,
+/// so the images of a nested folder stay together. ex: "ch2/page10.jpg" comes after
+/// "ch2/page9.jpg", and both come before "ch10/page1.jpg"
+pub fn compare_page_names(a: &str, b: &str) -> Ordering {
+	let mut a_components = a.split(|c| c == '/' || c == '\\');
+	let mut b_components = b.split(|c| c == '/' || c == '\\');
+
+	loop {
+		match (a_components.next(), b_components.next()) {
+			(Some(a), Some(b)) => match alphanumeric_sort::compare_str(a, b) {
+				Ordering::Equal => continue,
+				ordering => return ordering,
+			},
+			(None, Some(_)) => return Ordering::Less,
+			(Some(_), None) => return Ordering::Greater,
+			(None, None) => return Ordering::Equal,
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::types::models::metadata::MediaMetadataPage;
+
+	fn images(names: &[&str]) -> Vec<PageImage> {
+		names
+			.iter()
+			.map(|name| PageImage::new(name.to_string(), 0, &[]))
+			.collect()
+	}
+
+	#[test]
+	fn sorts_pages_naturally() {
+		let index = PageIndex::new(
+			images(&[
+				"ch10/page1.jpg",
+				"ch2/page10.jpg",
+				"ch2/page9.jpg",
+				"cover.jpg",
+			]),
+			None,
+		);
+
+		assert_eq!(
+			index.iter().collect::<Vec<&str>>(),
+			vec![
+				"ch2/page9.jpg",
+				"ch2/page10.jpg",
+				"ch10/page1.jpg",
+				"cover.jpg"
+			]
+		);
+		assert_eq!(index.get(1), Some("ch2/page9.jpg"));
+		assert_eq!(index.get(5), None);
+		assert_eq!(index.get(0), None);
+	}
+
+	#[test]
+	fn orders_pages_by_comic_info() {
+		let page = |image: i32, kind: Option<&str>| MediaMetadataPage {
+			image: Some(image),
+			kind: kind.map(|kind| kind.to_string()),
+			..Default::default()
+		};
+
+		let metadata = MediaMetadata {
+			pages: vec![
+				MediaMetadataPage {
+					image_width: Some(1988),
+					image_height: Some(3056),
+					..page(2, Some("FrontCover"))
+				},
+				page(0, None),
+				page(1, Some("Deleted")),
+				page(7, None),
+			],
+			..Default::default()
+		};
+
+		let index = PageIndex::new(
+			images(&["1.jpg", "2.jpg", "cover.jpg", "3.jpg"]),
+			Some(&metadata),
+		);
+
+		// Sorted, the images are 1, 2, 3, cover
+		assert_eq!(
+			index.iter().collect::<Vec<&str>>(),
+			vec!["3.jpg", "1.jpg", "cover.jpg"]
+		);
+		assert_eq!(index.len(), 3);
+
+		let cover = &index.into_images()[0];
+		assert_eq!(cover.content_type.as_deref(), Some("image/jpeg"));
+		assert_eq!((cover.width, cover.height), (Some(1988), Some(3056)));
+	}
+}
@@ -1,5 +1,219 @@
-use crate::fs::media_file::GetPageResult;
+use pdfium_render::prelude::*;
+use rocket::http::ContentType;
+use std::{cell::RefCell, io::Cursor, path::Path, rc::Rc};
+use walkdir::DirEntry;
 
-pub fn get_pdf_page(_file: &str, _page: usize) -> GetPageResult {
-	unimplemented!()
+use crate::{
+	fs::media_file::GetPageResult,
+	types::{
+		alias::ProcessResult,
+		errors::ProcessFileError,
+		models::{metadata::MediaMetadata, ProcessedMediaFile},
+	},
+};
+
+use super::checksum;
+
+/// The width pages are rendered at. Pages taller than they are wide are capped at
+/// `RENDER_MAX_HEIGHT` instead, so a long strip doesn't render into an enormous image.
+const RENDER_WIDTH: u16 = 1600;
+const RENDER_MAX_HEIGHT: u16 = 2400;
+
+thread_local! {
+	/// The pdfium bindings of the thread, see `load_pdfium`. The bindings can't be shared
+	/// between threads, but pdfs are read on the blocking threads, which are reused.
+	static PDFIUM: RefCell<Option<Rc<Pdfium>>> = RefCell::new(None);
+}
+
+/// Binds to the pdfium library, which does the actual rendering on the CPU. The directory
+/// holding the library can be set with STUMP_PDFIUM_PATH, otherwise the system library
+/// is used. The library is only bound once per thread, rather than for every page served.
+/// Failing to bind isn't remembered, so the library can be installed without a restart.
+fn load_pdfium() -> Result<Rc<Pdfium>, ProcessFileError> {
+	PDFIUM.with(|pdfium| {
+		if let Some(pdfium) = pdfium.borrow().as_ref() {
+			return Ok(pdfium.clone());
+		}
+
+		let bindings = match std::env::var("STUMP_PDFIUM_PATH") {
+			Ok(path) => Pdfium::bind_to_library(
+				Pdfium::pdfium_platform_library_name_at_path(&path),
+			),
+			Err(_) => Pdfium::bind_to_system_library(),
+		}
+		.map_err(|e| ProcessFileError::PdfiumLoadError(format!("{:?}", e)))?;
+
+		let bound = Rc::new(Pdfium::new(bindings));
+		*pdfium.borrow_mut() = Some(bound.clone());
+
+		Ok(bound)
+	})
+}
+
+fn load_pdf<'a>(
+	pdfium: &'a Pdfium,
+	path: &str,
+) -> Result<PdfDocument<'a>, ProcessFileError> {
+	pdfium
+		.load_pdf_from_file(path, None)
+		.map_err(|e| ProcessFileError::PdfReadError(format!("{:?}", e)))
+}
+
+pub fn digest_pdf(path: &Path, size: u64) -> Option<String> {
+	// The first few pages are enough to tell two PDFs apart, same as with epubs.
+	let bytes_to_read = size.min(40000);
+
+	match checksum::digest(path.to_str().unwrap(), bytes_to_read) {
+		Ok(digest) => Some(digest),
+		Err(e) => {
+			log::error!(
+				"Failed to digest pdf {:?}, unable to create checksum: {}",
+				path,
+				e
+			);
+			None
+		},
+	}
+}
+
+pub fn process_pdf(file: &DirEntry) -> ProcessResult {
+	log::info!("Processing PDF: {}", file.path().display());
+
+	let path = file.path();
+
+	let pdfium = load_pdfium()?;
+	let document = load_pdf(&pdfium, &path.to_string_lossy())?;
+
+	let pages = document.pages().len() as i32;
+
+	Ok(ProcessedMediaFile {
+		checksum: digest_pdf(path, file.metadata().map(|m| m.len()).unwrap_or_default()),
+		metadata: metadata_from_document_info(&document),
+		pages,
+		page_table: vec![],
+	})
+}
+
+/// Maps the document information dictionary of a PDF into media metadata. Returns `None`
+/// when none of the fields Stump uses are present.
+fn metadata_from_document_info(document: &PdfDocument) -> Option<MediaMetadata> {
+	let info = document.metadata();
+
+	let get = |tag: PdfDocumentMetadataTagType| {
+		info.get(tag)
+			.map(|tag| tag.value().trim().to_string())
+			.filter(|value| !value.is_empty())
+	};
+
+	let (year, month, day) = match get(PdfDocumentMetadataTagType::CreationDate) {
+		Some(date) => parse_pdf_date(&date),
+		None => (None, None, None),
+	};
+
+	let metadata = MediaMetadata {
+		title: get(PdfDocumentMetadataTagType::Title),
+		writer: get(PdfDocumentMetadataTagType::Author),
+		summary: get(PdfDocumentMetadataTagType::Subject),
+		tags: get(PdfDocumentMetadataTagType::Keywords),
+		year,
+		month,
+		day,
+		..Default::default()
+	};
+
+	match metadata == MediaMetadata::default() {
+		true => None,
+		false => Some(metadata),
+	}
+}
+
+/// PDF dates are formatted as D:YYYYMMDDHHmmSSOHH'mm', where everything after the year is
+/// optional. ex: "D:20180501120000+02'00'"
+fn parse_pdf_date(date: &str) -> (Option<i32>, Option<i32>, Option<i32>) {
+	let date = date.strip_prefix("D:").unwrap_or(date);
+
+	let part = |range: std::ops::Range<usize>| {
+		date.get(range).and_then(|part| part.parse::<i32>().ok())
+	};
+
+	let year = part(0..4);
+	let month = part(4..6).filter(|month| (1..=12).contains(month));
+	let day = part(6..8).filter(|day| (1..=31).contains(day));
+
+	(year, month, day)
+}
+
+/// Renders a page (starting at 1) of a PDF to a JPEG.
+pub fn get_pdf_page(file: &str, page: i32) -> GetPageResult {
+	let pdfium = load_pdfium()?;
+	let document = load_pdf(&pdfium, file)?;
+
+	let index = match u16::try_from(page - 1) {
+		Ok(index) if index < document.pages().len() => index,
+		_ => {
+			log::error!("Could not find page {} in pdf file {}", page, file);
+			return Err(ProcessFileError::NoImageError);
+		},
+	};
+
+	let render_config = PdfRenderConfig::new()
+		.set_target_width(RENDER_WIDTH)
+		.set_maximum_height(RENDER_MAX_HEIGHT);
+
+	let image = document
+		.pages()
+		.get(index)
+		.and_then(|page| page.render_with_config(&render_config))
+		.map_err(|e| ProcessFileError::PdfReadError(format!("{:?}", e)))?
+		.as_image();
+
+	// Pages are rendered with an alpha channel, which JPEG doesn't support.
+	let mut bytes = Vec::new();
+	image::DynamicImage::ImageRgb8(image.to_rgb8())
+		.write_to(
+			&mut Cursor::new(&mut bytes),
+			image::ImageOutputFormat::Jpeg(90),
+		)
+		.map_err(|e| ProcessFileError::PdfReadError(e.to_string()))?;
+
+	Ok((ContentType::JPEG, bytes))
+}
+
+/// Loads every page of a PDF, which parses it without rendering it.
+pub fn verify_pdf(file: &str) -> Result<(), ProcessFileError> {
+	let pdfium = load_pdfium()?;
+	let document = load_pdf(&pdfium, file)?;
+
+	let count = document.pages().len();
+
+	if count == 0 {
+		return Err(ProcessFileError::NoImageError);
+	}
+
+	for index in 0..count {
+		document.pages().get(index).map_err(|e| {
+			ProcessFileError::CorruptPageError(
+				format!("Page {}", index + 1),
+				format!("{:?}", e),
+			)
+		})?;
+	}
+
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn parses_pdf_dates() {
+		assert_eq!(
+			parse_pdf_date("D:20180501120000+02'00'"),
+			(Some(2018), Some(5), Some(1))
+		);
+		assert_eq!(parse_pdf_date("D:2018"), (Some(2018), None, None));
+		assert_eq!(parse_pdf_date("201813"), (Some(2018), None, None));
+		assert_eq!(parse_pdf_date(""), (None, None, None));
+	}
 }
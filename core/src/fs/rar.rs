@@ -1,104 +1,111 @@
-use rocket::http::ContentType;
+use std::collections::HashSet;
 use unrar::archive::Entry;
 use walkdir::DirEntry;
 
 use crate::{
 	fs::media_file::{self, GetPageResult, IsImage},
-	types::{alias::ProcessResult, errors::ProcessFileError, models::ProcessedMediaFile},
+	types::{
+		alias::ProcessResult,
+		errors::ProcessFileError,
+		models::{metadata::MediaMetadata, ProcessedMediaFile},
+	},
 };
 
-use super::checksum;
+use super::{
+	checksum,
+	page_index::{PageImage, PageIndex},
+};
 
 impl IsImage for Entry {
 	fn is_image(&self) -> bool {
-		if self.is_file() {
-			let file_name = self.filename.as_path().to_string_lossy().to_lowercase();
-			return file_name.ends_with(".jpg")
-				|| file_name.ends_with(".jpeg")
-				|| file_name.ends_with(".png");
-		}
-
-		false
+		self.is_file()
+			&& media_file::is_image_file_name(&self.filename.as_path().to_string_lossy())
 	}
 }
 
-pub fn convert_to_cbr() {
-	unimplemented!()
-}
+/// Reads the ComicInfo.xml of a rar, and builds the page index from its images.
+fn read_rar_pages(
+	path: &str,
+) -> Result<(Option<MediaMetadata>, PageIndex), ProcessFileError> {
+	let archive =
+		unrar::Archive::new(&path).map_err(|_| ProcessFileError::RarOpenError)?;
+
+	let mut comic_info = None;
+	let mut images = Vec::new();
+
+	let entries = archive
+		.list_extract()
+		.map_err(|_| ProcessFileError::RarOpenError)?;
+
+	for entry in entries {
+		let mut entry = entry.map_err(|_| ProcessFileError::RarReadError)?;
+		let filename = entry.filename.to_string_lossy().to_string();
+
+		if filename.eq("ComicInfo.xml") {
+			// FIXME: `read_bytes` needs more refactoring on my unrar fork, so a ComicInfo.xml
+			// which can't be read is treated as missing. One which isn't UTF-8 is read as
+			// best it can be, and is only missing if it then fails to parse.
+			if let Ok(bytes) = entry.read_bytes() {
+				comic_info = media_file::process_comic_info(
+					String::from_utf8_lossy(&bytes).into_owned(),
+				);
+			}
+		} else if entry.is_file() {
+			// Entries are sniffed rather than trusted by name, same as zips. Unlike a zip
+			// entry though, a rar entry can only be read by extracting it in full.
+			let size = entry.unpacked_size as u64;
+
+			match entry.read_bytes() {
+				Ok(bytes) => {
+					let header_len =
+						bytes.len().min(media_file::IMAGE_HEADER_LEN as usize);
+					let header = &bytes[..header_len];
+
+					if media_file::is_image_content(&filename, header) {
+						images.push(PageImage::new(filename, size, header));
+					}
+				},
+				// The entry can't be sniffed, so its name is all there is to go by.
+				Err(_) if entry.is_image() => {
+					images.push(PageImage::new(filename, size, &[]));
+				},
+				Err(_) => {},
+			}
+		}
+	}
 
-// TODO: fix error handling after rar changes
+	let pages = PageIndex::new(images, comic_info.as_ref());
 
-/// Processes a rar file in its entirety. Will return a tuple of the comic info and the list of
-/// files in the rar.
+	Ok((comic_info, pages))
+}
+
+/// Processes a rar file in its entirety. Will return the comic info and the number of pages
+/// in the rar.
 pub fn process_rar(file: &DirEntry) -> ProcessResult {
 	info!("Processing Rar: {}", file.path().display());
 
 	let path = file.path().to_string_lossy().to_string();
-	let archive = unrar::Archive::new(&path).unwrap();
-
-	// let mut entries: Vec<String> = Vec::new();
-	let mut pages = 0;
-
-	let mut metadata_buf = Vec::<u8>::new();
-
-	let checksum = digest_rar(&path);
-
-	match archive.list_extract() {
-		Ok(open_archive) => {
-			for entry in open_archive {
-				match entry {
-					Ok(mut e) => {
-						let filename = e.filename.to_string_lossy().to_string();
-
-						if filename.eq("ComicInfo.xml") {
-							// FIXME: this won't work. `read_bytes` needs more refactoring.
-							metadata_buf = match e.read_bytes() {
-								Ok(b) => b,
-								Err(_e) => {
-									// error!("Error reading metadata: {}", e);
-									// todo!()
-									vec![]
-								},
-							}
-						} else {
-							pages += 1;
-						}
-
-						// entries.push(filename);
-					},
-					Err(_e) => return Err(ProcessFileError::RarReadError),
-				}
-			}
-		},
-		Err(_e) => return Err(ProcessFileError::RarOpenError),
-	};
+
+	let (comic_info, pages) = read_rar_pages(&path)?;
 
 	Ok(ProcessedMediaFile {
-		checksum,
-		metadata: media_file::process_comic_info(
-			std::str::from_utf8(&metadata_buf)?.to_owned(),
-		),
-		pages,
+		checksum: digest_rar(&path),
+		metadata: comic_info,
+		pages: pages.len(),
+		page_table: pages.into_images(),
 	})
-
-	// if metadata_buf.is_empty() {
-	// 	Ok((None, entries))
-	// } else {
-	// 	Ok((
-	// 		media_file::process_comic_info(
-	// 			std::str::from_utf8(&metadata_buf)?.to_owned(),
-	// 		),
-	// 		entries,
-	// 	))
-	// }
 }
 
 pub fn digest_rar(file: &str) -> Option<String> {
-	let archive = unrar::Archive::new(&file).unwrap();
+	let entries = match unrar::Archive::new(&file).map(|archive| archive.list()) {
+		Ok(Ok(entries)) => entries,
+		_ => {
+			log::error!("Failed to digest rar {}, unable to open the archive", file);
+			return None;
+		},
+	};
 
-	let entries: Vec<_> = archive
-		.list()
-		.unwrap()
+	let entries: Vec<_> = entries
 		.filter_map(|e| e.ok())
 		.filter(|e| e.is_image())
 		.collect();
@@ -118,48 +125,85 @@ pub fn digest_rar(file: &str) -> Option<String> {
 	}
 }
 
-// Note: I am sorting by filename after opening, *however* this really is *not* ideal.
-// If the files were to have any other naming scheme that would be a problem. Is this a problem?
 // TODO: I have to solve the `read_bytes` issue on my unrar fork. For now, I am leaving this very unideal
 // solution in place. OpenArchive gets consumed by the iterator, and so when the iterator is done, the
 // OpenArchive handle stored in Entry is no more. That's why I create another archive to grab what I want before
 // the iterator is done. At least, I *think* that is what is happening.
 // Fix location: https://github.com/aaronleopold/unrar.rs/tree/aleopold--read-bytes
 pub fn get_rar_image(file: &str, page: i32) -> GetPageResult {
-	let archive = unrar::Archive::new(file).unwrap();
+	let (_, pages) = read_rar_pages(file)?;
+
+	let name = match pages.get(page) {
+		Some(name) => name,
+		None => {
+			log::error!(
+				"Could not find image for page {} in rar file {}",
+				page,
+				file
+			);
+			return Err(ProcessFileError::NoImageError);
+		},
+	};
+
+	let bytes = read_rar_image(file, name)?;
+
+	Ok((media_file::image_content_type(name, &bytes), bytes))
+}
+
+/// Reads an image from a rar file by its name, as stored in the page table of the book.
+pub fn read_rar_image(file: &str, name: &str) -> Result<Vec<u8>, ProcessFileError> {
+	let archive =
+		unrar::Archive::new(file).map_err(|_| ProcessFileError::RarOpenError)?;
 
-	let mut entries: Vec<_> = archive
+	let entry = archive
 		.list_extract()
-		.unwrap()
+		.map_err(|_| ProcessFileError::RarOpenError)?
 		.filter_map(|e| e.ok())
-		.filter(|e| e.is_image())
-		.collect();
+		.find(|e| e.filename.to_string_lossy() == name);
+
+	match entry {
+		Some(mut entry) => entry
+			.read_bytes()
+			.map_err(|_| ProcessFileError::RarReadError),
+		None => Err(ProcessFileError::NoImageError),
+	}
+}
 
-	entries.sort_by(|a, b| a.filename.cmp(&b.filename));
+/// Extracts every page of a rar, which also checks the CRC of each, and decodes the header of
+/// each image.
+pub fn verify_rar(file: &str) -> Result<(), ProcessFileError> {
+	let (_, pages) = read_rar_pages(file)?;
 
-	let entry = entries.into_iter().nth((page - 1) as usize).unwrap();
+	if pages.is_empty() {
+		return Err(ProcessFileError::NoImageError);
+	}
+
+	let names = pages.iter().collect::<HashSet<&str>>();
 
-	let archive = unrar::Archive::new(file).unwrap();
+	let archive =
+		unrar::Archive::new(file).map_err(|_| ProcessFileError::RarOpenError)?;
 
-	let bytes = archive
+	let entries = archive
 		.list_extract()
-		.unwrap()
-		.filter_map(|e| e.ok())
-		.filter(|e| e.filename == entry.filename)
-		.nth(0)
-		.unwrap()
-		.read_bytes()
-		.unwrap();
-
-	// if try_webp {
-	// 	match utils::webp::webp_from_bytes(&bytes) {
-	// 		Ok(webp_bytes) => return Ok((ContentType::WEBP, webp_bytes)),
-	// 		Err(e) => {
-	// 			log::error!("Error converting to webp: {}", e);
-	// 			// Err(GetPageError::WebpError)
-	// 		},
-	// 	};
-	// }
-
-	Ok((ContentType::JPEG, bytes))
+		.map_err(|_| ProcessFileError::RarOpenError)?;
+
+	for entry in entries {
+		let mut entry = entry.map_err(|_| ProcessFileError::RarReadError)?;
+		let name = entry.filename.to_string_lossy().to_string();
+
+		if !names.contains(name.as_str()) {
+			continue;
+		}
+
+		let bytes = entry.read_bytes().map_err(|_| {
+			ProcessFileError::CorruptPageError(
+				name.clone(),
+				"Could not be extracted".to_string(),
+			)
+		})?;
+
+		media_file::verify_image(&name, &bytes)?;
+	}
+
+	Ok(())
 }
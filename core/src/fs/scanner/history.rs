@@ -0,0 +1,230 @@
+use std::sync::{
+	atomic::{AtomicU64, Ordering},
+	Arc, Mutex,
+};
+
+use prisma_client_rust::{
+	chrono::{DateTime, FixedOffset, Utc},
+	raw, PrismaValue, Raw,
+};
+
+use crate::{
+	config::context::Context,
+	prisma::{library, library_scan, PrismaClient},
+	types::{enums::ScanTrigger, errors::ApiError},
+};
+
+/// The number of columns written for each scan error.
+const SCAN_ERROR_INSERT_COLUMNS: usize = 4;
+/// The number of scan errors written in a single statement. Every column is a
+/// parameter, and SQLite only allows 999 parameters in a statement.
+const SCAN_ERROR_BATCH_SIZE: usize = 999 / SCAN_ERROR_INSERT_COLUMNS;
+
+/// Counts what a scan did while it runs. Shared between the walk, the workers and the
+/// writer, so everything is atomic.
+#[derive(Debug, Default)]
+pub struct ScanStats {
+	files_seen: AtomicU64,
+	media_created: AtomicU64,
+	media_updated: AtomicU64,
+	media_missing: AtomicU64,
+	errors: Mutex<Vec<(String, String)>>,
+}
+
+impl ScanStats {
+	pub fn file_seen(&self) {
+		self.files_seen.fetch_add(1, Ordering::SeqCst);
+	}
+
+	pub fn media_created(&self, count: usize) {
+		self.media_created.fetch_add(count as u64, Ordering::SeqCst);
+	}
+
+	pub fn media_updated(&self, count: usize) {
+		self.media_updated.fetch_add(count as u64, Ordering::SeqCst);
+	}
+
+	pub fn media_missing(&self, count: usize) {
+		self.media_missing.fetch_add(count as u64, Ordering::SeqCst);
+	}
+
+	/// Records a file the scan could not process.
+	pub fn error(&self, path: impl Into<String>, message: impl ToString) {
+		if let Ok(mut errors) = self.errors.lock() {
+			errors.push((path.into(), message.to_string()));
+		}
+	}
+
+	fn take_errors(&self) -> Vec<(String, String)> {
+		self.errors
+			.lock()
+			.map(|mut errors| std::mem::take(&mut *errors))
+			.unwrap_or_default()
+	}
+
+	fn count(value: &AtomicU64) -> i32 {
+		value.load(Ordering::SeqCst) as i32
+	}
+}
+
+/// Persists a `library_scan` for a single run of the scanner. The scan is created as
+/// RUNNING when it starts, and the statistics are written once it finishes. Failing to
+/// record the history never fails the scan itself.
+pub struct ScanRecorder {
+	scan_id: Option<String>,
+	stats: Arc<ScanStats>,
+}
+
+impl ScanRecorder {
+	pub async fn start(
+		ctx: &Context,
+		library_id: String,
+		path: String,
+		trigger: ScanTrigger,
+	) -> Self {
+		let scan = ctx
+			.db
+			.library_scan()
+			.create(
+				library_scan::trigger::set(trigger.into()),
+				library_scan::path::set(path),
+				library_scan::library::link(library::id::equals(library_id)),
+				vec![],
+			)
+			.exec()
+			.await;
+
+		let scan_id = match scan {
+			Ok(scan) => Some(scan.id),
+			Err(e) => {
+				log::error!("Failed to record library scan: {:?}", e);
+				None
+			},
+		};
+
+		Self {
+			scan_id,
+			stats: Arc::new(ScanStats::default()),
+		}
+	}
+
+	/// Starts recording a scan of the library at `library_path`. Nothing is recorded if
+	/// there is no such library, the scan will fail on its own in that case.
+	pub async fn start_for_path(
+		ctx: &Context,
+		library_path: String,
+		trigger: ScanTrigger,
+	) -> Self {
+		let library = ctx
+			.db
+			.library()
+			.find_unique(library::path::equals(library_path.clone()))
+			.exec()
+			.await;
+
+		match library {
+			Ok(Some(library)) => {
+				Self::start(ctx, library.id, library_path, trigger).await
+			},
+			_ => Self {
+				scan_id: None,
+				stats: Arc::new(ScanStats::default()),
+			},
+		}
+	}
+
+	pub fn stats(&self) -> Arc<ScanStats> {
+		self.stats.clone()
+	}
+
+	/// Writes the statistics of the scan, along with the files which failed, and marks it
+	/// COMPLETED or FAILED depending on `result`.
+	pub async fn finish(self, ctx: &Context, result: &Result<(), ApiError>) {
+		let scan_id = match self.scan_id {
+			Some(scan_id) => scan_id,
+			None => return,
+		};
+
+		let stats = self.stats;
+
+		let (status, failure_reason) = match result {
+			Ok(_) => ("COMPLETED", None),
+			Err(e) => ("FAILED", Some(e.to_string())),
+		};
+
+		let completed_at: DateTime<FixedOffset> = Utc::now().into();
+
+		let updated = ctx
+			.db
+			.library_scan()
+			.find_unique(library_scan::id::equals(scan_id.clone()))
+			.update(vec![
+				library_scan::status::set(status.to_string()),
+				library_scan::failure_reason::set(failure_reason),
+				library_scan::files_seen::set(ScanStats::count(&stats.files_seen)),
+				library_scan::media_created::set(ScanStats::count(&stats.media_created)),
+				library_scan::media_updated::set(ScanStats::count(&stats.media_updated)),
+				library_scan::media_missing::set(ScanStats::count(&stats.media_missing)),
+				library_scan::completed_at::set(Some(completed_at)),
+			])
+			.exec()
+			.await;
+
+		if let Err(e) = updated {
+			log::error!("Failed to record library scan statistics: {:?}", e);
+		}
+
+		if let Err(e) = write_scan_errors(ctx, &scan_id, stats.take_errors()).await {
+			log::error!("Failed to record library scan errors: {:?}", e);
+		}
+	}
+}
+
+/// Writes the files a scan could not process, `SCAN_ERROR_BATCH_SIZE` at a time.
+async fn write_scan_errors(
+	ctx: &Context,
+	scan_id: &str,
+	errors: Vec<(String, String)>,
+) -> Result<(), ApiError> {
+	for errors in errors.chunks(SCAN_ERROR_BATCH_SIZE) {
+		let mut params = Vec::with_capacity(errors.len() * SCAN_ERROR_INSERT_COLUMNS);
+
+		for (path, message) in errors {
+			params.extend(vec![
+				PrismaValue::String(
+					cuid::cuid()
+						.map_err(|e| ApiError::InternalServerError(e.to_string()))?,
+				),
+				PrismaValue::String(path.clone()),
+				PrismaValue::String(message.clone()),
+				PrismaValue::String(scan_id.to_string()),
+			]);
+		}
+
+		let query = format!(
+			"INSERT INTO library_scan_errors (id, path, message, scanId) VALUES {}",
+			vec!["({}, {}, {}, {})"; errors.len()].join(", ")
+		);
+
+		ctx.db._execute_raw(Raw::new(&query, params)).await?;
+	}
+
+	Ok(())
+}
+
+/// Marks scans which are still RUNNING as FAILED. Only one server runs the scans, so
+/// when it starts up, any scan still RUNNING was cut off when it last stopped.
+pub async fn fail_interrupted_scans(db: &PrismaClient) -> Result<(), ApiError> {
+	let completed_at: DateTime<FixedOffset> = Utc::now().into();
+
+	db._execute_raw(raw!(
+		"UPDATE library_scans SET status={}, failureReason={}, completedAt={} WHERE status={}",
+		PrismaValue::String("FAILED".to_string()),
+		PrismaValue::String("The server stopped before the scan finished".to_string()),
+		PrismaValue::DateTime(completed_at),
+		PrismaValue::String("RUNNING".to_string())
+	))
+	.await?;
+
+	Ok(())
+}
@@ -1,27 +1,42 @@
-use prisma_client_rust::raw;
-use rocket::tokio::{self, task::JoinHandle};
+use rocket::tokio::{
+	self,
+	sync::{mpsc, Semaphore},
+	task::JoinHandle,
+};
 use std::{
-	collections::HashMap,
+	collections::{HashMap, HashSet},
 	path::Path,
 	sync::{
 		atomic::{AtomicU64, Ordering},
-		Arc, Mutex,
+		Arc,
 	},
 };
-use walkdir::WalkDir;
+use walkdir::{DirEntry, WalkDir};
 
 use crate::{
 	config::context::Context,
-	fs::scanner::ScannedFileTrait,
+	fs::{
+		folder,
+		scanner::{ScanOptions, ScannedFileTrait},
+	},
 	prisma::{library, media, series},
-	types::{errors::ApiError, event::ClientEvent},
+	types::{
+		enums::ScanTrigger,
+		errors::{ApiError, ProcessFileError},
+		event::ClientEvent,
+		models::library::LibraryLayoutMode,
+	},
 };
 
-use super::utils::mark_library_missing;
+use super::{
+	history::{ScanRecorder, ScanStats},
+	utils::{mark_library_missing, InsertedMedia, ProcessedMedia, MEDIA_BATCH_SIZE},
+};
 
 async fn precheck(
 	ctx: &Context,
 	path: String,
+	stats: &ScanStats,
 ) -> Result<(library::Data, Vec<series::Data>), ApiError> {
 	let db = ctx.get_db();
 
@@ -47,44 +62,181 @@ async fn precheck(
 		)));
 	}
 
-	let mut series = library.series()?.to_owned();
+	// The library path exists again, e.g. a network mount came back.
+	let mut library = library;
+
+	match super::utils::restore_library(ctx, &library).await {
+		Ok(Some(restored)) => {
+			// The update doesn't fetch relations, so the series already loaded are kept.
+			library.status = restored.status.clone();
+			let _ = ctx.emit_client_event(ClientEvent::UpdatedLibrary(restored));
+		},
+		Ok(None) => {},
+		Err(e) => {
+			log::error!("Failed to restore library: {:?}", e);
+		},
+	}
 
-	let series_map = series
-		.iter()
-		.map(|data| (data.path.as_str(), data.to_owned()).into())
-		.collect::<HashMap<&str, series::Data>>();
+	match super::utils::mark_vanished_media(ctx, &library.id).await {
+		Ok(count) => stats.media_missing(count),
+		Err(e) => {
+			log::error!("Failed to mark vanished media as MISSING: {:?}", e);
+		},
+	}
 
-	let new_entries = WalkDir::new(&path)
-		// I only allow depth of 1 because the top most directory will *always* be the series. Nested
-		// directories get 'folded' into the series represented by the top directory.
-		.max_depth(1)
-		.into_iter()
-		.filter_entry(|e| e.path().is_dir())
-		.filter_map(|e| e.ok())
-		.filter(|entry| {
-			let path = entry.path();
+	let mut series = Vec::new();
 
-			let path_str = path.as_os_str().to_string_lossy().to_string();
+	for s in library.series()?.to_owned() {
+		match super::utils::sync_series_status(ctx, &s).await {
+			Ok(Some(updated)) => {
+				let _ =
+					ctx.emit_client_event(ClientEvent::UpdatedSeries(updated.clone()));
+				series.push(updated);
+			},
+			Ok(None) => series.push(s),
+			Err(e) => {
+				log::error!("Failed to update series status: {:?}", e);
+				series.push(s);
+			},
+		}
+	}
 
-			// Only create series if there is media inside them, and if they aren't in
-			// the exisitng series map.
-			path.dir_has_media() && !series_map.contains_key(path_str.as_str())
+	let mut inserted_series = insert_new_series(
+		ctx,
+		&library,
+		&ScanOptions::from(&library),
+		&series,
+		Path::new(&path),
+	)
+	.await;
+
+	series.append(&mut inserted_series);
+
+	Ok((library, series))
+}
+
+/// Finds every entry which should be a series at, or nested under, `within` (a path in the
+/// library), according to the library's layout mode. Entries are either directories or, for
+/// one-shots, files in the library root. A directory is only a series if walking it as one
+/// would turn up media.
+pub(super) fn discover_series(options: &ScanOptions, within: &Path) -> Vec<DirEntry> {
+	let library_path = Path::new(&options.library_path);
+	let is_library_root = within == library_path;
+
+	let mut rules = options.ignore_rules();
+
+	let has_media = |entry: &DirEntry| {
+		options
+			.series_files(entry.path())
+			.any(|e| !e.path().should_ignore())
+	};
+
+	// With leaf folders, a folder book belongs to the series of the folder it is in. Only
+	// a folder book directly in the library root is a series of its own.
+	let is_nested_folder_book = |entry: &DirEntry| {
+		options.folder_books
+			&& options.layout_mode == LibraryLayoutMode::LeafFolders
+			&& entry.path().parent() != Some(library_path)
+			&& folder::is_folder_book(entry.path())
+	};
+
+	let walker = match options.layout_mode {
+		// The top most directory will be the series. Nested directories get 'folded' into
+		// the series represented by the top directory.
+		LibraryLayoutMode::TopLevelFolders => {
+			let top_level = within
+				.strip_prefix(library_path)
+				.ok()
+				.and_then(|relative| relative.components().next());
+
+			match top_level {
+				Some(top_level) => {
+					WalkDir::new(library_path.join(top_level)).max_depth(0)
+				},
+				None => WalkDir::new(library_path).min_depth(1).max_depth(1),
+			}
+		},
+		// Every directory with media directly inside of it is a series, regardless of how
+		// deeply it is nested.
+		LibraryLayoutMode::LeafFolders => {
+			WalkDir::new(within).min_depth(if is_library_root { 1 } else { 0 })
+		},
+	};
+
+	let mut entries: Vec<DirEntry> = walker
+		.follow_links(options.follow_symlinks)
+		.into_iter()
+		.filter_entry(|e| {
+			e.path().is_dir()
+				&& !e.path().is_invisible_file()
+				&& !rules.is_ignored(e.path(), true)
+				&& !is_nested_folder_book(e)
 		})
+		.filter_map(|e| e.ok())
+		.filter(has_media)
 		.collect();
 
-	// TODO: I need to check for missing series? maybe? UGH...
+	if options.root_files_as_one_shots {
+		let root_files = if is_library_root {
+			Some(WalkDir::new(library_path).min_depth(1).max_depth(1))
+		} else if within.parent() == Some(library_path) {
+			Some(WalkDir::new(within).max_depth(0))
+		} else {
+			None
+		};
 
-	let mut inserted_series =
-		super::utils::insert_series_many(ctx, new_entries, library.id.clone()).await;
+		if let Some(root_files) = root_files {
+			entries.extend(
+				root_files
+					.follow_links(options.follow_symlinks)
+					.into_iter()
+					.filter_map(|e| e.ok())
+					.filter(|e| {
+						let path = e.path();
+
+						path.is_file()
+							&& !rules.is_ignored(path, false)
+							&& !path.should_ignore() && !path.is_thumbnail_img()
+					}),
+			);
+		}
+	}
 
-	series.append(&mut inserted_series);
+	entries
+}
 
-	Ok((library, series))
+/// Creates any series discovered at, or nested under, `within` which don't exist yet.
+async fn insert_new_series(
+	ctx: &Context,
+	library: &library::Data,
+	options: &ScanOptions,
+	existing: &[series::Data],
+	within: &Path,
+) -> Vec<series::Data> {
+	let existing_paths = existing
+		.iter()
+		.map(|data| data.path.as_str())
+		.collect::<HashSet<&str>>();
+
+	let new_entries = discover_series(options, within)
+		.into_iter()
+		.filter(|entry| {
+			let path_str = entry.path().as_os_str().to_string_lossy().to_string();
+
+			// Only create series if they aren't in the exisitng series map.
+			!existing_paths.contains(path_str.as_str())
+		})
+		.collect();
+
+	super::utils::insert_series_many(ctx, new_entries, library.id.clone()).await
 }
 
 async fn scan_series(
 	ctx: Context,
 	series: series::Data,
+	options: ScanOptions,
+	rename_series: bool,
+	stats: Arc<ScanStats>,
 	mut on_progress: impl FnMut(String) + Send + Sync + 'static,
 ) {
 	let db = ctx.get_db();
@@ -101,11 +253,34 @@ async fn scan_series(
 		.map(|data| (data.path.clone(), false).into())
 		.collect::<HashMap<String, bool>>();
 
-	for entry in WalkDir::new(&series.path)
+	let existing_media = media
 		.into_iter()
-		.filter_map(|e| e.ok())
-		.filter(|e| e.path().is_file())
-	{
+		.map(|data| (data.path.clone(), data))
+		.collect::<HashMap<String, media::Data>>();
+
+	match super::utils::sync_series_thumbnail(&ctx, &series).await {
+		Ok(Some(updated)) => {
+			let _ = ctx.emit_client_event(ClientEvent::UpdatedSeries(updated));
+		},
+		Ok(None) => {},
+		Err(e) => {
+			log::error!("Failed to update series thumbnail: {:?}", e);
+		},
+	}
+
+	match super::utils::sync_series_details(&ctx, &series, rename_series).await {
+		Ok(Some(updated)) => {
+			let _ = ctx.emit_client_event(ClientEvent::UpdatedSeries(updated));
+		},
+		Ok(None) => {},
+		Err(e) => {
+			log::error!("Failed to update series details: {:?}", e);
+		},
+	}
+
+	let mut restored_media = vec![];
+
+	for entry in options.series_files(&series.path) {
 		let path = entry.path();
 		let path_str = path.to_str().unwrap_or("");
 
@@ -114,103 +289,182 @@ async fn scan_series(
 		// Tell client we are on the next file, this will increment the counter in the
 		// callback, as well.
 		on_progress(format!("Analyzing {:?}", path));
+		stats.file_seen();
 
 		if path.should_ignore() {
 			log::debug!("Skipping ignored file: {:?}", path);
 			continue;
-		} else if path.is_thumbnail_img() {
-			// TODO: these will *eventually* be supported, but not priority right now.
-			log::debug!(
-				"Stump does not support thumbnail image overrides yet ({:?}). Stay tuned!",
-				path
-			);
+		} else if path.is_img() {
+			// Images are only ever thumbnail overrides (cover.jpg, or a sidecar cover next to
+			// a book), which are picked up alongside the series and media they belong to.
+			log::debug!("Skipping image file: {:?}", path);
 			continue;
-		} else if let Some(_) = visited_media.get(path_str) {
-			log::debug!("Existing media found: {:?}", path);
+		} else if let Some(existing) = existing_media.get(path_str) {
 			*visited_media.entry(path_str.to_string()).or_insert(true) = true;
+
+			if !super::utils::file_has_changed(existing, &entry) {
+				log::debug!("Existing media found: {:?}", path);
+
+				if existing.status == "MISSING" {
+					restored_media.push(existing.clone());
+				}
+
+				// A sidecar cover may have been added or removed without touching the book.
+				match super::utils::sync_media_thumbnail(&ctx, existing).await {
+					Ok(Some(media)) => {
+						let _ = ctx.emit_client_event(ClientEvent::UpdatedMedia(media));
+					},
+					Ok(None) => {},
+					Err(e) => {
+						log::error!("Failed to update media thumbnail: {:?}", e);
+					},
+				}
+
+				continue;
+			}
+
+			log::debug!("Existing media has changed on disk: {:?}", path);
+
+			match super::utils::update_media(&ctx, &entry, existing.id.clone()).await {
+				Ok(media) => {
+					stats.media_updated(1);
+					let _ = ctx.emit_client_event(ClientEvent::UpdatedMedia(media));
+				},
+				Err(e) => {
+					log::error!("Failed to update media: {:?}", e);
+					stats.error(path_str, e);
+				},
+			}
+
 			continue;
 		}
 
 		log::debug!("New media found at {:?} in series {:?}", &path, &series.id);
 
 		match super::utils::insert_media(&ctx, &entry, series.id.clone()).await {
-			Ok(media) => {
-				visited_media.insert(media.path.clone(), true);
+			Ok(inserted) => {
+				visited_media.insert(inserted.media().path.clone(), true);
+
+				match inserted {
+					InsertedMedia::Created(_) => stats.media_created(1),
+					InsertedMedia::Relinked(_) => stats.media_updated(1),
+				}
 
 				// TODO: error handling...
-				let _ = ctx.emit_client_event(ClientEvent::CreatedMedia(media.clone()));
+				let _ = ctx.emit_client_event(inserted.into_event());
 			},
 			Err(e) => {
 				log::error!("Failed to insert media: {:?}", e);
+				stats.error(path_str, e);
 			},
 		}
 	}
 
+	stats.media_updated(restore_media(&ctx, restored_media).await);
+
+	// Media which were already MISSING are left alone, so they aren't counted twice.
 	let missing_media = visited_media
 		.into_iter()
-		.filter(|(_, visited)| !visited)
+		.filter(|(path, visited)| {
+			!visited
+				&& existing_media
+					.get(path)
+					.map_or(true, |media| media.status != "MISSING")
+		})
 		.map(|(path, _)| path)
 		.collect::<Vec<String>>();
 
 	if missing_media.len() > 0 {
 		log::info!("{} media in this series ({}) have not been found at the end of this series-level scan.", missing_media.len(), &series.id);
 		log::debug!("{:?}", missing_media);
-		// TODO: replace once batching is implemented -> https://github.com/Brendonovich/prisma-client-rust/issues/31
-		if let Err(e) = db
-			._execute_raw(raw!(format!(
-				"UPDATE media SET status=\"{}\" WHERE path in ({})",
-				"MISSING".to_string(),
-				missing_media
-					.into_iter()
-					.map(|path| format!("\"{}\"", path))
-					.collect::<Vec<_>>()
-					.join(",")
-			)
-			.as_str()))
-			.await
-		{
+
+		let missing_count = missing_media.len();
+
+		if let Err(e) = super::utils::mark_media_missing(&ctx, missing_media).await {
 			log::error!("Failed to mark missing media as MISSING: {:?}", e);
 		} else {
 			log::debug!("Marked missing media as MISSING.");
+			stats.media_missing(missing_count);
 		}
 	}
 }
 
-// FIXME: Okay, this made me sad lol. So with how this is currently I was averaging about
-// a 2x speedup over sync, which the is averaging a 1.52x speedup over my previous implementation.
-// The *major* snag I've hit with concurrent is it starts to cause timeouts and connection errors
-// to the database. I think there are just too many writers to the database file.
-//
-// I'm *definitely* not removing this, however I'm really hopeful that a
-// potential solution lies in https://github.com/Brendonovich/prisma-client-rust/issues/60.
-// Once transactions are supported, I think at the end of each series scan I can commit
-// the transaction to maybe reduce load on the sql file. For now, I'll use the sync
-// version...
-pub async fn scan_concurrent(
-	ctx: Context,
+/// Marks MISSING media whose files were found unchanged as READY again. Returns the number
+/// of media restored.
+async fn restore_media(ctx: &Context, restored_media: Vec<media::Data>) -> usize {
+	if restored_media.is_empty() {
+		return 0;
+	}
+
+	log::info!("{} missing media have come back", restored_media.len());
+
+	let paths = restored_media.iter().map(|m| m.path.clone()).collect();
+
+	if let Err(e) = super::utils::mark_media_ready(ctx, paths).await {
+		log::error!("Failed to mark restored media as READY: {:?}", e);
+		return 0;
+	}
+
+	let count = restored_media.len();
+
+	for mut media in restored_media {
+		media.status = "READY".to_string();
+		let _ = ctx.emit_client_event(ClientEvent::UpdatedMedia(media));
+	}
+
+	count
+}
+
+/// What the writer should do with a processed file.
+enum ScanWork {
+	Create { series_id: String },
+	Update { media_id: String },
+}
+
+/// A file processed by one of the blocking workers, on its way to the writer.
+struct ProcessedFile {
 	path: String,
-	_runner_id: String,
-) -> Result<(), ApiError> {
-	let (_library, series) = precheck(&ctx, path).await?;
+	work: ScanWork,
+	result: Result<ProcessedMedia, ProcessFileError>,
+}
 
-	let counter = Arc::new(Mutex::new(0));
+/// Reports scan progress to clients through `ClientEvent::job_progress`, and counts the file
+/// towards the scan's statistics. Cloned into the writer, since files finish both there and
+/// while walking the series.
+#[derive(Clone)]
+struct ScanProgress {
+	ctx: Context,
+	runner_id: String,
+	counter: Arc<AtomicU64>,
+	total: u64,
+	stats: Arc<ScanStats>,
+}
 
-	let start = std::time::Instant::now();
+impl ScanProgress {
+	fn tick(&self, message: String) {
+		self.stats.file_seen();
+
+		let current = self.counter.fetch_add(1, Ordering::SeqCst);
+
+		let _ = self.ctx.emit_client_event(ClientEvent::job_progress(
+			self.runner_id.to_owned(),
+			current,
+			self.total,
+			Some(message),
+		));
+	}
+}
 
-	let files_to_process: u64 = futures::future::join_all(
+async fn count_files_to_process(series: &[series::Data], options: &ScanOptions) -> u64 {
+	futures::future::join_all(
 		series
 			.iter()
 			.map(|data| {
 				let path = data.path.clone();
+				let options = options.clone();
 
 				tokio::task::spawn_blocking(move || {
-					WalkDir::new(&path)
-						.into_iter()
-						// FIXME: why won't this work??
-						// .filter_entry(|e| e.path().is_file())
-						.filter_map(|e| e.ok())
-						.filter(|e| e.path().is_file())
-						.count() as u64
+					options.series_files(&path).count() as u64
 				})
 			})
 			.collect::<Vec<JoinHandle<u64>>>(),
@@ -218,82 +472,528 @@ pub async fn scan_concurrent(
 	.await
 	.into_iter()
 	.filter_map(|res| res.ok())
-	.sum();
+	.sum()
+}
+
+/// The number of files processed at once. Processing is mostly IO and decompression, so
+/// there is no point in going wider than the machine.
+fn worker_count() -> usize {
+	std::thread::available_parallelism()
+		.map(|count| count.get())
+		.unwrap_or(4)
+}
+
+/// Scans a library with every new or changed file processed on a bounded pool of blocking
+/// workers. Running a writer per series overwhelmed SQLite with concurrent writers, so the
+/// processed files instead funnel into a single writer which inserts new media in batches.
+pub async fn scan_concurrent(
+	ctx: Context,
+	path: String,
+	runner_id: String,
+	trigger: ScanTrigger,
+) -> Result<(), ApiError> {
+	let recorder = ScanRecorder::start_for_path(&ctx, path.clone(), trigger).await;
+
+	let result =
+		run_scan_concurrent(ctx.get_ctx(), path, runner_id, recorder.stats()).await;
+
+	recorder.finish(&ctx, &result).await;
+
+	result
+}
+
+async fn run_scan_concurrent(
+	ctx: Context,
+	path: String,
+	runner_id: String,
+	stats: Arc<ScanStats>,
+) -> Result<(), ApiError> {
+	let (library, series) = precheck(&ctx, path, &stats).await?;
+	let options = ScanOptions::from(&library);
+
+	let start = std::time::Instant::now();
+
+	let files_to_process = count_files_to_process(&series, &options).await;
 
 	let duration = start.elapsed();
 
 	log::debug!(
-		"Files to process: {:?} (calculated in {}.{:03} seconds)",
+		"Files to process: {} (calculated in {}.{:03} seconds)",
 		files_to_process,
 		duration.as_secs(),
 		duration.subsec_millis()
 	);
 
-	let tasks: Vec<JoinHandle<()>> = series
-		.into_iter()
-		// .cloned()
-		.map(|s| {
-			let ctx_cpy = ctx.get_ctx();
-			// let r_id = runner_id.clone();
+	let _ = ctx.emit_client_event(ClientEvent::job_started(
+		runner_id.clone(),
+		0,
+		files_to_process,
+		Some(format!("Starting library scan at {}", &library.path)),
+	));
+
+	let progress = ScanProgress {
+		ctx: ctx.get_ctx(),
+		runner_id,
+		counter: Arc::new(AtomicU64::new(0)),
+		total: files_to_process,
+		stats: stats.clone(),
+	};
 
-			let counter_ref = counter.clone();
+	let (tx, rx) = mpsc::channel::<ProcessedFile>(MEDIA_BATCH_SIZE * 2);
+	let writer = tokio::spawn(write_processed_files(ctx.get_ctx(), rx, progress.clone()));
 
-			tokio::spawn(async move {
-				scan_series(ctx_cpy.get_ctx(), s, move |_msg| {
-					let mut shared = counter_ref.lock().unwrap();
+	let workers = Arc::new(Semaphore::new(worker_count()));
 
-					*shared += 1;
+	let mut missing_media = vec![];
+	let mut restored_media = vec![];
 
-					// log::debug!("{:?} - {:?}", msg, shared);
+	let rename_series = super::utils::should_rename_series(&ctx).await;
+
+	for s in series {
+		match super::utils::sync_series_thumbnail(&ctx, &s).await {
+			Ok(Some(updated)) => {
+				let _ = ctx.emit_client_event(ClientEvent::UpdatedSeries(updated));
+			},
+			Ok(None) => {},
+			Err(e) => {
+				log::error!("Failed to update series thumbnail: {:?}", e);
+			},
+		}
+
+		match super::utils::sync_series_details(&ctx, &s, rename_series).await {
+			Ok(Some(updated)) => {
+				let _ = ctx.emit_client_event(ClientEvent::UpdatedSeries(updated));
+			},
+			Ok(None) => {},
+			Err(e) => {
+				log::error!("Failed to update series details: {:?}", e);
+			},
+		}
 
-					// let _ = ctx_cpy.job_progress(ClientEvent::job_progress(
-					// 	"runnerid".to_string(),
-					// 	counter as u64,
-					// 	files_to_process,
-					// 	Some(msg),
-					// ));
+		let mut existing_media = ctx
+			.db
+			.media()
+			.find_many(vec![media::series_id::equals(Some(s.id.clone()))])
+			.exec()
+			.await?
+			.into_iter()
+			.map(|data| (data.path.clone(), data))
+			.collect::<HashMap<String, media::Data>>();
+
+		for entry in options.series_files(&s.path) {
+			let path = entry.path();
+			let path_str = path.to_string_lossy().to_string();
+
+			let work = if path.should_ignore() {
+				log::debug!("Skipping ignored file: {:?}", path);
+				None
+			} else if path.is_img() {
+				log::debug!("Skipping image file: {:?}", path);
+				None
+			} else if let Some(existing) = existing_media.remove(&path_str) {
+				if super::utils::file_has_changed(&existing, &entry) {
+					Some(ScanWork::Update {
+						media_id: existing.id,
+					})
+				} else {
+					if existing.status == "MISSING" {
+						restored_media.push(existing.clone());
+					}
+
+					match super::utils::sync_media_thumbnail(&ctx, &existing).await {
+						Ok(Some(media)) => {
+							let _ =
+								ctx.emit_client_event(ClientEvent::UpdatedMedia(media));
+						},
+						Ok(None) => {},
+						Err(e) => {
+							log::error!("Failed to update media thumbnail: {:?}", e);
+						},
+					}
+
+					None
+				}
+			} else {
+				Some(ScanWork::Create {
+					series_id: s.id.clone(),
 				})
-				.await;
-			})
-		})
-		.collect();
+			};
+
+			let work = match work {
+				Some(work) => work,
+				None => {
+					progress.tick(format!("Analyzing {:?}", path));
+					continue;
+				},
+			};
+
+			// Waiting on a permit keeps the walk from racing ahead of the workers.
+			let permit = workers.clone().acquire_owned().await.unwrap();
+			let tx = tx.clone();
+
+			tokio::task::spawn_blocking(move || {
+				let result = ProcessedMedia::from_entry(&entry);
+
+				let _ = tx.blocking_send(ProcessedFile {
+					path: path_str,
+					work,
+					result,
+				});
+
+				drop(permit);
+			});
+		}
+
+		// Whatever is left over was not found on disk during the walk. Media which were
+		// already MISSING are left alone, so they aren't counted twice.
+		missing_media.extend(
+			existing_media
+				.into_values()
+				.filter(|media| media.status != "MISSING")
+				.map(|media| media.path),
+		);
+	}
+
+	// The writer finishes once every worker has dropped its sender.
+	drop(tx);
+
+	if let Err(e) = writer.await {
+		log::error!("Scan writer failed: {:?}", e);
+	}
+
+	stats.media_updated(restore_media(&ctx, restored_media).await);
 
-	futures::future::join_all(tasks).await;
+	if !missing_media.is_empty() {
+		let missing_count = missing_media.len();
+
+		log::info!(
+			"{} media in library {} have not been found at the end of the scan.",
+			missing_count,
+			&library.id
+		);
+
+		if let Err(e) = super::utils::mark_media_missing(&ctx, missing_media).await {
+			log::error!("Failed to mark missing media as MISSING: {:?}", e);
+		} else {
+			stats.media_missing(missing_count);
+		}
+	}
 
 	Ok(())
 }
 
+/// The single writer for `scan_concurrent`. Updates and relinks are written as they come in,
+/// while new media are collected and inserted `MEDIA_BATCH_SIZE` at a time.
+async fn write_processed_files(
+	ctx: Context,
+	mut rx: mpsc::Receiver<ProcessedFile>,
+	progress: ScanProgress,
+) {
+	let stats = progress.stats.clone();
+	let mut batch = Vec::with_capacity(MEDIA_BATCH_SIZE);
+
+	while let Some(file) = rx.recv().await {
+		progress.tick(format!("Analyzing {:?}", file.path));
+
+		let processed = match file.result {
+			Ok(processed) => processed,
+			Err(e) => {
+				log::error!("Failed to process {}: {:?}", file.path, e);
+				stats.error(file.path, e);
+				continue;
+			},
+		};
+
+		match file.work {
+			ScanWork::Update { media_id } => {
+				match super::utils::update_processed_media(&ctx, processed, media_id)
+					.await
+				{
+					Ok(media) => {
+						stats.media_updated(1);
+						let _ = ctx.emit_client_event(ClientEvent::UpdatedMedia(media));
+					},
+					Err(e) => {
+						log::error!("Failed to update media: {:?}", e);
+						stats.error(file.path, e);
+					},
+				}
+			},
+			ScanWork::Create { series_id } => {
+				match super::utils::relink_media(&ctx, &processed, series_id.clone())
+					.await
+				{
+					Ok(Some(media)) => {
+						stats.media_updated(1);
+						let _ = ctx.emit_client_event(ClientEvent::UpdatedMedia(media));
+					},
+					Ok(None) => batch.push((processed, series_id)),
+					Err(e) => {
+						log::error!("Failed to relink media: {:?}", e);
+						stats.error(file.path, e);
+					},
+				}
+			},
+		}
+
+		if batch.len() >= MEDIA_BATCH_SIZE {
+			flush_media_batch(&ctx, &stats, std::mem::take(&mut batch)).await;
+		}
+	}
+
+	flush_media_batch(&ctx, &stats, batch).await;
+}
+
+async fn flush_media_batch(
+	ctx: &Context,
+	stats: &ScanStats,
+	batch: Vec<(ProcessedMedia, String)>,
+) {
+	if batch.is_empty() {
+		return;
+	}
+
+	let paths = batch
+		.iter()
+		.map(|(processed, _)| processed.path.clone())
+		.collect::<Vec<String>>();
+
+	match super::utils::insert_media_many(ctx, batch).await {
+		Ok(created) => {
+			stats.media_created(created.len());
+
+			for media in created {
+				let _ = ctx.emit_client_event(ClientEvent::CreatedMedia(media));
+			}
+		},
+		Err(e) => {
+			log::error!("Failed to insert media batch: {:?}", e);
+
+			for path in paths {
+				stats.error(path, &e);
+			}
+		},
+	}
+}
+
 pub async fn scan_sync(
 	ctx: Context,
 	path: String,
 	runner_id: String,
+	trigger: ScanTrigger,
 ) -> Result<(), ApiError> {
-	let (library, series) = precheck(&ctx, path).await?;
+	let recorder = ScanRecorder::start_for_path(&ctx, path.clone(), trigger).await;
+
+	let result = match precheck(&ctx, path, &recorder.stats()).await {
+		Ok((library, series)) => {
+			let message = format!("Starting library scan at {}", &library.path);
+			let options = ScanOptions::from(&library);
+
+			scan_series_many(
+				ctx.get_ctx(),
+				series,
+				options,
+				runner_id,
+				message,
+				recorder.stats(),
+			)
+			.await;
 
-	let start = std::time::Instant::now();
+			Ok(())
+		},
+		Err(e) => Err(e),
+	};
 
-	let files_to_process: u64 = futures::future::join_all(
-		series
-			.iter()
-			.map(|data| {
-				let path = data.path.clone();
+	recorder.finish(&ctx, &result).await;
 
-				tokio::task::spawn_blocking(move || {
-					WalkDir::new(&path)
-						.into_iter()
-						// FIXME: why won't this work??
-						// .filter_entry(|e| e.path().is_file())
-						.filter_map(|e| e.ok())
-						.filter(|e| e.path().is_file())
-						.count() as u64
-				})
-			})
-			.collect::<Vec<JoinHandle<u64>>>(),
+	result
+}
+
+/// Scans a single series, e.g. when only one series is known to have changed.
+pub async fn scan_single_series(
+	ctx: Context,
+	series_id: String,
+	runner_id: String,
+	trigger: ScanTrigger,
+) -> Result<(), ApiError> {
+	let series = ctx
+		.db
+		.series()
+		.find_unique(series::id::equals(series_id.clone()))
+		.with(series::library::fetch())
+		.exec()
+		.await?;
+
+	let series = match series {
+		Some(series) => series,
+		None => {
+			return Err(ApiError::NotFound(format!(
+				"Series with id {} not found",
+				series_id
+			)))
+		},
+	};
+
+	let library = match series.library()? {
+		Some(library) => library.to_owned(),
+		None => {
+			return Err(ApiError::InternalServerError(format!(
+				"Series {} does not belong to a library",
+				series_id
+			)))
+		},
+	};
+
+	let recorder =
+		ScanRecorder::start(&ctx, library.id.clone(), series.path.clone(), trigger).await;
+
+	let mut series = series;
+
+	match super::utils::sync_series_status(&ctx, &series).await {
+		Ok(Some(updated)) => {
+			series.status = updated.status.clone();
+			let _ = ctx.emit_client_event(ClientEvent::UpdatedSeries(updated));
+		},
+		Ok(None) => {},
+		Err(e) => {
+			log::error!("Failed to update series status: {:?}", e);
+		},
+	}
+
+	let result = if !Path::new(&series.path).exists() {
+		Err(ApiError::InternalServerError(format!(
+			"Series path does not exist in fs: {}",
+			series.path
+		)))
+	} else {
+		mark_vanished_series(&ctx, vec![series.id.clone()], &recorder.stats()).await;
+
+		let message = format!("Starting series scan at {}", &series.path);
+
+		scan_series_many(
+			ctx.get_ctx(),
+			vec![series],
+			ScanOptions::from(&library),
+			runner_id,
+			message,
+			recorder.stats(),
+		)
+		.await;
+
+		Ok(())
+	};
+
+	recorder.finish(&ctx, &result).await;
+
+	result
+}
+
+/// Scans whichever series `path` belongs to, or every series nested under it, after
+/// resolving the library it is in. Series which don't exist yet are created. Scanning the
+/// library root is the same as scanning the whole library.
+pub async fn scan_path(
+	ctx: Context,
+	path: String,
+	runner_id: String,
+	trigger: ScanTrigger,
+) -> Result<(), ApiError> {
+	let target = Path::new(&path);
+
+	let libraries = ctx
+		.db
+		.library()
+		.find_many(vec![])
+		.with(library::series::fetch(vec![]))
+		.exec()
+		.await?;
+
+	// Libraries shouldn't be nested, but if they are the closest one wins.
+	let library = libraries
+		.into_iter()
+		.filter(|library| target.starts_with(&library.path))
+		.max_by_key(|library| library.path.len());
+
+	let library = match library {
+		Some(library) if target.exists() => library,
+		_ => {
+			return Err(ApiError::NotFound(format!(
+				"No library contains the path {}",
+				path
+			)))
+		},
+	};
+
+	if target == Path::new(&library.path) {
+		return scan_concurrent(ctx, library.path, runner_id, trigger).await;
+	}
+
+	let options = ScanOptions::from(&library);
+	let existing = library.series()?.to_owned();
+
+	let recorder =
+		ScanRecorder::start(&ctx, library.id.clone(), path.clone(), trigger).await;
+
+	let inserted = insert_new_series(&ctx, &library, &options, &existing, target).await;
+
+	let series = existing
+		.into_iter()
+		.chain(inserted.into_iter())
+		.filter(|s| {
+			let series_path = Path::new(&s.path);
+
+			target.starts_with(series_path) || series_path.starts_with(target)
+		})
+		.collect::<Vec<series::Data>>();
+
+	mark_vanished_series(
+		&ctx,
+		series.iter().map(|s| s.id.clone()).collect(),
+		&recorder.stats(),
 	)
-	.await
-	.into_iter()
-	.filter_map(|res| res.ok())
-	.sum();
+	.await;
+
+	let message = format!("Starting scan at {}", &path);
+
+	scan_series_many(
+		ctx.get_ctx(),
+		series,
+		options,
+		runner_id,
+		message,
+		recorder.stats(),
+	)
+	.await;
+
+	let result = Ok(());
+
+	recorder.finish(&ctx, &result).await;
+
+	result
+}
+
+/// Marks the media of the series whose files no longer exist as MISSING before they are
+/// scanned, so files moved or renamed within them are relinked rather than added anew.
+async fn mark_vanished_series(ctx: &Context, series_ids: Vec<String>, stats: &ScanStats) {
+	match super::utils::mark_vanished_series_media(ctx, series_ids).await {
+		Ok(count) => stats.media_missing(count),
+		Err(e) => {
+			log::error!("Failed to mark vanished media as MISSING: {:?}", e);
+		},
+	}
+}
+
+/// Scans each of the series one after another, reporting progress across all of them.
+async fn scan_series_many(
+	ctx: Context,
+	series: Vec<series::Data>,
+	options: ScanOptions,
+	runner_id: String,
+	message: String,
+	stats: Arc<ScanStats>,
+) {
+	let start = std::time::Instant::now();
+
+	let files_to_process = count_files_to_process(&series, &options).await;
 
 	let duration = start.elapsed();
 
@@ -308,31 +1008,38 @@ pub async fn scan_sync(
 		runner_id.clone(),
 		0,
 		files_to_process,
-		Some(format!("Starting library scan at {}", &library.path)),
+		Some(message),
 	));
 
 	let counter = Arc::new(AtomicU64::new(0));
 
+	let rename_series = super::utils::should_rename_series(&ctx).await;
+
 	for s in series {
 		let progress_ctx = ctx.get_ctx();
 		let r_id = runner_id.clone();
 
 		let counter_ref = counter.clone();
 
-		scan_series(ctx.get_ctx(), s, move |msg| {
-			let current = counter_ref.fetch_add(1, Ordering::SeqCst);
-
-			let _ = progress_ctx.emit_client_event(ClientEvent::job_progress(
-				r_id.to_owned(),
-				current,
-				files_to_process,
-				Some(msg),
-			));
-		})
+		scan_series(
+			ctx.get_ctx(),
+			s,
+			options.clone(),
+			rename_series,
+			stats.clone(),
+			move |msg| {
+				let current = counter_ref.fetch_add(1, Ordering::SeqCst);
+
+				let _ = progress_ctx.emit_client_event(ClientEvent::job_progress(
+					r_id.to_owned(),
+					current,
+					files_to_process,
+					Some(msg),
+				));
+			},
+		)
 		.await;
 	}
-
-	Ok(())
 }
 
 // Note: You can't really run these tests from the top module level, as you need to
@@ -346,7 +1053,7 @@ mod tests {
 	use crate::config::context::*;
 
 	// use crate::prisma::*;
-	use crate::types::errors::ApiError;
+	use crate::types::{enums::ScanTrigger, errors::ApiError};
 
 	#[tokio::test(flavor = "multi_thread")]
 	async fn scan_concurrent() -> Result<(), ApiError> {
@@ -357,6 +1064,7 @@ mod tests {
 			ctx,
 			"/Users/aaronleopold/Documents/Stump/Demo".to_string(),
 			"runner_id_concurrent".to_string(),
+			ScanTrigger::Manual,
 		)
 		.await?;
 		let duration = start.elapsed();
@@ -379,6 +1087,7 @@ mod tests {
 			ctx,
 			"/Users/aaronleopold/Documents/Stump/Demo".to_string(),
 			"runner_id_sync".to_string(),
+			ScanTrigger::Manual,
 		)
 		.await?;
 		let duration = start.elapsed();
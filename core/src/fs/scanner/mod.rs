@@ -1,16 +1,180 @@
-use std::path::Path;
+use std::{
+	path::{Path, PathBuf},
+	str::FromStr,
+};
 
+pub mod history;
 pub mod library;
+pub mod report;
+pub mod series_metadata;
+pub mod stumpignore;
 pub mod utils;
+pub mod watcher;
 
 use rocket::http::ContentType;
+use walkdir::{DirEntry, WalkDir};
 
-use crate::fs::media_file;
+use crate::{
+	fs::{folder, media_file},
+	prisma::library as library_model,
+	types::models::library::{parse_ignore_patterns, LibraryLayoutMode},
+};
+
+use self::stumpignore::IgnoreRules;
+
+/// The per-library options which determine how a library is walked and how its series are
+/// detected.
+#[derive(Debug, Clone)]
+pub struct ScanOptions {
+	pub library_path: String,
+	pub layout_mode: LibraryLayoutMode,
+	pub root_files_as_one_shots: bool,
+	pub follow_symlinks: bool,
+	pub ignore_patterns: Vec<String>,
+	pub folder_books: bool,
+}
+
+impl From<&library_model::Data> for ScanOptions {
+	fn from(library: &library_model::Data) -> Self {
+		ScanOptions {
+			library_path: library.path.clone(),
+			layout_mode: LibraryLayoutMode::from_str(&library.layout_mode)
+				.unwrap_or_default(),
+			root_files_as_one_shots: library.root_files_as_one_shots,
+			follow_symlinks: library.follow_symlinks,
+			ignore_patterns: parse_ignore_patterns(library.ignore_patterns.as_deref()),
+			folder_books: library.folder_books,
+		}
+	}
+}
+
+impl ScanOptions {
+	/// Creates a walker over the files belonging to the series at `path`. With leaf folders,
+	/// nested folders are their own series and so are not walked. One-shot series have a
+	/// file as their path, in which case the walker just yields that file.
+	pub fn series_walker<P: AsRef<Path>>(&self, path: P) -> WalkDir {
+		let walker = WalkDir::new(path).follow_links(self.follow_symlinks);
+
+		match self.layout_mode {
+			LibraryLayoutMode::TopLevelFolders => walker,
+			LibraryLayoutMode::LeafFolders => walker.max_depth(1),
+		}
+	}
+
+	/// Creates the ignore rules for the library, i.e. its ignore patterns and any
+	/// `.stumpignore` files.
+	pub fn ignore_rules(&self) -> IgnoreRules {
+		IgnoreRules::new(&self.library_path, &self.ignore_patterns)
+	}
+
+	/// Walks the files belonging to the series at `path`, skipping anything ignored. When
+	/// folder books are enabled, they are yielded as a single entry and the images inside
+	/// of them are not walked.
+	pub fn series_files<P: AsRef<Path>>(
+		&self,
+		path: P,
+	) -> impl Iterator<Item = DirEntry> + Send {
+		let mut rules = self.ignore_rules();
+		let folder_books = self.folder_books;
+
+		let mut walker = self
+			.series_walker(path)
+			.into_iter()
+			.filter_entry(move |e| !rules.is_ignored(e.path(), e.file_type().is_dir()));
+
+		std::iter::from_fn(move || loop {
+			let entry = match walker.next()? {
+				Ok(entry) => entry,
+				Err(_) => continue,
+			};
+
+			if entry.file_type().is_dir() {
+				if folder_books && folder::is_folder_book(entry.path()) {
+					walker.skip_current_dir();
+					return Some(entry);
+				}
+			} else if entry.path().is_file() {
+				return Some(entry);
+			}
+		})
+	}
+
+	/// Determines the path of the series a file in the library belongs to, without
+	/// touching the filesystem. Returns `None` when the file doesn't belong to any series,
+	/// e.g. a file in the library root when root files aren't one-shots.
+	pub fn series_path_for(&self, library_path: &str, path: &Path) -> Option<PathBuf> {
+		let relative = path.strip_prefix(library_path).ok()?;
+
+		let mut components = relative.components();
+		let first = components.next()?;
+
+		// If there is nothing after the first component, the file is in the library root.
+		if components.next().is_none() {
+			return match self.root_files_as_one_shots {
+				true => Some(path.to_path_buf()),
+				false => None,
+			};
+		}
+
+		match self.layout_mode {
+			LibraryLayoutMode::TopLevelFolders => {
+				Some(Path::new(library_path).join(first))
+			},
+			LibraryLayoutMode::LeafFolders => path.parent().map(|p| p.to_path_buf()),
+		}
+	}
+
+	/// Determines the path of the series a folder book belongs to. Unlike files, a folder
+	/// book directly in the library root is its own series, the same as any other folder.
+	pub fn series_path_for_folder_book(
+		&self,
+		library_path: &str,
+		path: &Path,
+	) -> Option<PathBuf> {
+		let relative = path.strip_prefix(library_path).ok()?;
+
+		if relative.components().count() == 1 {
+			return Some(path.to_path_buf());
+		}
+
+		self.series_path_for(library_path, path)
+	}
+}
+
+/// Why the scanner skipped over a file.
+#[derive(Debug, Clone, PartialEq)]
+pub enum IgnoreReason {
+	/// The file is hidden, i.e. a dotfile.
+	Hidden,
+	/// The file matched a `.stumpignore` file or one of the library's ignore patterns.
+	IgnoreRule,
+	/// The file is an image, which are only ever used as thumbnail overrides.
+	Image,
+	/// The type of the file could not be determined.
+	UnknownType,
+	/// The file is of a type Stump does not support. Holds the mime type.
+	UnsupportedType(String),
+}
+
+impl std::fmt::Display for IgnoreReason {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			IgnoreReason::Hidden => write!(f, "Hidden file"),
+			IgnoreReason::IgnoreRule => write!(f, "Matched an ignore pattern"),
+			IgnoreReason::Image => write!(f, "Image used as a thumbnail override"),
+			IgnoreReason::UnknownType => write!(f, "Unable to determine the file type"),
+			IgnoreReason::UnsupportedType(mime) => {
+				write!(f, "Unsupported file type ({})", mime)
+			},
+		}
+	}
+}
 
 pub trait ScannedFileTrait {
 	fn get_kind(&self) -> std::io::Result<Option<infer::Type>>;
 	fn is_invisible_file(&self) -> bool;
 	fn should_ignore(&self) -> bool;
+	fn ignore_reason(&self) -> Option<IgnoreReason>;
 	fn is_img(&self) -> bool;
 	fn is_thumbnail_img(&self) -> bool;
 	fn dir_has_media(&self) -> bool;
@@ -30,20 +194,28 @@ impl ScannedFileTrait for Path {
 	}
 
 	fn should_ignore(&self) -> bool {
+		self.ignore_reason().is_some()
+	}
+
+	fn ignore_reason(&self) -> Option<IgnoreReason> {
 		if self.is_invisible_file() {
 			log::debug!("Found hidden file: {}", self.display());
-			return true;
+			return Some(IgnoreReason::Hidden);
 		}
 
-		// if self.is_dir() {
-		// 	return false;
-		// }
+		// Folder books are the only directories the scanner handles as media.
+		if self.is_dir() {
+			return match folder::is_folder_book(self) {
+				true => None,
+				false => Some(IgnoreReason::UnknownType),
+			};
+		}
 
 		let kind = infer::get_from_path(self);
 
 		if kind.is_err() {
 			log::debug!("Could not infer file type for {:?}: {:?}", self, kind);
-			return true;
+			return Some(IgnoreReason::UnknownType);
 		}
 
 		let kind = kind.unwrap();
@@ -61,10 +233,10 @@ impl ScannedFileTrait for Path {
 						"Ignoring file with unknown mime type {}",
 						self.display()
 					);
-					return true;
+					return Some(IgnoreReason::UnsupportedType(mime.to_string()));
 				}
 
-				false
+				None
 
 				// match mime {
 				// 	"application/zip" => false,
@@ -79,7 +251,7 @@ impl ScannedFileTrait for Path {
 			},
 			None => {
 				log::debug!("Unable to infer file type: {:?}", self);
-				return true;
+				return Some(IgnoreReason::UnknownType);
 			},
 		}
 	}
@@ -136,3 +308,88 @@ impl ScannedFileTrait for Path {
 			.any(|f| !f.path().should_ignore())
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use std::path::{Path, PathBuf};
+
+	use super::ScanOptions;
+	use crate::types::models::library::LibraryLayoutMode;
+
+	fn options(
+		layout_mode: LibraryLayoutMode,
+		root_files_as_one_shots: bool,
+	) -> ScanOptions {
+		ScanOptions {
+			library_path: "/comics".to_string(),
+			layout_mode,
+			root_files_as_one_shots,
+			follow_symlinks: false,
+			ignore_patterns: vec![],
+			folder_books: true,
+		}
+	}
+
+	#[test]
+	fn series_path_is_top_level_folder() {
+		assert_eq!(
+			options(LibraryLayoutMode::TopLevelFolders, false).series_path_for(
+				"/comics",
+				Path::new("/comics/Spider-Man/Volume 1/Spider-Man #1.cbz")
+			),
+			Some(PathBuf::from("/comics/Spider-Man"))
+		);
+	}
+
+	#[test]
+	fn series_path_is_leaf_folder() {
+		assert_eq!(
+			options(LibraryLayoutMode::LeafFolders, false).series_path_for(
+				"/comics",
+				Path::new("/comics/Marvel/Spider-Man/Spider-Man #1.cbz")
+			),
+			Some(PathBuf::from("/comics/Marvel/Spider-Man"))
+		);
+	}
+
+	#[test]
+	fn folder_book_series_path() {
+		let options = options(LibraryLayoutMode::LeafFolders, false);
+
+		assert_eq!(
+			options.series_path_for_folder_book(
+				"/comics",
+				Path::new("/comics/Tower of God/Chapter 001")
+			),
+			Some(PathBuf::from("/comics/Tower of God"))
+		);
+		assert_eq!(
+			options.series_path_for_folder_book(
+				"/comics",
+				Path::new("/comics/Tower of God")
+			),
+			Some(PathBuf::from("/comics/Tower of God"))
+		);
+	}
+
+	#[test]
+	fn root_files_are_one_shots_when_enabled() {
+		let path = Path::new("/comics/Spider-Man #1.cbz");
+
+		assert_eq!(
+			options(LibraryLayoutMode::TopLevelFolders, false)
+				.series_path_for("/comics", path),
+			None
+		);
+		assert_eq!(
+			options(LibraryLayoutMode::LeafFolders, true)
+				.series_path_for("/comics", path),
+			Some(path.to_path_buf())
+		);
+		assert_eq!(
+			options(LibraryLayoutMode::TopLevelFolders, true)
+				.series_path_for("/manga", Path::new("/comics/Spider-Man/#1.cbz")),
+			None
+		);
+	}
+}
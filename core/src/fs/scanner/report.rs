@@ -0,0 +1,252 @@
+use prisma_client_rust::chrono::Utc;
+use rocket::tokio;
+use std::{
+	collections::{HashMap, HashSet},
+	path::Path,
+	sync::Mutex,
+};
+
+use crate::{
+	config::context::Context,
+	fs::{
+		folder,
+		scanner::{IgnoreReason, ScanOptions, ScannedFileTrait},
+	},
+	prisma::{library, media, series},
+	types::{
+		errors::ApiError,
+		models::scan::{FailedFile, RelinkedFile, ScanReport, SkippedFile},
+	},
+};
+
+use super::{
+	library::discover_series,
+	utils::{file_has_changed, moved_media_candidates, ProcessedMedia},
+};
+
+/// The most recent report generated for each library, by library id. Generating a report
+/// processes every new and changed file, which can take as long as a scan, so reports are
+/// generated by a `ScanReportJob` and kept here to be fetched once it is done.
+#[derive(Default)]
+pub struct ScanReports {
+	reports: Mutex<HashMap<String, ScanReport>>,
+}
+
+impl ScanReports {
+	pub fn get(&self, library_id: &str) -> Option<ScanReport> {
+		self.lock().get(library_id).cloned()
+	}
+
+	pub fn insert(&self, report: ScanReport) {
+		self.lock().insert(report.library_id.clone(), report);
+	}
+
+	/// Removes the report of a library, e.g. once the library is deleted.
+	pub fn remove(&self, library_id: &str) {
+		self.lock().remove(library_id);
+	}
+
+	fn lock(&self) -> std::sync::MutexGuard<HashMap<String, ScanReport>> {
+		self.reports
+			.lock()
+			.unwrap_or_else(|poisoned| poisoned.into_inner())
+	}
+}
+
+/// Walks the library exactly as a scan would, but only reports what the scan would do.
+/// Nothing is written to the database. Note that new and changed files are still processed,
+/// in order to report the files which would fail, so this is about as slow as a scan.
+pub async fn scan_report(
+	ctx: &Context,
+	library_id: String,
+) -> Result<ScanReport, ApiError> {
+	let library = ctx
+		.db
+		.library()
+		.find_unique(library::id::equals(library_id.clone()))
+		.with(library::series::fetch(vec![]).with(series::media::fetch(vec![])))
+		.exec()
+		.await?;
+
+	let library = match library {
+		Some(library) => library,
+		None => {
+			return Err(ApiError::NotFound(format!(
+				"Library with id {} not found",
+				library_id
+			)))
+		},
+	};
+
+	if !Path::new(&library.path).exists() {
+		return Err(ApiError::InternalServerError(format!(
+			"Library path does not exist in fs: {}",
+			library.path
+		)));
+	}
+
+	let options = ScanOptions::from(&library);
+	let series = library.series()?.to_owned();
+
+	// The walk and the processing are all blocking.
+	let walked_library_id = library_id.clone();
+	let (mut report, new_media) = tokio::task::spawn_blocking(move || {
+		walk_library(walked_library_id, options, series)
+	})
+	.await
+	.map_err(|e| ApiError::InternalServerError(e.to_string()))?;
+
+	// Files which look like a media whose file is gone would be relinked, rather than created,
+	// so the old path wouldn't be marked MISSING either. The scan marks those media MISSING
+	// before walking the library, so any candidate would be taken over.
+	for processed in new_media {
+		let candidates = moved_media_candidates(ctx, &library_id, &processed).await?;
+
+		if let Some(moved) = candidates.into_iter().next() {
+			let path = processed.path;
+			report.media_to_create.retain(|p| p != &path);
+			report.media_missing.retain(|p| p != &moved.path);
+			report.media_to_relink.push(RelinkedFile {
+				from: moved.path,
+				to: path,
+			});
+		}
+	}
+
+	Ok(report)
+}
+
+/// Returns the report, along with each new media so relinks can be looked up afterwards.
+fn walk_library(
+	library_id: String,
+	options: ScanOptions,
+	series: Vec<series::Data>,
+) -> (ScanReport, Vec<ProcessedMedia>) {
+	let mut report = ScanReport {
+		library_id,
+		generated_at: Utc::now().to_string(),
+		..Default::default()
+	};
+
+	let mut new_media = vec![];
+
+	let existing_paths = series
+		.iter()
+		.map(|s| s.path.clone())
+		.collect::<HashSet<String>>();
+
+	let mut series_paths = vec![];
+
+	for s in series {
+		if !Path::new(&s.path).exists() {
+			report.series_missing.push(s.path.clone());
+		}
+
+		let media = s
+			.media()
+			.map(|media| media.to_owned())
+			.unwrap_or_default()
+			.into_iter()
+			.map(|m| (m.path.clone(), m))
+			.collect::<HashMap<String, media::Data>>();
+
+		series_paths.push((s.path, media));
+	}
+
+	for entry in discover_series(&options, Path::new(&options.library_path)) {
+		let path = entry.path().to_string_lossy().to_string();
+
+		if !existing_paths.contains(&path) {
+			report.series_to_create.push(path.clone());
+			series_paths.push((path, HashMap::new()));
+		}
+	}
+
+	let mut rules = options.ignore_rules();
+
+	for (series_path, mut existing_media) in series_paths {
+		let mut walker = options.series_walker(&series_path).into_iter();
+
+		while let Some(entry) = walker.next() {
+			let entry = match entry {
+				Ok(entry) => entry,
+				Err(e) => {
+					report.failed.push(FailedFile {
+						path: e
+							.path()
+							.map(|p| p.to_string_lossy().to_string())
+							.unwrap_or_default(),
+						error: e.to_string(),
+					});
+					continue;
+				},
+			};
+
+			let path = entry.path();
+			let path_str = path.to_string_lossy().to_string();
+			let is_dir = entry.file_type().is_dir();
+
+			let reason = if rules.is_ignored(path, is_dir) {
+				Some(IgnoreReason::IgnoreRule)
+			} else if is_dir && options.folder_books && folder::is_folder_book(path) {
+				// The images inside are the pages of the book, not files of their own.
+				walker.skip_current_dir();
+				None
+			} else if !path.is_file() {
+				continue;
+			} else if path.is_img() {
+				Some(IgnoreReason::Image)
+			} else {
+				path.ignore_reason()
+			};
+
+			if let Some(reason) = reason {
+				report.skipped.push(SkippedFile {
+					path: path_str,
+					reason: reason.to_string(),
+				});
+
+				if is_dir {
+					walker.skip_current_dir();
+				}
+
+				continue;
+			}
+
+			let existing = existing_media.remove(&path_str);
+
+			if let Some(existing) = existing.as_ref() {
+				if !file_has_changed(existing, &entry) {
+					if existing.status == "MISSING" {
+						report.media_to_restore.push(path_str);
+					}
+
+					continue;
+				}
+			}
+
+			match ProcessedMedia::from_entry(&entry) {
+				Ok(processed) => match existing {
+					Some(_) => report.media_to_update.push(path_str),
+					None => {
+						report.media_to_create.push(path_str);
+						new_media.push(processed);
+					},
+				},
+				Err(e) => report.failed.push(FailedFile {
+					path: path_str,
+					error: e.to_string(),
+				}),
+			}
+		}
+
+		report.media_missing.extend(
+			existing_media
+				.into_values()
+				.filter(|m| m.status != "MISSING")
+				.map(|m| m.path),
+		);
+	}
+
+	(report, new_media)
+}
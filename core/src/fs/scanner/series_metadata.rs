@@ -0,0 +1,138 @@
+use serde::Deserialize;
+use std::path::Path;
+
+use crate::fs::media_file;
+
+/// The Mylar series metadata file, kept in the series folder.
+pub const SERIES_JSON_FILE: &str = "series.json";
+
+/// A loose ComicInfo.xml in the series folder, describing the series as a whole.
+pub const COMIC_INFO_FILE: &str = "ComicInfo.xml";
+
+/// The series metadata written by Mylar to `series.json`. Only the fields Stump uses are
+/// read. See https://github.com/mylar3/mylar3/wiki/series.json-schema-(version-1.0.2)
+#[derive(Debug, Default, Deserialize, PartialEq)]
+pub struct SeriesMetadata {
+	#[serde(default)]
+	pub name: Option<String>,
+	#[serde(default)]
+	pub description_text: Option<String>,
+	#[serde(default)]
+	pub description_formatted: Option<String>,
+	#[serde(default)]
+	pub publisher: Option<String>,
+	#[serde(
+		default,
+		deserialize_with = "crate::types::models::metadata::lenient_int"
+	)]
+	pub year: Option<i32>,
+	/// ex: "Continuing" or "Ended"
+	#[serde(default)]
+	pub status: Option<String>,
+	#[serde(
+		default,
+		deserialize_with = "crate::types::models::metadata::lenient_int"
+	)]
+	pub total_issues: Option<i32>,
+}
+
+impl SeriesMetadata {
+	pub fn description(&self) -> Option<String> {
+		self.description_text
+			.clone()
+			.or_else(|| self.description_formatted.clone())
+	}
+}
+
+#[derive(Deserialize)]
+struct SeriesJson {
+	metadata: SeriesMetadata,
+}
+
+/// Reads the `series.json` in the series folder, if there is one. A malformed file is logged
+/// and otherwise ignored.
+pub fn read_series_json(series_path: &Path) -> Option<SeriesMetadata> {
+	let path = series_path.join(SERIES_JSON_FILE);
+
+	if !path.is_file() {
+		return None;
+	}
+
+	let contents = match std::fs::read_to_string(&path) {
+		Ok(contents) => contents,
+		Err(e) => {
+			log::warn!("Failed to read {:?}: {}", path, e);
+			return None;
+		},
+	};
+
+	match rocket::serde::json::serde_json::from_str::<SeriesJson>(&contents) {
+		Ok(series_json) => Some(series_json.metadata),
+		Err(e) => {
+			log::warn!("Failed to parse {:?}: {}", path, e);
+			None
+		},
+	}
+}
+
+/// The series name from a loose ComicInfo.xml in the series folder, if there is one.
+pub fn read_comic_info_series_name(series_path: &Path) -> Option<String> {
+	let contents = std::fs::read_to_string(series_path.join(COMIC_INFO_FILE)).ok()?;
+
+	media_file::process_comic_info(contents).and_then(|metadata| metadata.series)
+}
+
+/// The name a series should be given when the server is set to rename series, preferring
+/// `series.json` over ComicInfo.xml.
+pub fn series_name(
+	series_path: &Path,
+	series_json: Option<&SeriesMetadata>,
+) -> Option<String> {
+	series_json
+		.and_then(|metadata| metadata.name.clone())
+		.or_else(|| read_comic_info_series_name(series_path))
+		.map(|name| name.trim().to_string())
+		.filter(|name| !name.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn parses_mylar_series_json() {
+		let json = r#"{
+			"version": "1.0.2",
+			"metadata": {
+				"type": "comicSeries",
+				"publisher": "Marvel",
+				"imprint": null,
+				"name": "Amazing Spider-Man",
+				"comicid": 112200,
+				"year": 2018,
+				"description_text": "Peter Parker's back, baby!",
+				"description_formatted": null,
+				"volume": 5,
+				"booktype": "Print",
+				"collects": null,
+				"total_issues": "93",
+				"publication_run": "July 2018 - December 2021",
+				"status": "Ended"
+			}
+		}"#;
+
+		let metadata = rocket::serde::json::serde_json::from_str::<SeriesJson>(json)
+			.unwrap()
+			.metadata;
+
+		assert_eq!(metadata.name.as_deref(), Some("Amazing Spider-Man"));
+		assert_eq!(metadata.publisher.as_deref(), Some("Marvel"));
+		assert_eq!(metadata.year, Some(2018));
+		assert_eq!(metadata.total_issues, Some(93));
+		assert_eq!(metadata.status.as_deref(), Some("Ended"));
+		assert_eq!(
+			metadata.description().as_deref(),
+			Some("Peter Parker's back, baby!")
+		);
+	}
+}
@@ -0,0 +1,138 @@
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use std::{
+	collections::HashMap,
+	path::{Path, PathBuf},
+};
+
+/// The name of the ignore files Stump looks for in a library. They use gitignore syntax,
+/// and apply to the folder they are in and everything nested under it.
+pub const STUMPIGNORE_FILE: &str = ".stumpignore";
+
+/// The ignore rules for a library: the patterns configured on the library itself, plus any
+/// `.stumpignore` files found in the library. The `.stumpignore` files are read lazily and
+/// cached, so a single instance should be reused for the duration of a walk.
+pub struct IgnoreRules {
+	root: PathBuf,
+	library_rules: Gitignore,
+	dir_rules: HashMap<PathBuf, Option<Gitignore>>,
+}
+
+impl IgnoreRules {
+	pub fn new(library_path: &str, patterns: &[String]) -> Self {
+		let mut builder = GitignoreBuilder::new(library_path);
+
+		for pattern in patterns {
+			if let Err(e) = builder.add_line(None, pattern) {
+				log::warn!("Invalid library ignore pattern {:?}: {}", pattern, e);
+			}
+		}
+
+		let library_rules = builder.build().unwrap_or_else(|e| {
+			log::error!("Failed to build library ignore patterns: {}", e);
+			Gitignore::empty()
+		});
+
+		IgnoreRules {
+			root: PathBuf::from(library_path),
+			library_rules,
+			dir_rules: HashMap::new(),
+		}
+	}
+
+	/// Checks whether `path` itself is ignored. Its parent directories are *not* checked, so
+	/// this is meant for walks which prune ignored directories (i.e. `filter_entry`).
+	pub fn is_ignored(&mut self, path: &Path, is_dir: bool) -> bool {
+		if self.library_rules.matched(path, is_dir).is_ignore() {
+			return true;
+		}
+
+		let parent = match path.parent() {
+			Some(parent) => parent,
+			None => return false,
+		};
+
+		// The closest .stumpignore wins, the same as with nested .gitignore files.
+		for dir in parent
+			.ancestors()
+			.take_while(|dir| dir.starts_with(&self.root))
+		{
+			if let Some(rules) = self.rules_for(dir) {
+				let matched = rules.matched(path, is_dir);
+
+				if matched.is_ignore() {
+					return true;
+				} else if matched.is_whitelist() {
+					return false;
+				}
+			}
+		}
+
+		false
+	}
+
+	/// Checks whether `path`, or any of its parent directories within the library, is
+	/// ignored. Used for paths that don't come from a walk, e.g. filesystem events.
+	pub fn is_path_ignored(&mut self, path: &Path) -> bool {
+		let relative = match path.strip_prefix(&self.root) {
+			Ok(relative) => relative.to_path_buf(),
+			Err(_) => return false,
+		};
+
+		let mut current = self.root.clone();
+		let mut components = relative.components().peekable();
+
+		while let Some(component) = components.next() {
+			current.push(component);
+
+			let is_dir = components.peek().is_some() || current.is_dir();
+
+			if self.is_ignored(&current, is_dir) {
+				return true;
+			}
+		}
+
+		false
+	}
+
+	fn rules_for(&mut self, dir: &Path) -> Option<&Gitignore> {
+		self.dir_rules
+			.entry(dir.to_path_buf())
+			.or_insert_with(|| {
+				let file = dir.join(STUMPIGNORE_FILE);
+
+				if !file.exists() {
+					return None;
+				}
+
+				let (rules, error) = Gitignore::new(&file);
+
+				if let Some(e) = error {
+					log::warn!("Error(s) reading {:?}: {}", file, e);
+				}
+
+				Some(rules)
+			})
+			.as_ref()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::path::Path;
+
+	use super::IgnoreRules;
+
+	#[test]
+	fn library_patterns_are_ignored() {
+		let mut rules = IgnoreRules::new(
+			"/comics",
+			&["@eaDir".to_string(), "scans-raw/".to_string()],
+		);
+
+		assert!(rules.is_ignored(Path::new("/comics/Spider-Man/@eaDir"), true));
+		assert!(rules.is_ignored(Path::new("/comics/Spider-Man/scans-raw"), true));
+		assert!(!rules.is_ignored(Path::new("/comics/Spider-Man/scans-raw"), false));
+		assert!(!rules.is_ignored(Path::new("/comics/Spider-Man/#1.cbz"), false));
+		assert!(rules.is_path_ignored(Path::new("/comics/Spider-Man/@eaDir/#1.cbz")));
+	}
+}
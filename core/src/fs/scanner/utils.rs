@@ -1,29 +1,54 @@
-use prisma_client_rust::{raw, PrismaValue};
+use prisma_client_rust::{
+	chrono::{DateTime, FixedOffset, Utc},
+	raw, PrismaValue, Raw,
+};
 // use rocket::tokio::{self, task::JoinHandle};
+use rocket::serde::json::serde_json;
+use std::path::Path;
 use walkdir::DirEntry;
 
 use crate::{
 	config::context::Context,
-	fs::media_file,
+	db::utils::PrismaClientTrait,
+	fs::{
+		folder, media_file,
+		page_index::PageImage,
+		scanner::{
+			series_metadata::{self, SeriesMetadata},
+			ScannedFileTrait,
+		},
+	},
 	prisma::{library, media, series},
 	types::{
-		errors::{ApiError, ScanError},
+		enums::FileStatus,
+		errors::{ApiError, ProcessFileError, ScanError},
 		event::ClientEvent,
-		models::MediaMetadata,
+		models::metadata::MediaMetadata,
 	},
 };
 
-/// Will mark all series and media within the library as MISSING. Requires the
-/// series and series.media relations to have been loaded to function properly.
+/// Will mark the library, and all series and media within it, as MISSING. Requires the
+/// series relation to have been loaded to function properly.
 pub async fn mark_library_missing(
 	library: library::Data,
 	ctx: &Context,
 ) -> Result<(), ApiError> {
 	let db = ctx.get_db();
 
+	let updated = db
+		.library()
+		.find_unique(library::id::equals(library.id.clone()))
+		.update(vec![library::status::set(FileStatus::Missing.into())])
+		.exec()
+		.await?;
+
+	if let Some(updated) = updated {
+		let _ = ctx.emit_client_event(ClientEvent::UpdatedLibrary(updated));
+	}
+
 	db._execute_raw(raw!(
 		"UPDATE series SET status={} WHERE libraryId={}",
-		PrismaValue::String("MISSING".to_owned()),
+		PrismaValue::String(FileStatus::Missing.into()),
 		PrismaValue::String(library.id.clone())
 	))
 	.await?;
@@ -36,8 +61,12 @@ pub async fn mark_library_missing(
 		.map(|s| s.id.to_owned())
 		.collect::<Vec<_>>();
 
+	if series_ids.is_empty() {
+		return Ok(());
+	}
+
 	let media_query = format!(
-		"UPDATE media SET status\"{}\" WHERE seriesId in ({})",
+		"UPDATE media SET status=\"{}\" WHERE seriesId in ({})",
 		"MISSING".to_owned(),
 		series_ids
 			.into_iter()
@@ -51,73 +80,1012 @@ pub async fn mark_library_missing(
 	Ok(())
 }
 
-pub async fn insert_media(
+/// Marks a MISSING library as READY again, now that its path exists. Returns the updated
+/// library when a change was made.
+pub async fn restore_library(
 	ctx: &Context,
-	entry: &DirEntry,
-	series_id: String,
-) -> Result<media::Data, ScanError> {
-	let processed_entry = media_file::process_entry(entry)?;
+	library: &library::Data,
+) -> Result<Option<library::Data>, ScanError> {
+	if library.status != "MISSING" {
+		return Ok(None);
+	}
 
-	let path = entry.path();
+	log::info!("Library has come back: {}", library.path);
+
+	Ok(ctx
+		.db
+		.library()
+		.find_unique(library::id::equals(library.id.clone()))
+		.update(vec![library::status::set(FileStatus::Ready.into())])
+		.exec()
+		.await?)
+}
+
+/// Brings the status of a series in line with whether its path currently exists. Returns
+/// the updated series when a change was made.
+pub async fn sync_series_status(
+	ctx: &Context,
+	series: &series::Data,
+) -> Result<Option<series::Data>, ScanError> {
+	let status: String = match Path::new(&series.path).exists() {
+		true => FileStatus::Ready.into(),
+		false => FileStatus::Missing.into(),
+	};
+
+	if status == series.status {
+		return Ok(None);
+	}
+
+	log::info!("Series {} is now {}", series.path, status);
+
+	Ok(ctx
+		.db
+		.series()
+		.find_unique(series::id::equals(series.id.clone()))
+		.update(vec![series::status::set(status)])
+		.exec()
+		.await?)
+}
+
+/// The number of paths matched in a single statement by `set_media_status`. Every path is a
+/// parameter, and SQLite only allows 999 parameters in a statement.
+const MEDIA_PATH_BATCH_SIZE: usize = 900;
+
+/// Sets the status of the media at each of the given paths. The paths are bound as
+/// parameters, since file names may contain anything (including quotes).
+async fn set_media_status(
+	ctx: &Context,
+	paths: Vec<String>,
+	set: &str,
+	status: FileStatus,
+) -> Result<(), ScanError> {
+	for paths in paths.chunks(MEDIA_PATH_BATCH_SIZE) {
+		let query = format!(
+			"UPDATE media SET {} WHERE path IN ({})",
+			set,
+			vec!["{}"; paths.len()].join(", ")
+		);
+
+		let mut params = vec![PrismaValue::String(status.into())];
+		params.extend(paths.iter().map(|path| PrismaValue::String(path.clone())));
+
+		ctx.db._execute_raw(Raw::new(&query, params)).await?;
+	}
+
+	Ok(())
+}
+
+/// Will mark the media at each of the given paths as MISSING.
+pub async fn mark_media_missing(
+	ctx: &Context,
+	paths: Vec<String>,
+) -> Result<(), ScanError> {
+	set_media_status(ctx, paths, "status={}", FileStatus::Missing).await
+}
+
+/// Will mark the media at each of the given paths as READY, i.e. MISSING media whose files
+/// have come back unchanged.
+pub async fn mark_media_ready(
+	ctx: &Context,
+	paths: Vec<String>,
+) -> Result<(), ScanError> {
+	set_media_status(
+		ctx,
+		paths,
+		"status={}, statusReason=NULL",
+		FileStatus::Ready,
+	)
+	.await
+}
+
+/// Returns the size and last modified time of the file behind the entry. For folder books,
+/// these are the combined size and latest modified time of the files inside.
+fn get_file_stats(entry: &DirEntry) -> (u64, Option<DateTime<FixedOffset>>) {
+	if entry.file_type().is_dir() {
+		let (size, modified_at) = folder::folder_stats(entry.path());
+
+		return (
+			size,
+			modified_at.map(|time| DateTime::<Utc>::from(time).into()),
+		);
+	}
+
+	match entry.metadata() {
+		Ok(metadata) => (
+			metadata.len(),
+			metadata
+				.modified()
+				.ok()
+				.map(|time| DateTime::<Utc>::from(time).into()),
+		),
+		_ => (0, None),
+	}
+}
+
+/// Compares the size and modified time recorded for the media against the file on disk. Media
+/// without a recorded modified time (i.e. scanned before they were tracked) are always
+/// considered changed, so they get refreshed once.
+pub fn file_has_changed(media: &media::Data, entry: &DirEntry) -> bool {
+	let (size, modified_at) = get_file_stats(entry);
+
+	match (media.modified_at, modified_at) {
+		// Modified times are compared at second precision, since not every filesystem
+		// (or the database) keeps anything finer.
+		(Some(recorded), Some(current)) => {
+			media.size as u64 != size || recorded.timestamp() != current.timestamp()
+		},
+		_ => true,
+	}
+}
+
+/// The outcome of `insert_media`. A file which was moved or renamed keeps its existing media
+/// entity, and with it every user's read progress and the assigned tags.
+pub enum InsertedMedia {
+	Created(media::Data),
+	Relinked(media::Data),
+}
+
+impl InsertedMedia {
+	pub fn media(&self) -> &media::Data {
+		match self {
+			InsertedMedia::Created(media) => media,
+			InsertedMedia::Relinked(media) => media,
+		}
+	}
+
+	/// The event to notify clients with. Relinked media are an update, not a new entity.
+	pub fn into_event(self) -> ClientEvent {
+		match self {
+			InsertedMedia::Created(media) => ClientEvent::CreatedMedia(media),
+			InsertedMedia::Relinked(media) => ClientEvent::UpdatedMedia(media),
+		}
+	}
+}
+
+/// Finds the media in the library whose file could be the processed file before it was moved
+/// or renamed: one with the same checksum, size and extension, whose file no longer exists at
+/// its recorded path. The checksum only covers the start of a file, so it isn't enough on its
+/// own. Note that these media may not have been marked MISSING yet.
+pub async fn moved_media_candidates(
+	ctx: &Context,
+	library_id: &str,
+	processed: &ProcessedMedia,
+) -> Result<Vec<media::Data>, ScanError> {
+	let checksum = match processed.checksum.as_deref() {
+		Some(checksum) => checksum,
+		None => return Ok(vec![]),
+	};
+
+	let candidates = ctx
+		.db
+		.media()
+		.find_many(vec![
+			media::checksum::equals(Some(checksum.to_string())),
+			media::size::equals(processed.size),
+			media::extension::equals(processed.extension.clone()),
+		])
+		.with(media::series::fetch())
+		.exec()
+		.await?;
+
+	Ok(candidates
+		.into_iter()
+		.filter(|media| {
+			let series_library_id = media
+				.series()
+				.ok()
+				.flatten()
+				.and_then(|series| series.library_id.as_deref());
+
+			series_library_id == Some(library_id)
+		})
+		.filter(|media| !Path::new(&media.path).exists())
+		.collect())
+}
+
+/// Finds the MISSING media the processed file was most likely moved or renamed from, among
+/// the `moved_media_candidates`. Only media which are known to be gone are taken over, so a
+/// file which merely looks like another one can't take the place of a media still on disk.
+pub async fn find_moved_media(
+	ctx: &Context,
+	library_id: &str,
+	processed: &ProcessedMedia,
+) -> Result<Option<media::Data>, ScanError> {
+	Ok(moved_media_candidates(ctx, library_id, processed)
+		.await?
+		.into_iter()
+		.find(|media| media.status == "MISSING"))
+}
+
+/// Marks the media of the library whose files no longer exist as MISSING. This is done before
+/// the library is walked, so a file which was moved or renamed is relinked to its media (see
+/// `find_moved_media`) wherever in the library it is found. Returns the number of media marked.
+pub async fn mark_vanished_media(
+	ctx: &Context,
+	library_id: &str,
+) -> Result<usize, ScanError> {
+	mark_vanished(
+		ctx,
+		vec![series::library_id::equals(Some(library_id.to_string()))],
+	)
+	.await
+}
+
+/// Marks the media of the given series whose files no longer exist as MISSING, the same way
+/// as `mark_vanished_media` does for a whole library. Used before scanning only some of the
+/// series of a library. Returns the number of media marked.
+pub async fn mark_vanished_series_media(
+	ctx: &Context,
+	series_ids: Vec<String>,
+) -> Result<usize, ScanError> {
+	mark_vanished(ctx, vec![series::id::in_vec(series_ids)]).await
+}
+
+async fn mark_vanished(
+	ctx: &Context,
+	series_where: Vec<series::WhereParam>,
+) -> Result<usize, ScanError> {
+	let series = ctx
+		.db
+		.series()
+		.find_many(series_where)
+		.with(series::media::fetch(vec![]))
+		.exec()
+		.await?;
+
+	let vanished = series
+		.iter()
+		.flat_map(|series| {
+			series
+				.media()
+				.map(|media| media.to_owned())
+				.unwrap_or_default()
+		})
+		.filter(|media| media.status != "MISSING" && !Path::new(&media.path).exists())
+		.map(|media| media.path)
+		.collect::<Vec<String>>();
+
+	let count = vanished.len();
+
+	mark_media_missing(ctx, vanished).await?;
+
+	Ok(count)
+}
+
+/// The image extensions checked for sidecar covers, in order of preference.
+const SIDECAR_EXTENSIONS: [&str; 4] = ["jpg", "jpeg", "png", "webp"];
+
+/// Looks for a sidecar cover sitting next to a book, i.e. an image with the same name as the
+/// book. ex: "Spider-Man #1.jpg" for "Spider-Man #1.cbz"
+pub fn find_sidecar_thumbnail(path: &Path) -> Option<String> {
+	SIDECAR_EXTENSIONS
+		.iter()
+		.map(|ext| path.with_extension(ext))
+		.find(|sidecar| sidecar.is_file() && sidecar.is_img())
+		.map(|sidecar| sidecar.to_string_lossy().to_string())
+}
+
+/// Looks for a cover image (cover, thumbnail or folder) directly inside of a series
+/// directory. One-shot series are files, so their cover is a sidecar instead.
+pub fn find_series_thumbnail(path: &Path) -> Option<String> {
+	if path.is_file() {
+		return find_sidecar_thumbnail(path);
+	}
+
+	let mut images = std::fs::read_dir(path)
+		.ok()?
+		.filter_map(|item| item.ok())
+		.map(|item| item.path())
+		.filter(|path| path.is_file() && path.is_thumbnail_img())
+		.collect::<Vec<_>>();
+
+	// A `cover` image is preferred over a `thumbnail`, which is preferred over a `folder`.
+	images.sort_by_key(|image| {
+		match image
+			.file_stem()
+			.and_then(|stem| stem.to_str())
+			.map(|stem| stem.to_lowercase())
+			.as_deref()
+		{
+			Some("cover") => 0,
+			Some("thumbnail") => 1,
+			_ => 2,
+		}
+	});
+
+	images
+		.first()
+		.map(|image| image.to_string_lossy().to_string())
+}
+
+/// Records the current sidecar cover for an existing media, if it has changed since the
+/// last scan. Returns the updated media when a change was made.
+pub async fn sync_media_thumbnail(
+	ctx: &Context,
+	media: &media::Data,
+) -> Result<Option<media::Data>, ScanError> {
+	let thumbnail_path = find_sidecar_thumbnail(Path::new(&media.path));
+
+	if thumbnail_path == media.thumbnail_path {
+		return Ok(None);
+	}
+
+	Ok(ctx
+		.db
+		.media()
+		.find_unique(media::id::equals(media.id.clone()))
+		.update(vec![media::thumbnail_path::set(thumbnail_path)])
+		.exec()
+		.await?)
+}
 
-	let path_str = path.to_str().unwrap().to_string();
-	let mut name = entry.file_name().to_str().unwrap().to_string();
-	let ext = path.extension().unwrap().to_str().unwrap().to_string();
+/// Records the current cover image for a series, if it has changed since the last scan.
+/// Returns the updated series when a change was made.
+pub async fn sync_series_thumbnail(
+	ctx: &Context,
+	series: &series::Data,
+) -> Result<Option<series::Data>, ScanError> {
+	let thumbnail_path = find_series_thumbnail(Path::new(&series.path));
+
+	if thumbnail_path == series.thumbnail_path {
+		return Ok(None);
+	}
+
+	Ok(ctx
+		.db
+		.series()
+		.find_unique(series::id::equals(series.id.clone()))
+		.update(vec![series::thumbnail_path::set(thumbnail_path)])
+		.exec()
+		.await?)
+}
+
+/// Everything Stump derives from a media file on disk. Processing a file is blocking (and
+/// for archives, slow), so this is done up front, separately from any database writes.
+pub struct ProcessedMedia {
+	pub path: String,
+	pub name: String,
+	pub extension: String,
+	pub size: i64,
+	pub pages: i32,
+	pub checksum: Option<String>,
+	pub description: Option<String>,
+	pub modified_at: Option<DateTime<FixedOffset>>,
+	pub thumbnail_path: Option<String>,
+	pub metadata: Option<MediaMetadata>,
+	pub page_table: Vec<PageImage>,
+}
 
-	// remove extension from name, not sure why file_name() includes it smh
-	if name.ends_with(format!(".{}", ext).as_str()) {
-		name.truncate(name.len() - (ext.len() + 1));
+impl ProcessedMedia {
+	pub fn from_entry(entry: &DirEntry) -> Result<Self, ProcessFileError> {
+		let processed_entry = media_file::process_entry(entry)?;
+
+		let path = entry.path();
+
+		let path_str = path.to_str().unwrap().to_string();
+		let mut name = entry.file_name().to_str().unwrap().to_string();
+
+		let ext = if entry.file_type().is_dir() {
+			// A folder book's name is the whole folder name, e.g. "Chapter 1.5"
+			folder::FOLDER_BOOK_EXTENSION.to_string()
+		} else {
+			let ext = path.extension().unwrap().to_str().unwrap().to_string();
+
+			// remove extension from name, not sure why file_name() includes it smh
+			if name.ends_with(format!(".{}", ext).as_str()) {
+				name.truncate(name.len() - (ext.len() + 1));
+			}
+
+			ext
+		};
+
+		let (size, modified_at) = get_file_stats(entry);
+
+		let metadata = processed_entry.metadata;
+
+		// The PageCount of a ComicInfo.xml isn't trusted, since it often disagrees with the
+		// images actually in the book. The page count has to match what can be served.
+		let pages = processed_entry.pages;
+
+		Ok(ProcessedMedia {
+			path: path_str,
+			name,
+			extension: ext,
+			size: i64::try_from(size)
+				.map_err(|e| ProcessFileError::Unknown(e.to_string()))?,
+			pages,
+			checksum: processed_entry.checksum,
+			description: metadata.as_ref().and_then(|m| m.summary.clone()),
+			modified_at,
+			thumbnail_path: find_sidecar_thumbnail(path),
+			metadata,
+			page_table: processed_entry.page_table,
+		})
 	}
+}
 
-	// Note: make this return a tuple if I need to grab anything else from metadata.
-	let size = match entry.metadata() {
-		Ok(metadata) => metadata.len(),
-		_ => 0,
+/// Relinks the media the processed file was moved or renamed from, if there is one in the
+/// library of the series.
+pub async fn relink_media(
+	ctx: &Context,
+	processed: &ProcessedMedia,
+	series_id: String,
+) -> Result<Option<media::Data>, ScanError> {
+	let library_id = ctx
+		.db
+		.series()
+		.find_unique(series::id::equals(series_id.clone()))
+		.exec()
+		.await?
+		.and_then(|series| series.library_id);
+
+	let moved_media = match library_id {
+		Some(library_id) => find_moved_media(ctx, &library_id, processed).await?,
+		None => None,
 	};
 
-	let comic_info = processed_entry.metadata.unwrap_or(MediaMetadata::default());
+	let moved_media = match moved_media {
+		Some(moved_media) => moved_media,
+		None => return Ok(None),
+	};
+
+	log::info!(
+		"Media {} was moved from {} to {}",
+		moved_media.id,
+		moved_media.path,
+		processed.path
+	);
+
+	let media = ctx
+		.db
+		.media()
+		.find_unique(media::id::equals(moved_media.id.clone()))
+		.update(vec![
+			media::name::set(processed.name.clone()),
+			media::size::set(processed.size),
+			media::extension::set(processed.extension.clone()),
+			media::pages::set(processed.pages),
+			media::path::set(processed.path.clone()),
+			media::checksum::set(processed.checksum.clone()),
+			// The file may have changed along with its path, so its hashes have to be computed
+			// again, and it has to be verified again.
+			media::full_checksum::set(None),
+			media::cover_hash::set(None),
+			media::verified_at::set(None),
+			media::description::set(processed.description.clone()),
+			media::modified_at::set(processed.modified_at),
+			media::thumbnail_path::set(processed.thumbnail_path.clone()),
+			media::status::set("READY".to_string()),
+			media::status_reason::set(None),
+			media::series::link(series::id::equals(series_id)),
+		])
+		.exec()
+		.await?;
+
+	match media {
+		Some(media) => {
+			set_media_metadata(ctx, media.id.clone(), processed.metadata.clone()).await?;
+			set_media_pages(ctx, media.id.clone(), processed.page_table.clone()).await?;
+
+			Ok(Some(media))
+		},
+		None => Err(ScanError::Unknown(format!(
+			"Media with id {} not found",
+			moved_media.id
+		))),
+	}
+}
+
+/// Creates a media entity for the file, unless the file is one Stump already knew about under
+/// a different path. In that case, the existing entity is relinked to the new path and series.
+pub async fn insert_media(
+	ctx: &Context,
+	entry: &DirEntry,
+	series_id: String,
+) -> Result<InsertedMedia, ScanError> {
+	let processed = ProcessedMedia::from_entry(entry)?;
+
+	insert_processed_media(ctx, processed, series_id).await
+}
+
+/// Writes an already processed file to a new (or relinked) media entity. See `insert_media`.
+pub async fn insert_processed_media(
+	ctx: &Context,
+	processed: ProcessedMedia,
+	series_id: String,
+) -> Result<InsertedMedia, ScanError> {
+	if let Some(media) = relink_media(ctx, &processed, series_id.clone()).await? {
+		return Ok(InsertedMedia::Relinked(media));
+	}
 
 	let media = ctx
 		.db
 		.media()
 		.create(
-			media::name::set(name),
-			media::size::set(size.try_into().unwrap()),
-			media::extension::set(ext),
-			media::pages::set(match comic_info.page_count {
-				Some(count) => count as i32,
-				None => processed_entry.pages,
-			}),
-			media::path::set(path_str),
+			media::name::set(processed.name),
+			media::size::set(processed.size),
+			media::extension::set(processed.extension),
+			media::pages::set(processed.pages),
+			media::path::set(processed.path),
 			vec![
-				media::checksum::set(processed_entry.checksum),
-				media::description::set(comic_info.summary),
+				media::checksum::set(processed.checksum),
+				media::description::set(processed.description),
+				media::modified_at::set(processed.modified_at),
+				media::thumbnail_path::set(processed.thumbnail_path),
 				media::series::link(series::id::equals(series_id)),
 			],
 		)
 		.exec()
 		.await?;
 
+	set_media_metadata(ctx, media.id.clone(), processed.metadata).await?;
+	set_media_pages(ctx, media.id.clone(), processed.page_table).await?;
+
 	log::debug!("Created new media: {:?}", media);
 
+	Ok(InsertedMedia::Created(media))
+}
+
+/// The number of parameters in each row written by `insert_media_many`.
+const MEDIA_INSERT_COLUMNS: usize = 12;
+
+/// The number of new media written to the database in a single statement, see
+/// `MEDIA_METADATA_BATCH_SIZE`.
+pub const MEDIA_BATCH_SIZE: usize = 999 / MEDIA_INSERT_COLUMNS;
+
+/// Creates media entities for many processed files with a single statement, followed by
+/// their metadata and page tables. The media cost one write (and one lock on the database
+/// file) instead of one per file. This isn't atomic: if the metadata or pages can't be
+/// written, the new media are deleted again, so the next scan picks the files up anew. At
+/// most `MEDIA_BATCH_SIZE` files are written at once. The processed files are expected to
+/// have been checked with `relink_media` already.
+pub async fn insert_media_many(
+	ctx: &Context,
+	batch: Vec<(ProcessedMedia, String)>,
+) -> Result<Vec<media::Data>, ScanError> {
+	if batch.is_empty() {
+		return Ok(vec![]);
+	}
+
+	if batch.len() > MEDIA_BATCH_SIZE {
+		return Err(ScanError::Unknown(format!(
+			"Cannot insert {} media at once, the most is {}",
+			batch.len(),
+			MEDIA_BATCH_SIZE
+		)));
+	}
+
+	let now: DateTime<FixedOffset> = Utc::now().into();
+
+	let mut ids = Vec::with_capacity(batch.len());
+	let mut rows = Vec::with_capacity(batch.len());
+	let mut params = Vec::with_capacity(batch.len() * MEDIA_INSERT_COLUMNS);
+	let mut metadata = vec![];
+	let mut pages = Vec::with_capacity(batch.len());
+
+	for (processed, series_id) in batch {
+		let id = cuid::cuid().map_err(|e| ScanError::Unknown(e.to_string()))?;
+
+		if let Some(processed_metadata) = processed.metadata {
+			metadata.push((id.clone(), processed_metadata));
+		}
+
+		pages.push((id.clone(), processed.page_table));
+
+		rows.push("({}, {}, {}, {}, {}, {}, {}, {}, {}, {}, {}, {})");
+
+		params.extend(vec![
+			PrismaValue::String(id.clone()),
+			PrismaValue::String(processed.name),
+			optional_string(processed.description),
+			PrismaValue::Int(processed.size),
+			PrismaValue::String(processed.extension),
+			PrismaValue::Int(processed.pages as i64),
+			PrismaValue::DateTime(now),
+			processed
+				.modified_at
+				.map(PrismaValue::DateTime)
+				.unwrap_or(PrismaValue::Null),
+			optional_string(processed.thumbnail_path),
+			optional_string(processed.checksum),
+			PrismaValue::String(processed.path),
+			PrismaValue::String(series_id),
+		]);
+
+		ids.push(id);
+	}
+
+	let query = format!(
+		"INSERT INTO media (id, name, description, size, extension, pages, updatedAt, modifiedAt, thumbnailPath, checksum, path, seriesId) VALUES {}",
+		rows.join(", ")
+	);
+
+	ctx.db._execute_raw(Raw::new(&query, params)).await?;
+
+	let written = match write_media_metadata(ctx, metadata).await {
+		Ok(_) => write_media_pages(ctx, pages).await,
+		Err(e) => Err(e),
+	};
+
+	if let Err(e) = written {
+		// The metadata and pages of the media are removed along with them.
+		let deleted = ctx
+			.db
+			.media()
+			.find_many(vec![media::id::in_vec(ids)])
+			.delete()
+			.exec()
+			.await;
+
+		if let Err(delete_error) = deleted {
+			log::error!("Failed to remove partially written media: {}", delete_error);
+		}
+
+		return Err(e);
+	}
+
+	let media = ctx
+		.db
+		.media()
+		.find_many(vec![media::id::in_vec(ids)])
+		.exec()
+		.await?;
+
+	log::debug!("Created {} new media", media.len());
+
 	Ok(media)
 }
 
-pub async fn insert_series(
+fn optional_string(value: Option<String>) -> PrismaValue {
+	value.map(PrismaValue::String).unwrap_or(PrismaValue::Null)
+}
+
+fn optional_int(value: Option<i32>) -> PrismaValue {
+	value
+		.map(|value| PrismaValue::Int(value as i64))
+		.unwrap_or(PrismaValue::Null)
+}
+
+/// The columns of `media_metadata` written by `write_media_metadata`, in order.
+const MEDIA_METADATA_COLUMNS: [&str; 46] = [
+	"id",
+	"mediaId",
+	"title",
+	"series",
+	"number",
+	"count",
+	"volume",
+	"alternateSeries",
+	"alternateNumber",
+	"alternateCount",
+	"summary",
+	"notes",
+	"year",
+	"month",
+	"day",
+	"writer",
+	"penciller",
+	"inker",
+	"colorist",
+	"letterer",
+	"coverArtist",
+	"editor",
+	"translator",
+	"publisher",
+	"imprint",
+	"genre",
+	"tags",
+	"web",
+	"languageIso",
+	"format",
+	"blackAndWhite",
+	"manga",
+	"characters",
+	"teams",
+	"locations",
+	"mainCharacterOrTeam",
+	"scanInformation",
+	"storyArc",
+	"storyArcNumber",
+	"seriesGroup",
+	"ageRating",
+	"communityRating",
+	"review",
+	"gtin",
+	"pageCount",
+	"pages",
+];
+
+/// The number of metadata rows written in a single statement. Every column is a parameter,
+/// and SQLite only allows 999 parameters in a statement.
+const MEDIA_METADATA_BATCH_SIZE: usize = 20;
+
+fn media_metadata_values(
+	media_id: String,
+	metadata: MediaMetadata,
+) -> Result<Vec<PrismaValue>, ScanError> {
+	let id = cuid::cuid().map_err(|e| ScanError::Unknown(e.to_string()))?;
+
+	let pages = match metadata.pages.is_empty() {
+		true => None,
+		false => serde_json::to_string(&metadata.pages).ok(),
+	};
+
+	Ok(vec![
+		PrismaValue::String(id),
+		PrismaValue::String(media_id),
+		optional_string(metadata.title),
+		optional_string(metadata.series),
+		optional_string(metadata.number),
+		optional_int(metadata.count),
+		optional_int(metadata.volume),
+		optional_string(metadata.alternate_series),
+		optional_string(metadata.alternate_number),
+		optional_int(metadata.alternate_count),
+		optional_string(metadata.summary),
+		optional_string(metadata.notes),
+		optional_int(metadata.year),
+		optional_int(metadata.month),
+		optional_int(metadata.day),
+		optional_string(metadata.writer),
+		optional_string(metadata.penciller),
+		optional_string(metadata.inker),
+		optional_string(metadata.colorist),
+		optional_string(metadata.letterer),
+		optional_string(metadata.cover_artist),
+		optional_string(metadata.editor),
+		optional_string(metadata.translator),
+		optional_string(metadata.publisher),
+		optional_string(metadata.imprint),
+		optional_string(metadata.genre),
+		optional_string(metadata.tags),
+		optional_string(metadata.web),
+		optional_string(metadata.language_iso),
+		optional_string(metadata.format),
+		optional_string(metadata.black_and_white),
+		optional_string(metadata.manga),
+		optional_string(metadata.characters),
+		optional_string(metadata.teams),
+		optional_string(metadata.locations),
+		optional_string(metadata.main_character_or_team),
+		optional_string(metadata.scan_information),
+		optional_string(metadata.story_arc),
+		optional_string(metadata.story_arc_number),
+		optional_string(metadata.series_group),
+		optional_string(metadata.age_rating),
+		// The column is REAL, so SQLite stores the text as a number.
+		optional_string(metadata.community_rating.map(|rating| rating.to_string())),
+		optional_string(metadata.review),
+		optional_string(metadata.gtin),
+		optional_int(metadata.page_count),
+		optional_string(pages),
+	])
+}
+
+/// Writes the metadata for each of the media, replacing any metadata they already have.
+pub async fn write_media_metadata(
+	ctx: &Context,
+	metadata: Vec<(String, MediaMetadata)>,
+) -> Result<(), ScanError> {
+	let row = format!("({})", vec!["{}"; MEDIA_METADATA_COLUMNS.len()].join(", "));
+
+	let mut metadata = metadata.into_iter().peekable();
+
+	while metadata.peek().is_some() {
+		let mut rows = Vec::with_capacity(MEDIA_METADATA_BATCH_SIZE);
+		let mut params = vec![];
+
+		for (media_id, media_metadata) in
+			metadata.by_ref().take(MEDIA_METADATA_BATCH_SIZE)
+		{
+			rows.push(row.as_str());
+			params.extend(media_metadata_values(media_id, media_metadata)?);
+		}
+
+		// mediaId is unique, so existing metadata is replaced.
+		let query = format!(
+			"INSERT OR REPLACE INTO media_metadata ({}) VALUES {}",
+			MEDIA_METADATA_COLUMNS.join(", "),
+			rows.join(", ")
+		);
+
+		ctx.db._execute_raw(Raw::new(&query, params)).await?;
+	}
+
+	Ok(())
+}
+
+/// Sets the metadata of a media, or removes it when the file no longer has any.
+pub async fn set_media_metadata(
+	ctx: &Context,
+	media_id: String,
+	metadata: Option<MediaMetadata>,
+) -> Result<(), ScanError> {
+	match metadata {
+		Some(metadata) => write_media_metadata(ctx, vec![(media_id, metadata)]).await,
+		None => {
+			ctx.db
+				._execute_raw(raw!(
+					"DELETE FROM media_metadata WHERE mediaId={}",
+					PrismaValue::String(media_id)
+				))
+				.await?;
+
+			Ok(())
+		},
+	}
+}
+
+/// The columns of `media_pages` written by `write_media_pages`, in order.
+const MEDIA_PAGE_COLUMNS: [&str; 8] = [
+	"id",
+	"mediaId",
+	"number",
+	"name",
+	"size",
+	"contentType",
+	"width",
+	"height",
+];
+
+/// The number of page rows written in a single statement, see `MEDIA_METADATA_BATCH_SIZE`.
+const MEDIA_PAGE_BATCH_SIZE: usize = 120;
+
+/// Writes the page table of each of the media, replacing any pages they already have.
+pub async fn write_media_pages(
+	ctx: &Context,
+	pages: Vec<(String, Vec<PageImage>)>,
+) -> Result<(), ScanError> {
+	// The existing pages are removed first, since a book may have fewer pages than before.
+	for media_ids in pages.chunks(MEDIA_PAGE_BATCH_SIZE) {
+		let query = format!(
+			"DELETE FROM media_pages WHERE mediaId IN ({})",
+			vec!["{}"; media_ids.len()].join(", ")
+		);
+
+		let params = media_ids
+			.iter()
+			.map(|(media_id, _)| PrismaValue::String(media_id.clone()))
+			.collect();
+
+		ctx.db._execute_raw(Raw::new(&query, params)).await?;
+	}
+
+	let row = format!("({})", vec!["{}"; MEDIA_PAGE_COLUMNS.len()].join(", "));
+
+	let mut pages = pages
+		.into_iter()
+		.flat_map(|(media_id, images)| {
+			images
+				.into_iter()
+				.enumerate()
+				.map(move |(index, image)| (media_id.clone(), index + 1, image))
+		})
+		.peekable();
+
+	while pages.peek().is_some() {
+		let mut rows = Vec::with_capacity(MEDIA_PAGE_BATCH_SIZE);
+		let mut params = vec![];
+
+		for (media_id, number, image) in pages.by_ref().take(MEDIA_PAGE_BATCH_SIZE) {
+			let id = cuid::cuid().map_err(|e| ScanError::Unknown(e.to_string()))?;
+
+			rows.push(row.as_str());
+			params.extend(vec![
+				PrismaValue::String(id),
+				PrismaValue::String(media_id),
+				PrismaValue::Int(number as i64),
+				PrismaValue::String(image.name),
+				PrismaValue::Int(image.size as i64),
+				optional_string(image.content_type),
+				optional_int(image.width),
+				optional_int(image.height),
+			]);
+		}
+
+		let query = format!(
+			"INSERT INTO media_pages ({}) VALUES {}",
+			MEDIA_PAGE_COLUMNS.join(", "),
+			rows.join(", ")
+		);
+
+		ctx.db._execute_raw(Raw::new(&query, params)).await?;
+	}
+
+	Ok(())
+}
+
+/// Sets the page table of a media, replacing the pages from its last scan.
+pub async fn set_media_pages(
+	ctx: &Context,
+	media_id: String,
+	pages: Vec<PageImage>,
+) -> Result<(), ScanError> {
+	write_media_pages(ctx, vec![(media_id, pages)]).await
+}
+
+/// Re-processes the file for an existing media entity, refreshing everything derived from
+/// the file's contents. The media will be marked READY afterwards.
+pub async fn update_media(
 	ctx: &Context,
 	entry: &DirEntry,
-	library_id: String,
-) -> Result<series::Data, ScanError> {
-	let path = entry.path();
+	media_id: String,
+) -> Result<media::Data, ScanError> {
+	let processed = ProcessedMedia::from_entry(entry)?;
+
+	update_processed_media(ctx, processed, media_id).await
+}
+
+/// Writes an already processed file to an existing media entity. See `update_media`.
+pub async fn update_processed_media(
+	ctx: &Context,
+	processed: ProcessedMedia,
+	media_id: String,
+) -> Result<media::Data, ScanError> {
+	let media = ctx
+		.db
+		.media()
+		.find_unique(media::id::equals(media_id.clone()))
+		.update(vec![
+			media::size::set(processed.size),
+			media::pages::set(processed.pages),
+			media::checksum::set(processed.checksum),
+			// The file changed, so its hashes have to be computed again, and it has to be
+			// verified again.
+			media::full_checksum::set(None),
+			media::cover_hash::set(None),
+			media::verified_at::set(None),
+			media::description::set(processed.description),
+			media::modified_at::set(processed.modified_at),
+			media::thumbnail_path::set(processed.thumbnail_path),
+			media::status::set("READY".to_string()),
+			media::status_reason::set(None),
+		])
+		.exec()
+		.await?;
+
+	match media {
+		Some(media) => {
+			set_media_metadata(ctx, media.id.clone(), processed.metadata).await?;
+			set_media_pages(ctx, media.id.clone(), processed.page_table).await?;
 
-	// TODO: use this??
-	// let metadata = match path.metadata() {
-	// 	Ok(metadata) => Some(metadata),
-	// 	_ => None,
-	// };
+			log::debug!("Updated media: {:?}", media);
+
+			Ok(media)
+		},
+		None => Err(ScanError::Unknown(format!(
+			"Media with id {} not found",
+			media_id
+		))),
+	}
+}
+
+/// The name and details of the series at `path`. The series is described by the
+/// `series.json` in its folder, if there is one. With `rename_series`, the series is named
+/// after its `series.json` or ComicInfo.xml instead of the folder.
+fn series_details(
+	path: &Path,
+	rename_series: bool,
+) -> Result<(String, Option<SeriesMetadata>), ScanError> {
+	// One-shots are a single file, there is no folder to hold any metadata.
+	let series_json = match path.is_dir() {
+		true => series_metadata::read_series_json(path),
+		false => None,
+	};
+
+	let metadata_name = match rename_series && path.is_dir() {
+		true => series_metadata::series_name(path, series_json.as_ref()),
+		false => None,
+	};
+
+	// One-shot series are a single file, so the extension is dropped from the name.
+	let name = match path.is_file() {
+		true => path.file_stem(),
+		false => path.file_name(),
+	};
 
 	// TODO: change error
-	let name = match path.file_name() {
+	let name = match name {
 		Some(name) => match name.to_str() {
 			Some(name) => name.to_string(),
 			_ => {
@@ -133,13 +1101,39 @@ pub async fn insert_series(
 		},
 	};
 
+	Ok((metadata_name.unwrap_or(name), series_json))
+}
+
+/// Creates a series entity for the entry, named and described as per `series_details`.
+pub async fn insert_series(
+	ctx: &Context,
+	entry: &DirEntry,
+	library_id: String,
+	rename_series: bool,
+) -> Result<series::Data, ScanError> {
+	let path = entry.path();
+
+	let (name, series_json) = series_details(path, rename_series)?;
+
+	let mut params = vec![series::library::link(library::id::equals(library_id))];
+
+	if let Some(series_json) = series_json {
+		params.extend(vec![
+			series::description::set(series_json.description()),
+			series::publisher::set(series_json.publisher),
+			series::year::set(series_json.year),
+			series::publication_status::set(series_json.status),
+			series::total_issues::set(series_json.total_issues),
+		]);
+	}
+
 	let series = ctx
 		.db
 		.series()
 		.create(
 			series::name::set(name),
 			series::path::set(path.to_str().unwrap().to_string()),
-			vec![series::library::link(library::id::equals(library_id))],
+			params,
 		)
 		.exec()
 		.await?;
@@ -149,6 +1143,58 @@ pub async fn insert_series(
 	Ok(series)
 }
 
+/// Refreshes the name and details of an existing series, as per `series_details`, when its
+/// `series.json` or the `rename_series` preference changed since it was last scanned.
+/// Returns the updated series when a change was made.
+pub async fn sync_series_details(
+	ctx: &Context,
+	series: &series::Data,
+	rename_series: bool,
+) -> Result<Option<series::Data>, ScanError> {
+	let (name, series_json) = series_details(Path::new(&series.path), rename_series)?;
+
+	// Without a series.json, there is nothing to describe the series with anymore.
+	let series_json = series_json.unwrap_or_default();
+	let description = series_json.description();
+
+	let unchanged = name == series.name
+		&& description == series.description
+		&& series_json.publisher == series.publisher
+		&& series_json.year == series.year
+		&& series_json.status == series.publication_status
+		&& series_json.total_issues == series.total_issues;
+
+	if unchanged {
+		return Ok(None);
+	}
+
+	Ok(ctx
+		.db
+		.series()
+		.find_unique(series::id::equals(series.id.clone()))
+		.update(vec![
+			series::name::set(name),
+			series::description::set(description),
+			series::publisher::set(series_json.publisher),
+			series::year::set(series_json.year),
+			series::publication_status::set(series_json.status),
+			series::total_issues::set(series_json.total_issues),
+		])
+		.exec()
+		.await?)
+}
+
+/// Whether the server is set to name series after their metadata. See `insert_series`.
+pub async fn should_rename_series(ctx: &Context) -> bool {
+	match ctx.db.get_server_preferences().await {
+		Ok(preferences) => preferences.rename_series,
+		Err(e) => {
+			log::error!("Failed to get server preferences: {:?}", e);
+			false
+		},
+	}
+}
+
 pub async fn insert_series_many(
 	ctx: &Context,
 	entries: Vec<DirEntry>,
@@ -156,8 +1202,14 @@ pub async fn insert_series_many(
 ) -> Vec<series::Data> {
 	let mut inserted_series = vec![];
 
+	if entries.is_empty() {
+		return inserted_series;
+	}
+
+	let rename_series = should_rename_series(ctx).await;
+
 	for entry in entries {
-		match insert_series(&ctx, &entry, library_id.clone()).await {
+		match insert_series(&ctx, &entry, library_id.clone(), rename_series).await {
 			Ok(series) => {
 				let _ = ctx.emit_client_event(ClientEvent::CreatedSeries(series.clone()));
 
@@ -0,0 +1,487 @@
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use rocket::tokio::{
+	self,
+	sync::mpsc::{unbounded_channel, UnboundedReceiver},
+	time::timeout,
+};
+use std::{
+	collections::{BTreeMap, HashMap, HashSet},
+	path::{Path, PathBuf},
+	time::{Duration, Instant},
+};
+use walkdir::{DirEntry, WalkDir};
+
+use crate::{
+	config::context::Context,
+	fs::{
+		folder,
+		scanner::{ScanOptions, ScannedFileTrait},
+	},
+	prisma::{library, media, series},
+	types::{errors::ScanError, event::ClientEvent},
+};
+
+use super::utils::{
+	file_has_changed, insert_processed_media, insert_series, mark_media_missing,
+	should_rename_series, sync_media_thumbnail, sync_series_status,
+	sync_series_thumbnail, update_processed_media, ProcessedMedia,
+};
+
+/// How long a library must be quiet before the collected changes are applied. Download
+/// clients and file managers tend to emit a burst of events for a single file (create,
+/// a handful of writes, maybe a rename), so applying changes as they come in would have
+/// Stump processing half-written archives.
+const DEBOUNCE_DURATION: Duration = Duration::from_secs(5);
+
+/// The longest changes are held back for. A long copy keeps a library from ever being quiet
+/// for `DEBOUNCE_DURATION`, so the changes collected so far are applied after this long
+/// regardless. Files still being written are picked up again by their next event.
+const MAX_DEBOUNCE_DURATION: Duration = Duration::from_secs(60);
+
+/// Keeps a filesystem watcher alive for every watched library. Dropping a watcher closes
+/// its event channel, which in turn stops the task debouncing its events.
+pub struct LibraryWatcher {
+	watchers: HashMap<String, RecommendedWatcher>,
+}
+
+impl LibraryWatcher {
+	pub fn new() -> Self {
+		LibraryWatcher {
+			watchers: HashMap::new(),
+		}
+	}
+
+	/// Starts watching the library at `path`. If the library is already being watched, the
+	/// previous watcher is replaced (e.g. when the library path was updated).
+	pub fn watch(
+		&mut self,
+		ctx: Context,
+		library_id: String,
+		path: String,
+	) -> notify::Result<()> {
+		self.unwatch(&library_id);
+
+		let (tx, rx) = unbounded_channel::<Event>();
+
+		let mut watcher =
+			notify::recommended_watcher(move |res: notify::Result<Event>| match res {
+				Ok(event) => {
+					let _ = tx.send(event);
+				},
+				Err(e) => {
+					log::error!("Library watcher error: {:?}", e);
+				},
+			})?;
+
+		watcher.watch(Path::new(&path), RecursiveMode::Recursive)?;
+
+		tokio::spawn(debounce_changes(ctx, library_id.clone(), rx));
+
+		log::info!("Watching library {} at {}", library_id, path);
+
+		self.watchers.insert(library_id, watcher);
+
+		Ok(())
+	}
+
+	pub fn unwatch(&mut self, library_id: &str) {
+		if self.watchers.remove(library_id).is_some() {
+			log::info!("Stopped watching library {}", library_id);
+		}
+	}
+}
+
+/// Collects the paths touched by filesystem events until the library has been quiet for
+/// `DEBOUNCE_DURATION` (or changes have been pending for `MAX_DEBOUNCE_DURATION`), then
+/// applies them all at once.
+async fn debounce_changes(
+	ctx: Context,
+	library_id: String,
+	mut rx: UnboundedReceiver<Event>,
+) {
+	let mut pending = HashSet::<PathBuf>::new();
+	let mut pending_since = Instant::now();
+
+	loop {
+		let next = if pending.is_empty() {
+			rx.recv().await
+		} else {
+			let wait = MAX_DEBOUNCE_DURATION
+				.saturating_sub(pending_since.elapsed())
+				.min(DEBOUNCE_DURATION);
+
+			match timeout(wait, rx.recv()).await {
+				Ok(next) => next,
+				// Nothing happened within the debounce window, so the burst is over (or it
+				// has gone on for too long to keep waiting).
+				Err(_) => {
+					apply_changes(&ctx, &library_id, pending.drain().collect()).await;
+					continue;
+				},
+			}
+		};
+
+		match next {
+			Some(event) => {
+				// Reads don't change anything Stump cares about
+				if !matches!(event.kind, EventKind::Access(_)) {
+					if pending.is_empty() {
+						pending_since = Instant::now();
+					}
+
+					pending.extend(event.paths);
+				}
+			},
+			// The watcher was dropped, i.e. the library is no longer watched.
+			None => break,
+		}
+	}
+
+	log::debug!("Stopped debouncing changes for library {}", library_id);
+}
+
+/// Reconciles the database with the current state of each changed path. The event kinds
+/// themselves aren't trusted, since a burst might contain a create, a few modifies and a
+/// rename for the same file. Whatever exists on disk now is what gets applied.
+async fn apply_changes(ctx: &Context, library_id: &str, paths: Vec<PathBuf>) {
+	log::debug!(
+		"Applying {} filesystem change(s) to library {}",
+		paths.len(),
+		library_id
+	);
+
+	// Whatever was cached for the changed files is stale, whether or not the change ends
+	// up affecting the database.
+	for path in &paths {
+		ctx.cache.invalidate(path);
+	}
+
+	let library = ctx
+		.db
+		.library()
+		.find_unique(library::id::equals(library_id.to_string()))
+		.with(library::series::fetch(vec![]))
+		.exec()
+		.await;
+
+	let library = match library {
+		Ok(Some(library)) => library,
+		Ok(None) => {
+			log::warn!("Library {} no longer exists, skipping changes", library_id);
+			return;
+		},
+		Err(e) => {
+			log::error!("Failed to load library {}: {:?}", library_id, e);
+			return;
+		},
+	};
+
+	let mut series = library.series().map(|s| s.to_owned()).unwrap_or_default();
+	let options = ScanOptions::from(&library);
+
+	// Checking and walking the changed paths is blocking.
+	let resolve_options = options.clone();
+	let resolved =
+		tokio::task::spawn_blocking(move || resolve_changes(&resolve_options, paths))
+			.await;
+
+	let (removed, files) = match resolved {
+		Ok(resolved) => resolved,
+		Err(e) => {
+			log::error!(
+				"Failed to resolve changes for library {}: {:?}",
+				library_id,
+				e
+			);
+			return;
+		},
+	};
+
+	for path in removed.iter().filter(|path| path.is_img()) {
+		if let Err(e) = apply_image_change(ctx, &series, path).await {
+			log::error!("Failed to apply removed image {:?}: {:?}", path, e);
+		}
+	}
+
+	if let Err(e) = apply_removals(ctx, &series, removed).await {
+		log::error!("Failed to apply removed files: {:?}", e);
+	}
+
+	for (path, entry) in files {
+		if let Err(e) =
+			apply_file_change(ctx, &library, &options, &mut series, &entry).await
+		{
+			log::error!("Failed to apply change for {:?}: {:?}", path, e);
+		}
+	}
+}
+
+/// Splits the changed paths into the ones which were removed, and the files which exist now.
+/// A changed directory (e.g. a new issue folder was copied in) is expanded to the files
+/// inside of it. The map dedupes files that were also reported on their own, as well as the
+/// pages of a folder book, which all resolve to the book itself.
+fn resolve_changes(
+	options: &ScanOptions,
+	paths: Vec<PathBuf>,
+) -> (Vec<PathBuf>, BTreeMap<PathBuf, DirEntry>) {
+	let mut rules = options.ignore_rules();
+
+	let (removed, mut existing): (Vec<PathBuf>, Vec<PathBuf>) =
+		paths.into_iter().partition(|path| !path.exists());
+
+	// A page removed from a folder book changes the book, it doesn't remove anything.
+	if options.folder_books {
+		existing.extend(
+			removed
+				.iter()
+				.filter_map(|path| path.parent())
+				.filter(|parent| parent.exists() && folder::is_folder_book(parent))
+				.map(|parent| parent.to_path_buf()),
+		);
+	}
+
+	let mut files = BTreeMap::<PathBuf, DirEntry>::new();
+
+	for path in existing {
+		if rules.is_path_ignored(&path) {
+			continue;
+		}
+
+		for entry in WalkDir::new(&path)
+			.follow_links(options.follow_symlinks)
+			.into_iter()
+			.filter_entry(|e| !rules.is_ignored(e.path(), e.file_type().is_dir()))
+			.filter_map(|e| e.ok())
+			.filter(|e| e.path().is_file())
+		{
+			let entry = folder_book_for(options, entry.path()).unwrap_or(entry);
+
+			files.insert(entry.path().to_path_buf(), entry);
+		}
+	}
+
+	(removed, files)
+}
+
+/// Marks the media (and series) at, or nested under, each removed path as MISSING.
+async fn apply_removals(
+	ctx: &Context,
+	series: &[series::Data],
+	removed: Vec<PathBuf>,
+) -> Result<(), ScanError> {
+	for path in removed {
+		let path_str = path.to_string_lossy().to_string();
+
+		let missing_media = ctx
+			.db
+			.media()
+			.find_many(vec![media::path::starts_with(path_str.clone())])
+			.exec()
+			.await?
+			.into_iter()
+			.filter(|m| Path::new(&m.path).starts_with(&path))
+			.collect::<Vec<media::Data>>();
+
+		if !missing_media.is_empty() {
+			log::info!(
+				"{} media removed from the filesystem at {:?}",
+				missing_media.len(),
+				path
+			);
+
+			mark_media_missing(
+				ctx,
+				missing_media.iter().map(|m| m.path.clone()).collect(),
+			)
+			.await?;
+
+			for mut media in missing_media {
+				media.status = "MISSING".to_string();
+				let _ = ctx.emit_client_event(ClientEvent::UpdatedMedia(media));
+			}
+		}
+
+		for s in series
+			.iter()
+			.filter(|s| Path::new(&s.path).starts_with(&path))
+		{
+			log::info!("Series removed from the filesystem: {}", s.path);
+
+			let updated = ctx
+				.db
+				.series()
+				.find_unique(series::id::equals(s.id.clone()))
+				.update(vec![series::status::set("MISSING".to_string())])
+				.exec()
+				.await?;
+
+			if let Some(updated) = updated {
+				let _ = ctx.emit_client_event(ClientEvent::UpdatedSeries(updated));
+			}
+		}
+	}
+
+	Ok(())
+}
+
+async fn apply_file_change(
+	ctx: &Context,
+	library: &library::Data,
+	options: &ScanOptions,
+	series: &mut Vec<series::Data>,
+	entry: &DirEntry,
+) -> Result<(), ScanError> {
+	let path = entry.path();
+
+	if path.is_img() {
+		return apply_image_change(ctx, series, path).await;
+	} else if path.should_ignore() {
+		return Ok(());
+	}
+
+	let series_path = match entry.file_type().is_dir() {
+		true => options.series_path_for_folder_book(&library.path, path),
+		false => options.series_path_for(&library.path, path),
+	};
+
+	let series_path = match series_path {
+		Some(series_path) => series_path,
+		None => {
+			log::debug!("No series could be determined for {:?}, skipping", path);
+			return Ok(());
+		},
+	};
+
+	let series_id = match series
+		.iter_mut()
+		.find(|s| Path::new(&s.path) == series_path)
+	{
+		Some(s) => {
+			// Files are showing up again, so the series has come back.
+			if s.status == "MISSING" {
+				if let Some(updated) = sync_series_status(ctx, s).await? {
+					s.status = updated.status.clone();
+					let _ = ctx.emit_client_event(ClientEvent::UpdatedSeries(updated));
+				}
+			}
+
+			s.id.clone()
+		},
+		None => {
+			let series_entry = match dir_entry(series_path) {
+				Some(series_entry) => series_entry,
+				None => {
+					log::debug!("Series path for {:?} could not be read, skipping", path);
+					return Ok(());
+				},
+			};
+
+			let rename_series = should_rename_series(ctx).await;
+			let created =
+				insert_series(ctx, &series_entry, library.id.clone(), rename_series)
+					.await?;
+			let _ = ctx.emit_client_event(ClientEvent::CreatedSeries(created.clone()));
+
+			let series_id = created.id.clone();
+			series.push(created);
+
+			series_id
+		},
+	};
+
+	let existing = ctx
+		.db
+		.media()
+		.find_first(vec![media::path::equals(
+			path.to_string_lossy().to_string(),
+		)])
+		.exec()
+		.await?;
+
+	// Expanded directories will include files which didn't actually change.
+	if let Some(media) = existing.as_ref() {
+		if media.status != "MISSING" && !file_has_changed(media, entry) {
+			return Ok(());
+		}
+	}
+
+	// Processing a file reads (and for archives, decompresses) it, which is blocking.
+	let processed_entry = entry.clone();
+	let processed =
+		tokio::task::spawn_blocking(move || ProcessedMedia::from_entry(&processed_entry))
+			.await
+			.map_err(|e| ScanError::Unknown(e.to_string()))??;
+
+	match existing {
+		Some(media) => {
+			let media = update_processed_media(ctx, processed, media.id).await?;
+			let _ = ctx.emit_client_event(ClientEvent::UpdatedMedia(media));
+		},
+		None => {
+			let inserted = insert_processed_media(ctx, processed, series_id).await?;
+			let _ = ctx.emit_client_event(inserted.into_event());
+		},
+	}
+
+	Ok(())
+}
+
+/// Images are only ever thumbnail overrides, so a changed image means the cover of the
+/// series it sits in, or of the book it sits next to, should be re-synced.
+async fn apply_image_change(
+	ctx: &Context,
+	series: &[series::Data],
+	path: &Path,
+) -> Result<(), ScanError> {
+	let parent = path.parent().unwrap_or(path);
+	let book_path = path.with_extension("");
+	let book_path_str = book_path.to_string_lossy().to_string();
+
+	// One-shot series are a single book, so their cover is the book's sidecar.
+	for s in series.iter().filter(|s| {
+		let series_path = Path::new(&s.path);
+
+		series_path == parent || series_path.with_extension("") == book_path
+	}) {
+		if let Some(updated) = sync_series_thumbnail(ctx, s).await? {
+			let _ = ctx.emit_client_event(ClientEvent::UpdatedSeries(updated));
+		}
+	}
+
+	let books = ctx
+		.db
+		.media()
+		.find_many(vec![media::path::starts_with(book_path_str)])
+		.exec()
+		.await?
+		.into_iter()
+		.filter(|m| Path::new(&m.path).with_extension("") == book_path);
+
+	for book in books {
+		if let Some(updated) = sync_media_thumbnail(ctx, &book).await? {
+			let _ = ctx.emit_client_event(ClientEvent::UpdatedMedia(updated));
+		}
+	}
+
+	Ok(())
+}
+
+/// The folder book the file is a page of, if folder books are enabled for the library.
+fn folder_book_for(options: &ScanOptions, path: &Path) -> Option<DirEntry> {
+	if !options.folder_books {
+		return None;
+	}
+
+	match path.parent() {
+		Some(parent) if folder::is_folder_book(parent) => dir_entry(parent.to_path_buf()),
+		_ => None,
+	}
+}
+
+fn dir_entry(path: PathBuf) -> Option<DirEntry> {
+	WalkDir::new(path)
+		.max_depth(0)
+		.into_iter()
+		.next()
+		.and_then(|e| e.ok())
+}
@@ -0,0 +1,202 @@
+use sevenz_rust::{Password, SevenZReader};
+use std::{
+	collections::{HashMap, HashSet},
+	io::Read,
+	path::Path,
+};
+use walkdir::DirEntry;
+
+use crate::{
+	fs::media_file::{self, GetPageResult},
+	types::{
+		alias::ProcessResult,
+		errors::ProcessFileError,
+		models::{metadata::MediaMetadata, ProcessedMediaFile},
+	},
+};
+
+use super::{
+	checksum,
+	page_index::{PageImage, PageIndex},
+};
+
+fn open_7z(path: &str) -> Result<SevenZReader<std::fs::File>, ProcessFileError> {
+	SevenZReader::open(path, Password::empty())
+		.map_err(|e| ProcessFileError::SevenZipReadError(format!("{:?}", e)))
+}
+
+pub fn digest_7z(path: &Path, size: u64) -> Option<String> {
+	// 7z archives are usually solid, so unlike zips there are no per-file offsets to go by.
+	let bytes_to_read = size.min(40000);
+
+	match checksum::digest(path.to_str().unwrap(), bytes_to_read) {
+		Ok(digest) => Some(digest),
+		Err(e) => {
+			log::error!(
+				"Failed to digest 7z file {:?}, unable to create checksum: {}",
+				path,
+				e
+			);
+			None
+		},
+	}
+}
+
+/// The ComicInfo.xml and page index of a 7z file, along with the contents of its images
+/// when they were kept (see `read_7z_entries`).
+type SevenZipEntries = (Option<MediaMetadata>, PageIndex, HashMap<String, Vec<u8>>);
+
+/// Walks a 7z file once, reading its ComicInfo.xml and building the page index from its
+/// images. With `keep_images`, the images are read in full and kept, otherwise only their
+/// headers are read.
+fn read_7z_entries(
+	path: &str,
+	keep_images: bool,
+) -> Result<SevenZipEntries, ProcessFileError> {
+	let mut comic_info = None;
+	let mut images = Vec::new();
+	let mut kept = HashMap::new();
+
+	open_7z(path)?
+		.for_each_entries(|entry, reader| {
+			if entry.name() == "ComicInfo.xml" {
+				let mut contents = String::new();
+				reader.read_to_string(&mut contents)?;
+				comic_info = media_file::process_comic_info(contents);
+			} else if !entry.is_directory() {
+				let mut bytes = Vec::new();
+
+				match keep_images {
+					true => reader.read_to_end(&mut bytes)?,
+					false => reader
+						.take(media_file::IMAGE_HEADER_LEN)
+						.read_to_end(&mut bytes)?,
+				};
+
+				let header_len = bytes.len().min(media_file::IMAGE_HEADER_LEN as usize);
+				let header = &bytes[..header_len];
+
+				if media_file::is_image_content(entry.name(), header) {
+					images.push(PageImage::new(
+						entry.name().to_string(),
+						entry.size(),
+						header,
+					));
+
+					if keep_images {
+						kept.insert(entry.name().to_string(), bytes);
+					}
+				}
+			}
+
+			Ok(true)
+		})
+		.map_err(|e| ProcessFileError::SevenZipReadError(format!("{:?}", e)))?;
+
+	let pages = PageIndex::new(images, comic_info.as_ref());
+
+	Ok((comic_info, pages, kept))
+}
+
+/// Reads the ComicInfo.xml of a 7z file, and builds the page index from its images.
+fn read_7z_pages(
+	path: &str,
+) -> Result<(Option<MediaMetadata>, PageIndex), ProcessFileError> {
+	let (comic_info, pages, _) = read_7z_entries(path, false)?;
+
+	Ok((comic_info, pages))
+}
+
+/// Processes a 7z file in its entirety, reading the ComicInfo.xml and counting the pages.
+pub fn process_7z(file: &DirEntry) -> ProcessResult {
+	log::info!("Processing 7z: {}", file.path().display());
+
+	let path = file.path();
+	let (comic_info, pages) = read_7z_pages(&path.to_string_lossy())?;
+
+	Ok(ProcessedMediaFile {
+		checksum: digest_7z(path, file.metadata().map(|m| m.len()).unwrap_or_default()),
+		metadata: comic_info,
+		pages: pages.len(),
+		page_table: pages.into_images(),
+	})
+}
+
+/// Get an image from a 7z file by index (page). Which image is the page is only known
+/// once every entry has been seen, and 7z files are usually solid, so rather than walking
+/// the archive a second time for the page, the images are kept while it is walked once.
+/// Pages are normally read by name from the stored page table instead (see
+/// `read_7z_image`).
+pub fn get_7z_image(file: &str, page: i32) -> GetPageResult {
+	let (_, pages, mut images) = read_7z_entries(file, true)?;
+
+	let target = match pages.get(page) {
+		Some(target) => target,
+		None => {
+			log::error!("Could not find image for page {} in 7z file {}", page, file);
+			return Err(ProcessFileError::NoImageError);
+		},
+	};
+
+	let contents = images
+		.remove(target)
+		.ok_or(ProcessFileError::NoImageError)?;
+
+	Ok((media_file::image_content_type(target, &contents), contents))
+}
+
+/// Reads an image from a 7z file by its name, as stored in the page table of the book.
+pub fn read_7z_image(file: &str, name: &str) -> Result<Vec<u8>, ProcessFileError> {
+	let mut contents = None;
+
+	// Entries can't be read out of order, so the archive is walked until the target.
+	open_7z(file)?
+		.for_each_entries(|entry, reader| {
+			if entry.name() == name {
+				let mut bytes = Vec::new();
+				reader.read_to_end(&mut bytes)?;
+				contents = Some(bytes);
+				return Ok(false);
+			}
+
+			Ok(true)
+		})
+		.map_err(|e| ProcessFileError::SevenZipReadError(format!("{:?}", e)))?;
+
+	contents.ok_or(ProcessFileError::NoImageError)
+}
+
+/// Decompresses every page of a 7z file, which also checks the CRC of each, and decodes the
+/// header of each image.
+pub fn verify_7z(file: &str) -> Result<(), ProcessFileError> {
+	let (_, pages) = read_7z_pages(file)?;
+
+	if pages.is_empty() {
+		return Err(ProcessFileError::NoImageError);
+	}
+
+	let names = pages.iter().collect::<HashSet<&str>>();
+
+	let mut problem = None;
+
+	open_7z(file)?
+		.for_each_entries(|entry, reader| {
+			if names.contains(entry.name()) {
+				let mut contents = Vec::new();
+				reader.read_to_end(&mut contents)?;
+
+				if let Err(e) = media_file::verify_image(entry.name(), &contents) {
+					problem = Some(e);
+					return Ok(false);
+				}
+			}
+
+			Ok(true)
+		})
+		.map_err(|e| ProcessFileError::SevenZipReadError(format!("{:?}", e)))?;
+
+	match problem {
+		Some(problem) => Err(problem),
+		None => Ok(()),
+	}
+}
@@ -0,0 +1,203 @@
+use std::{
+	collections::HashMap,
+	fs::File,
+	io::{Read, Seek, SeekFrom},
+	path::Path,
+};
+use tar::{Archive, Entry};
+use walkdir::DirEntry;
+
+use crate::{
+	fs::media_file::{self, GetPageResult},
+	types::{
+		alias::ProcessResult,
+		errors::ProcessFileError,
+		models::{metadata::MediaMetadata, ProcessedMediaFile},
+	},
+};
+
+use super::{
+	checksum,
+	page_index::{PageImage, PageIndex},
+};
+
+fn entry_name<R: Read>(entry: &Entry<R>) -> Option<String> {
+	entry
+		.path()
+		.ok()
+		.map(|path| path.to_string_lossy().to_string())
+}
+
+pub fn digest_tar(path: &str) -> Option<String> {
+	let mut archive = Archive::new(File::open(path).ok()?);
+
+	// Same as zips, the first few files are enough to tell two archives apart. Entries are
+	// stored as is, so their sizes line up with the file.
+	let byte_offset = archive
+		.entries()
+		.ok()?
+		.filter_map(|entry| entry.ok())
+		.take(6)
+		.fold(0, |acc, entry| acc + entry.size());
+
+	match checksum::digest(path, byte_offset) {
+		Ok(digest) => Some(digest),
+		Err(e) => {
+			log::error!(
+				"Failed to digest tar file {}, unable to create checksum: {}",
+				path,
+				e
+			);
+			None
+		},
+	}
+}
+
+/// Where the data of an image is in the tar file: (offset, size)
+type ImageLocation = (u64, u64);
+
+/// Reads the ComicInfo.xml of a tar, and builds the page index from its images. Also returns
+/// where each image is in the file, so it can be read without walking the archive again.
+fn read_tar_pages(
+	path: &Path,
+) -> Result<
+	(
+		Option<MediaMetadata>,
+		PageIndex,
+		HashMap<String, ImageLocation>,
+	),
+	ProcessFileError,
+> {
+	let mut archive = Archive::new(File::open(path)?);
+
+	let mut comic_info = None;
+	let mut images = Vec::new();
+	let mut locations = HashMap::new();
+
+	for entry in archive.entries()? {
+		let mut entry = entry?;
+		let name = match entry_name(&entry) {
+			Some(name) => name,
+			None => continue,
+		};
+
+		if name == "ComicInfo.xml" {
+			let mut contents = String::new();
+			entry.read_to_string(&mut contents)?;
+			comic_info = media_file::process_comic_info(contents);
+		} else if entry.header().entry_type().is_file() {
+			let location = (entry.raw_file_position(), entry.size());
+
+			let mut header = Vec::new();
+			(&mut entry)
+				.take(media_file::IMAGE_HEADER_LEN)
+				.read_to_end(&mut header)?;
+
+			if media_file::is_image_content(&name, &header) {
+				images.push(PageImage::new(name.clone(), entry.size(), &header));
+				locations.insert(name, location);
+			}
+		}
+	}
+
+	let pages = PageIndex::new(images, comic_info.as_ref());
+
+	Ok((comic_info, pages, locations))
+}
+
+/// Processes a tar file in its entirety, reading the ComicInfo.xml and counting the pages.
+pub fn process_tar(file: &DirEntry) -> ProcessResult {
+	log::info!("Processing Tar: {}", file.path().display());
+
+	let (comic_info, pages, _) = read_tar_pages(file.path())?;
+
+	Ok(ProcessedMediaFile {
+		checksum: digest_tar(&file.path().to_string_lossy()),
+		metadata: comic_info,
+		pages: pages.len(),
+		page_table: pages.into_images(),
+	})
+}
+
+/// Get an image from a tar file by index (page).
+pub fn get_tar_image(file: &str, page: i32) -> GetPageResult {
+	let (_, pages, locations) = read_tar_pages(Path::new(file))?;
+
+	let image = pages
+		.get(page)
+		.and_then(|name| locations.get(name).map(|location| (name, location)));
+
+	let (name, (position, size)) = match image {
+		Some(image) => image,
+		None => {
+			log::error!(
+				"Could not find image for page {} in tar file {}",
+				page,
+				file
+			);
+			return Err(ProcessFileError::NoImageError);
+		},
+	};
+
+	// Tar entries aren't compressed, so the image can be read straight from the file.
+	let mut tar_file = File::open(file)?;
+	let mut contents = vec![0u8; *size as usize];
+	tar_file.seek(SeekFrom::Start(*position))?;
+	tar_file.read_exact(&mut contents)?;
+
+	Ok((media_file::image_content_type(name, &contents), contents))
+}
+
+/// Reads an image from a tar file by its name, as stored in the page table of the book. Only
+/// the headers of the entries before it are read, the data of each is seeked past.
+pub fn read_tar_image(file: &str, name: &str) -> Result<Vec<u8>, ProcessFileError> {
+	let mut archive = Archive::new(File::open(file)?);
+
+	for entry in archive.entries_with_seek()? {
+		let mut entry = entry?;
+
+		if entry_name(&entry).as_deref() == Some(name) {
+			let mut contents = Vec::with_capacity(entry.size() as usize);
+			entry.read_to_end(&mut contents)?;
+
+			return Ok(contents);
+		}
+	}
+
+	Err(ProcessFileError::NoImageError)
+}
+
+/// Reads every page of a tar file, checking none of them are cut off, and decodes the header
+/// of each image. Tar files have no checksums of their own to go by.
+pub fn verify_tar(file: &str) -> Result<(), ProcessFileError> {
+	let (_, pages, locations) = read_tar_pages(Path::new(file))?;
+
+	if pages.is_empty() {
+		return Err(ProcessFileError::NoImageError);
+	}
+
+	let mut tar_file = File::open(file)?;
+	let file_size = tar_file.metadata()?.len();
+
+	for name in pages.iter() {
+		let (position, size) = match locations.get(name) {
+			Some(location) => *location,
+			None => return Err(ProcessFileError::NoImageError),
+		};
+
+		if position + size > file_size {
+			return Err(ProcessFileError::CorruptPageError(
+				name.to_string(),
+				"The file is cut off".to_string(),
+			));
+		}
+
+		let mut contents = vec![0u8; size as usize];
+		tar_file.seek(SeekFrom::Start(position))?;
+		tar_file.read_exact(&mut contents)?;
+
+		media_file::verify_image(name, &contents)?;
+	}
+
+	Ok(())
+}
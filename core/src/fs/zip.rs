@@ -1,36 +1,38 @@
 use crate::{
-	fs::media_file::{self, GetPageResult, IsImage},
-	types::{alias::ProcessResult, errors::ProcessFileError, models::ProcessedMediaFile},
+	fs::media_file::{self, GetPageResult},
+	types::{
+		alias::ProcessResult,
+		errors::ProcessFileError,
+		models::{metadata::MediaMetadata, ProcessedMediaFile},
+	},
 };
 
-use std::io::Read;
+use std::io::{Read, Seek};
 use walkdir::DirEntry;
-use zip::read::ZipFile;
-
-use super::checksum;
-
-impl<'a> IsImage for ZipFile<'a> {
-	// FIXME: use infer here
-	fn is_image(&self) -> bool {
-		if self.is_file() {
-			let content_type = media_file::guess_content_type(self.name());
-
-			// TODO: is this all??
-			return content_type.is_jpeg()
-				|| content_type.is_png()
-				|| content_type.is_webp()
-				|| content_type.is_svg()
-				|| content_type.is_tiff();
-		}
+use zip::ZipArchive;
 
-		false
-	}
-}
+use super::{
+	checksum,
+	page_index::{PageImage, PageIndex},
+};
 
 // TODO: result return
 pub fn digest_zip(path: &str) -> Option<String> {
-	let zip_file = std::fs::File::open(path).unwrap();
-	let mut archive = zip::ZipArchive::new(zip_file).unwrap();
+	let archive = std::fs::File::open(path)
+		.map_err(ProcessFileError::from)
+		.and_then(|zip_file| Ok(zip::ZipArchive::new(zip_file)?));
+
+	let mut archive = match archive {
+		Ok(archive) => archive,
+		Err(e) => {
+			log::error!(
+				"Failed to digest zipfile {}, unable to open it: {}",
+				path,
+				e
+			);
+			return None;
+		},
+	};
 
 	let mut byte_offset = 0;
 
@@ -39,9 +41,17 @@ pub fn digest_zip(path: &str) -> Option<String> {
 			break;
 		}
 
-		let file = archive.by_index(i).unwrap();
-
-		byte_offset += file.size();
+		match archive.by_index(i) {
+			Ok(file) => byte_offset += file.size(),
+			Err(e) => {
+				log::error!(
+					"Failed to digest zipfile {}, unable to read it: {}",
+					path,
+					e
+				);
+				return None;
+			},
+		}
 	}
 
 	match checksum::digest(path, byte_offset) {
@@ -57,80 +67,122 @@ pub fn digest_zip(path: &str) -> Option<String> {
 	}
 }
 
-/// Processes a zip file in its entirety. Will return a tuple of the comic info and the list of
-/// files in the zip.
-pub fn process_zip(file: &DirEntry) -> ProcessResult {
-	info!("Processing Zip: {}", file.path().display());
-
-	let zip_file = std::fs::File::open(file.path())?;
-	let mut archive = zip::ZipArchive::new(zip_file)?;
-
+/// Reads the ComicInfo.xml of a zip, and builds the page index from its images.
+fn read_zip_pages<R: Read + Seek>(
+	archive: &mut ZipArchive<R>,
+) -> Result<(Option<MediaMetadata>, PageIndex), ProcessFileError> {
 	let mut comic_info = None;
-	// let mut entries = Vec::new();
-	let mut pages = 0;
+	let mut images = Vec::new();
 
 	for i in 0..archive.len() {
 		let mut file = archive.by_index(i)?;
-		// entries.push(file.name().to_owned());
+
 		if file.name() == "ComicInfo.xml" {
 			let mut contents = String::new();
 			file.read_to_string(&mut contents)?;
 			comic_info = media_file::process_comic_info(contents);
-		} else {
-			pages += 1;
+		} else if file.is_file() {
+			// Entries are sniffed rather than trusted by name, which is cheap since only
+			// the first few bytes are decompressed.
+			let mut header = Vec::new();
+			file.by_ref()
+				.take(media_file::IMAGE_HEADER_LEN)
+				.read_to_end(&mut header)?;
+
+			if media_file::is_image_content(file.name(), &header) {
+				images.push(PageImage::new(file.name().to_owned(), file.size(), &header));
+			}
 		}
 	}
-	// 7054b81b-09f1-48f9-9167-8396ccd57533
+
+	let pages = PageIndex::new(images, comic_info.as_ref());
+
+	Ok((comic_info, pages))
+}
+
+/// Processes a zip file in its entirety. Will return the comic info and the number of pages
+/// in the zip.
+pub fn process_zip(file: &DirEntry) -> ProcessResult {
+	info!("Processing Zip: {}", file.path().display());
+
+	let zip_file = std::fs::File::open(file.path())?;
+	let mut archive = zip::ZipArchive::new(zip_file)?;
+
+	let (comic_info, pages) = read_zip_pages(&mut archive)?;
+
 	Ok(ProcessedMediaFile {
 		checksum: digest_zip(file.path().to_str().unwrap()),
 		metadata: comic_info,
-		pages,
+		pages: pages.len(),
+		page_table: pages.into_images(),
 	})
 }
 
-// FIXME: this solution is terrible, was just fighting with borrow checker and wanted
-// a quick solve. TODO: rework this!
 /// Get an image from a zip file by index (page).
 pub fn get_zip_image(file: &str, page: i32) -> GetPageResult {
 	let zip_file = std::fs::File::open(file)?;
 
-	let mut archive = zip::ZipArchive::new(&zip_file)?;
-	// FIXME: stinky clone here
-	let file_names_archive = archive.clone();
+	let mut archive = zip::ZipArchive::new(zip_file)?;
 
 	if archive.len() == 0 {
 		log::error!("Zip file {} is empty", file);
 		return Err(ProcessFileError::ArchiveEmptyError);
 	}
 
-	let mut file_names = file_names_archive.file_names().collect::<Vec<_>>();
-	// NOTE: I noticed some zip files *also* come back out of order >:(
-	// TODO: look more into this!
-	file_names.sort_by(|a, b| a.cmp(b));
+	let (_, pages) = read_zip_pages(&mut archive)?;
 
-	let mut images_seen = 0;
-	for name in file_names {
-		let mut file = archive.by_name(name)?;
+	let name = match pages.get(page) {
+		Some(name) => name,
+		None => {
+			log::error!(
+				"Could not find image for page {} in zip file {}",
+				page,
+				file
+			);
+			return Err(ProcessFileError::NoImageError);
+		},
+	};
 
-		let mut contents = Vec::new();
-		// Note: guessing mime here since this file isn't accessible from the filesystem,
-		// it lives inside the zip file.
-		let content_type = media_file::guess_content_type(name);
-
-		if images_seen + 1 == page && file.is_image() {
-			log::debug!("Found target image: {}", name);
-			file.read_to_end(&mut contents)?;
-			return Ok((content_type, contents));
-		} else if file.is_image() {
-			images_seen += 1;
-		}
+	let mut contents = Vec::new();
+	archive.by_name(name)?.read_to_end(&mut contents)?;
+
+	Ok((media_file::image_content_type(name, &contents), contents))
+}
+
+/// Reads an image from a zip file by its name, as stored in the page table of the book.
+pub fn read_zip_image(file: &str, name: &str) -> Result<Vec<u8>, ProcessFileError> {
+	let zip_file = std::fs::File::open(file)?;
+	let mut archive = zip::ZipArchive::new(zip_file)?;
+
+	let mut contents = Vec::new();
+	archive.by_name(name)?.read_to_end(&mut contents)?;
+
+	Ok(contents)
+}
+
+/// Reads every page of a zip, which also checks the CRC of each, and decodes the header of
+/// each image.
+pub fn verify_zip(file: &str) -> Result<(), ProcessFileError> {
+	let zip_file = std::fs::File::open(file)?;
+	let mut archive = zip::ZipArchive::new(zip_file)?;
+
+	let (_, pages) = read_zip_pages(&mut archive)?;
+
+	if pages.is_empty() {
+		return Err(ProcessFileError::NoImageError);
 	}
 
-	log::error!(
-		"Could not find image for page {} in zip file {}",
-		page,
-		file
-	);
+	for name in pages.iter() {
+		let mut contents = Vec::new();
+		archive
+			.by_name(name)?
+			.read_to_end(&mut contents)
+			.map_err(|e| {
+				ProcessFileError::CorruptPageError(name.to_string(), e.to_string())
+			})?;
+
+		media_file::verify_image(name, &contents)?;
+	}
 
-	Err(ProcessFileError::NoImageError)
+	Ok(())
 }
@@ -0,0 +1,115 @@
+use rocket::tokio;
+
+use super::Job;
+
+use crate::{
+	config::context::Context,
+	db::utils::PrismaClientTrait,
+	fs::media_file,
+	prisma::media,
+	types::{errors::ApiError, event::ClientEvent},
+};
+
+/// Computes the full checksum of every media which doesn't have one yet, or of every media
+/// when `force` is set. A media whose file can't be read is logged and skipped.
+#[derive(Debug)]
+pub struct FullChecksumJob {
+	pub force: bool,
+}
+
+#[async_trait::async_trait]
+impl Job for FullChecksumJob {
+	async fn run(&self, runner_id: String, ctx: Context) -> Result<(), ApiError> {
+		let filters = match self.force {
+			true => vec![media::status::equals("READY".to_string())],
+			false => vec![
+				media::status::equals("READY".to_string()),
+				media::full_checksum::equals(None),
+			],
+		};
+
+		let pending = ctx.db.media().find_many(filters).exec().await?;
+
+		let total = pending.len() as u64;
+
+		let _ = ctx.emit_client_event(ClientEvent::job_started(
+			runner_id.clone(),
+			0,
+			total,
+			Some(format!("Computing full checksums for {} media", total)),
+		));
+
+		let mut computed = 0;
+
+		for (index, media) in pending.into_iter().enumerate() {
+			let _ = ctx.emit_client_event(ClientEvent::job_progress(
+				runner_id.clone(),
+				index as u64,
+				total,
+				Some(format!("Computing full checksum of {}", media.path)),
+			));
+
+			let path = media.path.clone();
+			let full_checksum =
+				tokio::task::spawn_blocking(move || media_file::full_checksum(&path))
+					.await
+					.map_err(|e| ApiError::InternalServerError(e.to_string()))?;
+
+			match full_checksum {
+				Ok(full_checksum) => {
+					ctx.db
+						.media()
+						.find_unique(media::id::equals(media.id))
+						.update(vec![media::full_checksum::set(Some(full_checksum))])
+						.exec()
+						.await?;
+
+					computed += 1;
+				},
+				Err(e) => {
+					log::error!(
+						"Failed to compute full checksum of {}: {}",
+						media.path,
+						e
+					);
+				},
+			}
+		}
+
+		let conflicts = ctx.db.checksum_conflicts().await?;
+
+		let message = match conflicts.is_empty() {
+			true => format!("Computed {} of {} full checksums", computed, total),
+			false => format!(
+				"Computed {} of {} full checksums. {} groups of media share a checksum, but not their contents",
+				computed,
+				total,
+				conflicts.len()
+			),
+		};
+
+		for conflict in &conflicts {
+			log::warn!(
+				"Media with checksum {} are not duplicates: {}",
+				conflict.checksum,
+				conflict
+					.media
+					.iter()
+					.map(|media| media.path.as_str())
+					.collect::<Vec<&str>>()
+					.join(", ")
+			);
+		}
+
+		log::info!("{}", message);
+
+		let _ = ctx.emit_client_event(ClientEvent::job_progress(
+			runner_id,
+			total,
+			total,
+			Some(message),
+		));
+
+		Ok(())
+	}
+}
@@ -0,0 +1,95 @@
+use std::path::Path;
+
+use super::Job;
+
+use crate::{
+	config::context::Context,
+	db::utils::PrismaClientTrait,
+	fs::convert::convert_media_to_cbz,
+	prisma::media,
+	types::{errors::ApiError, event::ClientEvent},
+};
+
+/// Converts each of the media from .cbr to .cbz, one after another. A media which fails to
+/// convert is logged and skipped, so the rest still get converted.
+#[derive(Debug)]
+pub struct CbzConversionJob {
+	pub media_ids: Vec<String>,
+}
+
+#[async_trait::async_trait]
+impl Job for CbzConversionJob {
+	async fn run(&self, runner_id: String, ctx: Context) -> Result<(), ApiError> {
+		let delete_original = ctx.db.get_server_preferences().await?.delete_converted_cbr;
+
+		let total = self.media_ids.len() as u64;
+
+		let _ = ctx.emit_client_event(ClientEvent::job_started(
+			runner_id.clone(),
+			0,
+			total,
+			Some(format!("Converting {} cbr files to cbz", total)),
+		));
+
+		let mut converted = 0;
+
+		for (index, media_id) in self.media_ids.iter().enumerate() {
+			let _ = ctx.emit_client_event(ClientEvent::job_progress(
+				runner_id.clone(),
+				index as u64,
+				total,
+				Some(format!("Converting media {}", media_id)),
+			));
+
+			match convert_media_to_cbz(&ctx, media_id.clone(), delete_original).await {
+				Ok(media) => {
+					log::info!("Converted media {} to {}", media.id, media.path);
+					converted += 1;
+				},
+				Err(e) => {
+					log::error!("Failed to convert media {}: {:?}", media_id, e);
+				},
+			}
+		}
+
+		log::info!("Converted {} of {} cbr files to cbz", converted, total);
+
+		Ok(())
+	}
+}
+
+/// Queues the .cbr media at, or nested under, `path` for conversion, if the server is set to
+/// convert them on scan. Media whose .cbz already exists next to them (i.e. an original which
+/// was kept after converting it) are left alone.
+pub async fn queue_cbz_conversion(ctx: &Context, path: &str) -> Result<(), ApiError> {
+	if !ctx.db.get_server_preferences().await?.convert_cbr_to_cbz {
+		return Ok(());
+	}
+
+	let media_ids = ctx
+		.db
+		.media()
+		.find_many(vec![
+			media::path::starts_with(path.to_string()),
+			media::status::equals("READY".to_string()),
+		])
+		.exec()
+		.await?
+		.into_iter()
+		.filter(|m| {
+			let media_path = Path::new(&m.path);
+
+			m.extension.eq_ignore_ascii_case("cbr")
+				&& media_path.starts_with(path)
+				&& !media_path.with_extension("cbz").exists()
+		})
+		.map(|m| m.id)
+		.collect::<Vec<String>>();
+
+	if !media_ids.is_empty() {
+		log::info!("Queueing {} cbr files for conversion", media_ids.len());
+		ctx.spawn_job(Box::new(CbzConversionJob { media_ids }));
+	}
+
+	Ok(())
+}
@@ -0,0 +1,78 @@
+use rocket::tokio;
+
+use super::Job;
+
+use crate::{
+	config::context::Context,
+	fs::cover_hash,
+	prisma::media,
+	types::{errors::ApiError, event::ClientEvent},
+};
+
+/// Computes the perceptual hash of the cover of every media which doesn't have one yet, or
+/// of every media when `force` is set. A media whose cover can't be read is logged and
+/// skipped.
+#[derive(Debug)]
+pub struct CoverHashJob {
+	pub force: bool,
+}
+
+#[async_trait::async_trait]
+impl Job for CoverHashJob {
+	async fn run(&self, runner_id: String, ctx: Context) -> Result<(), ApiError> {
+		let filters = match self.force {
+			true => vec![media::status::equals("READY".to_string())],
+			false => vec![
+				media::status::equals("READY".to_string()),
+				media::cover_hash::equals(None),
+			],
+		};
+
+		let pending = ctx.db.media().find_many(filters).exec().await?;
+
+		let total = pending.len() as u64;
+
+		let _ = ctx.emit_client_event(ClientEvent::job_started(
+			runner_id.clone(),
+			0,
+			total,
+			Some(format!("Hashing the covers of {} media", total)),
+		));
+
+		let mut hashed = 0;
+
+		for (index, media) in pending.into_iter().enumerate() {
+			let _ = ctx.emit_client_event(ClientEvent::job_progress(
+				runner_id.clone(),
+				index as u64,
+				total,
+				Some(format!("Hashing the cover of {}", media.path)),
+			));
+
+			let path = media.path.clone();
+			let hash = tokio::task::spawn_blocking(move || cover_hash::cover_hash(&path))
+				.await
+				.map_err(|e| ApiError::InternalServerError(e.to_string()))?;
+
+			match hash {
+				Ok(hash) => {
+					ctx.db
+						.media()
+						.find_unique(media::id::equals(media.id))
+						.update(vec![media::cover_hash::set(Some(hash))])
+						.exec()
+						.await?;
+
+					hashed += 1;
+				},
+				Err(e) => {
+					log::error!("Failed to hash the cover of {}: {}", media.path, e);
+				},
+			}
+		}
+
+		log::info!("Hashed the covers of {} of {} media", hashed, total);
+
+		Ok(())
+	}
+}
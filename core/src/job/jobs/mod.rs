@@ -1,4 +1,8 @@
+pub mod checksum;
+pub mod convert;
+pub mod cover_hash;
 pub mod scan;
+pub mod verify;
 
 use std::{collections::HashMap, sync::Arc};
 
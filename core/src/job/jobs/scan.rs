@@ -1,20 +1,41 @@
-use super::Job;
+use super::{convert::queue_cbz_conversion, Job};
 
 use crate::{
-	config::context::Context, fs::scanner::library::scan_sync as scan,
-	types::errors::ApiError,
+	config::context::Context,
+	fs::scanner::{
+		library::{scan_concurrent as scan, scan_path, scan_single_series},
+		report::scan_report,
+	},
+	prisma::series,
+	types::{enums::ScanTrigger, errors::ApiError},
 };
 
+/// Queues the conversion of any .cbr files the scan came across. A failure here doesn't fail
+/// the scan, which has already finished.
+async fn after_scan(ctx: &Context, path: &str) {
+	if let Err(e) = queue_cbz_conversion(ctx, path).await {
+		log::error!("Failed to queue cbr conversion for {}: {:?}", path, e);
+	}
+}
+
 #[derive(Debug)]
 pub struct LibraryScannerJob {
 	pub path: String,
+	/// What started the scan, recorded in the library's scan history.
+	pub trigger: ScanTrigger,
 }
 
 #[async_trait::async_trait]
 impl Job for LibraryScannerJob {
 	async fn run(&self, runner_id: String, ctx: Context) -> Result<(), ApiError> {
 		let start = std::time::Instant::now();
-		scan(ctx, self.path.clone(), runner_id.clone()).await?;
+		scan(
+			ctx.get_ctx(),
+			self.path.clone(),
+			runner_id.clone(),
+			self.trigger,
+		)
+		.await?;
 		let duration = start.elapsed();
 
 		log::info!(
@@ -23,6 +44,106 @@ impl Job for LibraryScannerJob {
 			duration.subsec_millis()
 		);
 
+		after_scan(&ctx, &self.path).await;
+
+		Ok(())
+	}
+}
+
+#[derive(Debug)]
+pub struct SeriesScannerJob {
+	pub series_id: String,
+}
+
+#[async_trait::async_trait]
+impl Job for SeriesScannerJob {
+	async fn run(&self, runner_id: String, ctx: Context) -> Result<(), ApiError> {
+		let start = std::time::Instant::now();
+		scan_single_series(
+			ctx.get_ctx(),
+			self.series_id.clone(),
+			runner_id.clone(),
+			ScanTrigger::Manual,
+		)
+		.await?;
+		let duration = start.elapsed();
+
+		log::info!(
+			"Finished series scan in {}.{:03} seconds",
+			duration.as_secs(),
+			duration.subsec_millis()
+		);
+
+		let series = ctx
+			.db
+			.series()
+			.find_unique(series::id::equals(self.series_id.clone()))
+			.exec()
+			.await?;
+
+		if let Some(series) = series {
+			after_scan(&ctx, &series.path).await;
+		}
+
+		Ok(())
+	}
+}
+
+/// Scans the series a path belongs to, or the series nested under it. See `scan_path`.
+#[derive(Debug)]
+pub struct PathScannerJob {
+	pub path: String,
+}
+
+#[async_trait::async_trait]
+impl Job for PathScannerJob {
+	async fn run(&self, runner_id: String, ctx: Context) -> Result<(), ApiError> {
+		let start = std::time::Instant::now();
+		scan_path(
+			ctx.get_ctx(),
+			self.path.clone(),
+			runner_id.clone(),
+			ScanTrigger::Manual,
+		)
+		.await?;
+		let duration = start.elapsed();
+
+		log::info!(
+			"Finished scan of {} in {}.{:03} seconds",
+			self.path,
+			duration.as_secs(),
+			duration.subsec_millis()
+		);
+
+		after_scan(&ctx, &self.path).await;
+
+		Ok(())
+	}
+}
+
+/// Generates a report of what scanning a library would do, without changing anything, and
+/// keeps it to be fetched later (see `ScanReports`).
+#[derive(Debug)]
+pub struct ScanReportJob {
+	pub library_id: String,
+}
+
+#[async_trait::async_trait]
+impl Job for ScanReportJob {
+	async fn run(&self, _runner_id: String, ctx: Context) -> Result<(), ApiError> {
+		let start = std::time::Instant::now();
+		let report = scan_report(&ctx, self.library_id.clone()).await?;
+		let duration = start.elapsed();
+
+		log::info!(
+			"Generated scan report for library {} in {}.{:03} seconds",
+			self.library_id,
+			duration.as_secs(),
+			duration.subsec_millis()
+		);
+
+		ctx.scan_reports.insert(report);
+
 		Ok(())
 	}
 }
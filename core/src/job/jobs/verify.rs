@@ -0,0 +1,124 @@
+use prisma_client_rust::chrono::{DateTime, FixedOffset, Utc};
+use rocket::tokio;
+use std::path::Path;
+
+use super::Job;
+
+use crate::{
+	config::context::Context,
+	fs::media_file,
+	prisma::media,
+	types::{
+		enums::FileStatus,
+		errors::{ApiError, ProcessFileError},
+		event::ClientEvent,
+	},
+};
+
+/// Reads every media in full (see `media_file::verify_media`). Media which can't be read are
+/// marked with an ERROR status and the reason why, while media which were marked before and
+/// can be read now are marked READY again. Missing media are left to the scanner.
+#[derive(Debug)]
+pub struct MediaVerificationJob;
+
+#[async_trait::async_trait]
+impl Job for MediaVerificationJob {
+	async fn run(&self, runner_id: String, ctx: Context) -> Result<(), ApiError> {
+		let pending = ctx
+			.db
+			.media()
+			.find_many(vec![])
+			.exec()
+			.await?
+			.into_iter()
+			.filter(|media| media.status != "MISSING")
+			.collect::<Vec<media::Data>>();
+
+		let total = pending.len() as u64;
+
+		let _ = ctx.emit_client_event(ClientEvent::job_started(
+			runner_id.clone(),
+			0,
+			total,
+			Some(format!("Verifying {} media", total)),
+		));
+
+		let (mut verified, mut errored) = (0, 0);
+
+		for (index, media) in pending.into_iter().enumerate() {
+			let _ = ctx.emit_client_event(ClientEvent::job_progress(
+				runner_id.clone(),
+				index as u64,
+				total,
+				Some(format!("Verifying {}", media.path)),
+			));
+
+			if !Path::new(&media.path).exists() {
+				log::debug!("Skipping missing media {}", media.path);
+				continue;
+			}
+
+			let path = media.path.clone();
+			let result =
+				tokio::task::spawn_blocking(move || media_file::verify_media(&path))
+					.await
+					.map_err(|e| ApiError::InternalServerError(e.to_string()))?;
+
+			let now: DateTime<FixedOffset> = Utc::now().into();
+
+			let update = match result {
+				Ok(_) if media.status == "ERROR" => vec![
+					media::status::set(FileStatus::Ready.into()),
+					media::status_reason::set(None),
+					media::verified_at::set(Some(now)),
+				],
+				Ok(_) => vec![media::verified_at::set(Some(now))],
+				// Without pdfium, pdfs can't be read at all, which says nothing about the file.
+				Err(ProcessFileError::PdfiumLoadError(e)) => {
+					log::warn!(
+						"Skipping {}, pdfium could not be loaded: {}",
+						media.path,
+						e
+					);
+					continue;
+				},
+				Err(e) => {
+					log::error!("Media {} is corrupt: {}", media.path, e);
+
+					errored += 1;
+
+					vec![
+						media::status::set(FileStatus::Error.into()),
+						media::status_reason::set(Some(e.to_string())),
+						media::verified_at::set(Some(now)),
+					]
+				},
+			};
+
+			let status_changed = update.len() > 1;
+
+			let updated = ctx
+				.db
+				.media()
+				.find_unique(media::id::equals(media.id))
+				.update(update)
+				.exec()
+				.await?;
+
+			verified += 1;
+
+			if let (true, Some(updated)) = (status_changed, updated) {
+				let _ = ctx.emit_client_event(ClientEvent::UpdatedMedia(updated));
+			}
+		}
+
+		log::info!(
+			"Verified {} of {} media, {} of which are corrupt",
+			verified,
+			total,
+			errored
+		);
+
+		Ok(())
+	}
+}
@@ -2,6 +2,7 @@
 extern crate rocket;
 
 use db::migration::run_migrations;
+use fs::scanner::history::fail_interrupted_scans;
 
 use config::{context::Context, cors, env, helmet::Helmet, logging, session};
 use rocket::{
@@ -76,6 +77,10 @@ async fn rocket() -> _ {
 	match run_migrations(route_ctx.get_db()).await {
 		Ok(_) => {
 			log::info!("Migrations ran successfully");
+
+			if let Err(e) = fail_interrupted_scans(route_ctx.get_db()).await {
+				log::error!("Failed to mark interrupted scans as failed: {:?}", e);
+			}
 		},
 		Err(e) => {
 			log::error!("Failed to run migrations: {:?}", e);
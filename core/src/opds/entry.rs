@@ -163,7 +163,7 @@ impl From<media::Data> for OpdsEntry {
 				format!("{}/pages/1", base_url),
 			),
 			OpdsLink::new(
-				OpdsLinkType::Zip,
+				OpdsLinkType::from_extension(&m.extension),
 				OpdsLinkRel::Acquisition,
 				format!("{}/file/{}", base_url, file_name_encoded),
 			),
@@ -196,13 +196,25 @@ impl From<media::Data> for OpdsEntry {
 			None => Some(format!("{:.1} MiB - {}", mib, m.extension)),
 		};
 
+		// The metadata relation is optional, and likely not loaded for every feed.
+		let authors = match m.metadata() {
+			Ok(Some(metadata)) => metadata.writer.as_ref().map(|writers| {
+				writers
+					.split(',')
+					.map(|writer| writer.trim().to_string())
+					.filter(|writer| !writer.is_empty())
+					.collect::<Vec<String>>()
+			}),
+			_ => None,
+		};
+
 		OpdsEntry {
 			id: m.id.to_string(),
 			title: m.name,
 			updated: chrono::Utc::now().into(),
 			content,
 			links,
-			authors: None,
+			authors,
 			stream_link: Some(stream_link),
 		}
 	}
@@ -10,10 +10,37 @@ pub enum OpdsLinkType {
 	Navigation,  // "application/atom+xml;profile=opds-catalog;kind=navigation",
 	OctetStream, // "application/octet-stream",
 	Zip,         // "application/zip"
+	Cbz,         // "application/vnd.comicbook+zip"
+	Rar,         // "application/vnd.rar"
+	Cbr,         // "application/vnd.comicbook-rar"
+	SevenZip,    // "application/x-7z-compressed"
+	Cb7,         // "application/x-cb7"
+	Tar,         // "application/x-tar"
+	Cbt,         // "application/x-cbt"
+	Pdf,         // "application/pdf"
 	Epub,        // "application/epub+zip"
 	Search,      // "application/opensearchdescription+xml"
 }
 
+impl OpdsLinkType {
+	/// The type of an acquisition link for a book, going by its file extension.
+	pub fn from_extension(extension: &str) -> Self {
+		match extension.to_lowercase().as_str() {
+			"zip" => OpdsLinkType::Zip,
+			"cbz" => OpdsLinkType::Cbz,
+			"rar" => OpdsLinkType::Rar,
+			"cbr" => OpdsLinkType::Cbr,
+			"7z" => OpdsLinkType::SevenZip,
+			"cb7" => OpdsLinkType::Cb7,
+			"tar" => OpdsLinkType::Tar,
+			"cbt" => OpdsLinkType::Cbt,
+			"pdf" => OpdsLinkType::Pdf,
+			"epub" => OpdsLinkType::Epub,
+			_ => OpdsLinkType::OctetStream,
+		}
+	}
+}
+
 impl OpdsEnumStr for OpdsLinkType {
 	fn as_str(&self) -> &'static str {
 		match self {
@@ -26,6 +53,14 @@ impl OpdsEnumStr for OpdsLinkType {
 			},
 			OpdsLinkType::OctetStream => "application/octet-stream",
 			OpdsLinkType::Zip => "application/zip",
+			OpdsLinkType::Cbz => "application/vnd.comicbook+zip",
+			OpdsLinkType::Rar => "application/vnd.rar",
+			OpdsLinkType::Cbr => "application/vnd.comicbook-rar",
+			OpdsLinkType::SevenZip => "application/x-7z-compressed",
+			OpdsLinkType::Cb7 => "application/x-cb7",
+			OpdsLinkType::Tar => "application/x-tar",
+			OpdsLinkType::Cbt => "application/x-cbt",
+			OpdsLinkType::Pdf => "application/pdf",
 			OpdsLinkType::Epub => "application/epub+zip",
 			OpdsLinkType::Search => "application/opensearchdescription+xml",
 		}
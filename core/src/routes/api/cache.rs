@@ -0,0 +1,21 @@
+use rocket::serde::json::Json;
+use rocket_okapi::openapi;
+
+use crate::{
+	guards::auth::AdminGuard,
+	types::{
+		alias::{ApiResult, Context},
+		models::cache::CacheStats,
+	},
+};
+
+/// Get how the in-memory cache of pages and open archives is doing since the server
+/// started, e.g. how many pages were served from it and how much memory it takes up.
+#[openapi(tag = "Cache")]
+#[get("/cache")]
+pub async fn get_cache_stats(
+	ctx: &Context,
+	_auth: AdminGuard,
+) -> ApiResult<Json<CacheStats>> {
+	Ok(Json(ctx.cache.stats()))
+}
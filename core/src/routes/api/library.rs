@@ -7,29 +7,38 @@ use serde::Deserialize;
 
 use crate::{
 	db::utils::{FindManyTrait, PrismaClientTrait},
-	fs::{self},
+	fs,
 	guards::auth::{AdminGuard, Auth},
-	job::jobs::scan::LibraryScannerJob,
+	job::jobs::scan::{LibraryScannerJob, PathScannerJob, ScanReportJob},
 	prisma::{
-		library, media,
+		library, library_scan, media,
 		series::{self, OrderByParam},
 		tag,
 	},
 	types::{
 		alias::{ApiResult, Context},
+		enums::{FileStatus, ScanTrigger},
 		errors::ApiError,
+		event::InternalEvent,
 		http::ImageResponse,
-		models::{library::Library, series::Series, tag::Tag},
+		models::{
+			library::{join_ignore_patterns, Library, LibraryLayoutMode},
+			scan::{LibraryScan, ScanReport},
+			series::Series,
+			tag::Tag,
+		},
 		pageable::{PageParams, Pageable, PagedRequestParams},
 		query::QueryOrder,
 	},
 };
 
 /// Get the libraries accessible by the current user. Library `tags` relation is loaded
-/// on this route.
+/// on this route. Optional query param `status` will only return libraries with that
+/// status, e.g. "READY" to hide MISSING libraries.
 #[openapi(tag = "Library")]
-#[get("/libraries?<unpaged>&<page_params..>")]
+#[get("/libraries?<status>&<unpaged>&<page_params..>")]
 pub async fn get_libraries(
+	status: Option<String>,
 	unpaged: Option<bool>,
 	page_params: Option<PagedRequestParams>,
 	ctx: &Context,
@@ -37,9 +46,14 @@ pub async fn get_libraries(
 ) -> ApiResult<Json<Pageable<Vec<Library>>>> {
 	let db = ctx.get_db();
 
+	let filters = match FileStatus::from_query(status)? {
+		Some(status) => vec![library::status::equals(status)],
+		None => vec![],
+	};
+
 	let libraries = db
 		.library()
-		.find_many(vec![])
+		.find_many(filters)
 		.with(library::tags::fetch(vec![]))
 		.exec()
 		.await?
@@ -93,11 +107,13 @@ pub async fn get_library_by_id(
 // FIXME: this is absolutely atrocious...
 // This should be much better once https://github.com/Brendonovich/prisma-client-rust/issues/24 is added
 // but for now I will have this disgustingly gross and ugly work around...
-/// Returns the series in a given library. Will *not* load the media relation.
+/// Returns the series in a given library. Will *not* load the media relation. Optional
+/// query param `status` will only return series with that status.
 #[openapi(tag = "Series")]
-#[get("/libraries/<id>/series?<unpaged>&<req_params..>")]
+#[get("/libraries/<id>/series?<status>&<unpaged>&<req_params..>")]
 pub async fn get_library_series(
 	id: String,
+	status: Option<String>,
 	unpaged: Option<bool>,
 	req_params: Option<PagedRequestParams>,
 	ctx: &Context,
@@ -110,10 +126,15 @@ pub async fn get_library_series(
 	let order_by_param: OrderByParam =
 		QueryOrder::from(page_params.clone()).try_into()?;
 
-	let base_query = db
-		.series()
-		.find_many(vec![series::library_id::equals(Some(id.clone()))])
-		.order_by(order_by_param);
+	let status = FileStatus::from_query(status)?;
+
+	let mut filters = vec![series::library_id::equals(Some(id.clone()))];
+
+	if let Some(status) = status.clone() {
+		filters.push(series::status::equals(status));
+	}
+
+	let base_query = db.series().find_many(filters).order_by(order_by_param);
 
 	let series = match unpaged {
 		true => base_query.exec().await?,
@@ -140,7 +161,7 @@ pub async fn get_library_series(
 		return Ok(Json(series.into()));
 	}
 
-	let series_count = db.series_count(id).await?;
+	let series_count = db.series_count(id, status).await?;
 
 	Ok(Json((series, series_count, page_params).into()))
 }
@@ -166,9 +187,21 @@ pub async fn get_library_thumbnail(
 
 	let series = library_series.first().unwrap();
 
+	if let Some(thumbnail_path) = series.thumbnail_path.as_deref() {
+		match fs::media_file::get_image(thumbnail_path) {
+			Ok(image) => return Ok(image),
+			Err(e) => {
+				log::warn!("Failed to read series thumbnail {}: {}", thumbnail_path, e);
+			},
+		}
+	}
+
 	let media = series.media()?.first().unwrap();
 
-	Ok(fs::media_file::get_page(media.path.as_str(), 1)?)
+	Ok(fs::media_file::get_thumbnail(
+		media.path.as_str(),
+		media.thumbnail_path.as_deref(),
+	)?)
 }
 
 /// Queue a ScannerJob to scan the library by id. The job, when started, is
@@ -199,6 +232,7 @@ pub async fn scan_library(
 
 	let job = LibraryScannerJob {
 		path: lib.path.clone(),
+		trigger: ScanTrigger::Manual,
 	};
 
 	ctx.spawn_job(Box::new(job));
@@ -206,7 +240,145 @@ pub async fn scan_library(
 	Ok(())
 }
 
+/// Start generating a report of what scanning the library by id would do, without changing
+/// anything, in the background. Useful for checking what Stump would make of a new library
+/// before it is scanned, e.g. by creating it with `scan` set to `false`. Generating the
+/// report processes every new and changed file, so it takes about as long as a scan. Once it
+/// is done, the report is fetched with `GET /libraries/<id>/scan/report`.
+#[openapi(tag = "Library")]
+#[post("/libraries/<id>/scan/report")]
+pub async fn generate_library_scan_report(
+	id: String,
+	ctx: &Context,
+	_auth: AdminGuard,
+) -> ApiResult<()> {
+	let library = ctx
+		.db
+		.library()
+		.find_unique(library::id::equals(id.clone()))
+		.exec()
+		.await?;
+
+	if library.is_none() {
+		return Err(ApiError::NotFound(format!(
+			"Library with id {} not found",
+			id
+		)));
+	}
+
+	ctx.spawn_job(Box::new(ScanReportJob { library_id: id }));
+
+	Ok(())
+}
+
+/// Get the most recent report of what scanning the library by id would do, as generated by
+/// `POST /libraries/<id>/scan/report`.
+#[openapi(tag = "Library")]
+#[get("/libraries/<id>/scan/report")]
+pub async fn get_library_scan_report(
+	id: String,
+	ctx: &Context,
+	_auth: AdminGuard,
+) -> ApiResult<Json<ScanReport>> {
+	match ctx.scan_reports.get(&id) {
+		Some(report) => Ok(Json(report)),
+		None => Err(ApiError::NotFound(format!(
+			"No scan report has been generated for library {}",
+			id
+		))),
+	}
+}
+
+/// Get the scan history of a library by id, most recent first. Each scan includes the files
+/// which could not be scanned, along with the error for each.
+#[openapi(tag = "Library")]
+#[get("/libraries/<id>/scans?<unpaged>&<page_params..>")]
+pub async fn get_library_scans(
+	id: String,
+	unpaged: Option<bool>,
+	page_params: Option<PagedRequestParams>,
+	ctx: &Context,
+	_auth: Auth,
+) -> ApiResult<Json<Pageable<Vec<LibraryScan>>>> {
+	let db = ctx.get_db();
+
+	let library = db
+		.library()
+		.find_unique(library::id::equals(id.clone()))
+		.exec()
+		.await?;
+
+	if library.is_none() {
+		return Err(ApiError::NotFound(format!(
+			"Library with id {} not found",
+			id
+		)));
+	}
+
+	let scans = db
+		.library_scan()
+		.find_many(vec![library_scan::library_id::equals(id)])
+		.with(library_scan::errors::fetch(vec![]))
+		.order_by(library_scan::started_at::order(Direction::Desc))
+		.exec()
+		.await?
+		.into_iter()
+		.map(|scan| scan.into())
+		.collect::<Vec<LibraryScan>>();
+
+	let unpaged = unpaged.unwrap_or(page_params.is_none());
+
+	if unpaged {
+		return Ok(Json(scans.into()));
+	}
+
+	Ok(Json((scans, page_params).into()))
+}
+
 #[derive(Deserialize, JsonSchema)]
+pub struct ScanPath {
+	/// The path to scan. Must be within a library, ex: "/comics/Marvel/Spider-Man"
+	path: String,
+}
+
+/// Queue a ScannerJob to scan only the series at, or nested under, a path within a library.
+/// Scanning the root of a library is the same as scanning the whole library.
+#[openapi(tag = "Library")]
+#[post("/libraries/scan", data = "<input>")]
+pub async fn scan_library_path(
+	input: Json<ScanPath>,
+	ctx: &Context,
+	_auth: AdminGuard,
+) -> Result<(), ApiError> {
+	let path = Path::new(&input.path);
+
+	let in_library = ctx
+		.db
+		.library()
+		.find_many(vec![])
+		.exec()
+		.await?
+		.iter()
+		.any(|library| path.starts_with(&library.path));
+
+	// The library is checked first, and both cases give the same error, so the endpoint
+	// can't be used to find out which paths exist outside of the libraries.
+	if !in_library || !path.exists() {
+		return Err(ApiError::BadRequest(format!(
+			"No library contains the path {}",
+			input.path
+		)));
+	}
+
+	ctx.spawn_job(Box::new(PathScannerJob {
+		path: input.path.to_owned(),
+	}));
+
+	Ok(())
+}
+
+#[derive(Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
 pub struct CreateLibrary {
 	/// The name of the library to create.
 	name: String,
@@ -216,6 +388,16 @@ pub struct CreateLibrary {
 	description: Option<String>,
 	/// Optional tags to assign to the library.
 	tags: Option<Vec<Tag>>,
+	/// Optional layout mode used to detect series. Default is `TOP_LEVEL_FOLDERS`.
+	layout_mode: Option<LibraryLayoutMode>,
+	/// Optional flag to treat files in the library root as one-shots. Default is `false`.
+	root_files_as_one_shots: Option<bool>,
+	/// Optional flag to follow symbolic links while scanning. Default is `false`.
+	follow_symlinks: Option<bool>,
+	/// Optional gitignore style patterns for files and folders the scanner should skip.
+	ignore_patterns: Option<Vec<String>>,
+	/// Optional flag to treat leaf folders holding only images as books. Default is `false`.
+	folder_books: Option<bool>,
 	/// Optional flag to indicate if the library should be automatically scanned after creation. Default is `true`.
 	scan: Option<bool>,
 }
@@ -243,7 +425,18 @@ pub async fn create_library(
 		.create(
 			library::name::set(input.name.to_owned()),
 			library::path::set(input.path.to_owned()),
-			vec![library::description::set(input.description.to_owned())],
+			vec![
+				library::description::set(input.description.to_owned()),
+				library::layout_mode::set(input.layout_mode.unwrap_or_default().into()),
+				library::root_files_as_one_shots::set(
+					input.root_files_as_one_shots.unwrap_or(false),
+				),
+				library::follow_symlinks::set(input.follow_symlinks.unwrap_or(false)),
+				library::ignore_patterns::set(join_ignore_patterns(
+					&input.ignore_patterns.to_owned().unwrap_or_default(),
+				)),
+				library::folder_books::set(input.folder_books.unwrap_or(false)),
+			],
 		)
 		.exec()
 		.await?;
@@ -264,10 +457,16 @@ pub async fn create_library(
 		}
 	}
 
+	ctx.emit_event(InternalEvent::WatchLibrary(
+		lib.id.clone(),
+		lib.path.clone(),
+	));
+
 	// `scan` is not a required field, however it will default to true if not provided
 	if input.scan.unwrap_or(true) {
 		ctx.spawn_job(Box::new(LibraryScannerJob {
 			path: lib.path.clone(),
+			trigger: ScanTrigger::Create,
 		}));
 	}
 
@@ -287,6 +486,16 @@ pub struct UpdateLibrary {
 	tags: Option<Vec<Tag>>,
 	/// The tags to remove from the library.
 	removed_tags: Option<Vec<Tag>>,
+	/// The updated layout mode of the library.
+	layout_mode: Option<LibraryLayoutMode>,
+	/// Whether files in the library root should be treated as one-shots.
+	root_files_as_one_shots: Option<bool>,
+	/// Whether symbolic links should be followed while scanning.
+	follow_symlinks: Option<bool>,
+	/// The updated ignore patterns of the library.
+	ignore_patterns: Option<Vec<String>>,
+	/// Whether leaf folders holding only images should be treated as books.
+	folder_books: Option<bool>,
 	/// Optional flag to indicate if the library should be automatically scanned after update. Default is `true`.
 	scan: Option<bool>,
 }
@@ -310,12 +519,36 @@ pub async fn update_library(
 		)));
 	}
 
-	let updates: Vec<library::SetParam> = vec![
+	let mut updates: Vec<library::SetParam> = vec![
 		library::name::set(input.name.to_owned()),
 		library::path::set(input.path.to_owned()),
 		library::description::set(input.description.to_owned()),
 	];
 
+	if let Some(layout_mode) = input.layout_mode {
+		updates.push(library::layout_mode::set(layout_mode.into()));
+	}
+
+	if let Some(root_files_as_one_shots) = input.root_files_as_one_shots {
+		updates.push(library::root_files_as_one_shots::set(
+			root_files_as_one_shots,
+		));
+	}
+
+	if let Some(follow_symlinks) = input.follow_symlinks {
+		updates.push(library::follow_symlinks::set(follow_symlinks));
+	}
+
+	if let Some(ignore_patterns) = input.ignore_patterns.as_ref() {
+		updates.push(library::ignore_patterns::set(join_ignore_patterns(
+			ignore_patterns,
+		)));
+	}
+
+	if let Some(folder_books) = input.folder_books {
+		updates.push(library::folder_books::set(folder_books));
+	}
+
 	// FIXME: this is disgusting. I don't understand why the library::tag::link doesn't
 	// work with multiple tags, nor why providing multiple library::tag::link params
 	// doesn't work. Regardless, absolutely do NOT keep this. Correction required,
@@ -387,10 +620,17 @@ pub async fn update_library(
 
 	let updated = updated.unwrap();
 
+	// The path may have changed, so the watcher is always replaced.
+	ctx.emit_event(InternalEvent::WatchLibrary(
+		updated.id.clone(),
+		updated.path.clone(),
+	));
+
 	// `scan` is not a required field, however it will default to true if not provided
 	if input.scan.unwrap_or(true) {
 		ctx.spawn_job(Box::new(LibraryScannerJob {
 			path: updated.path.clone(),
+			trigger: ScanTrigger::Update,
 		}));
 	}
 
@@ -421,5 +661,8 @@ pub async fn delete_library(
 		)));
 	}
 
+	ctx.scan_reports.remove(&id);
+	ctx.emit_event(InternalEvent::UnwatchLibrary(id));
+
 	Ok(Json(deleted.unwrap().into()))
 }
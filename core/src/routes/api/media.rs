@@ -1,30 +1,42 @@
-use prisma_client_rust::{raw, Direction};
+use prisma_client_rust::Direction;
 use rocket::{fs::NamedFile, serde::json::Json};
 use rocket_okapi::openapi;
 
 use crate::{
 	db::utils::{FindManyTrait, PrismaClientTrait},
-	fs,
-	guards::auth::Auth,
+	fs::{self, cover_hash, page_index::PageImage},
+	guards::auth::{AdminGuard, Auth},
+	job::jobs::{
+		checksum::FullChecksumJob, convert::CbzConversionJob, cover_hash::CoverHashJob,
+		verify::MediaVerificationJob,
+	},
 	prisma::{
 		media::{self, OrderByParam},
-		read_progress, user,
+		media_page, read_progress, user,
 	},
 	types::{
 		alias::{ApiResult, Context},
+		enums::FileStatus,
 		errors::ApiError,
 		http::{FileResponse, ImageResponse},
-		models::{media::Media, read_progress::ReadProgress},
+		models::{
+			media::{
+				ChecksumConflict, Media, MediaVerificationReport, SimilarMediaCluster,
+			},
+			read_progress::ReadProgress,
+		},
 		pageable::{PageParams, Pageable, PagedRequestParams},
-		query::QueryOrder,
+		query::{ChecksumKind, QueryOrder},
 	},
 };
 
 /// Get all media accessible to the requester. This is a paginated request, and
-/// has various pagination params available.
+/// has various pagination params available. Optional query param `status` will only
+/// return media with that status.
 #[openapi(tag = "Media")]
-#[get("/media?<unpaged>&<req_params..>")]
+#[get("/media?<status>&<unpaged>&<req_params..>")]
 pub async fn get_media(
+	status: Option<String>,
 	unpaged: Option<bool>,
 	req_params: Option<PagedRequestParams>,
 	ctx: &Context,
@@ -37,9 +49,16 @@ pub async fn get_media(
 	let order_by_param: OrderByParam =
 		QueryOrder::from(page_params.clone()).try_into()?;
 
+	let status = FileStatus::from_query(status)?;
+
+	let filters = match status.clone() {
+		Some(status) => vec![media::status::equals(status)],
+		None => vec![],
+	};
+
 	let base_query = db
 		.media()
-		.find_many(vec![])
+		.find_many(filters)
 		.with(media::read_progresses::fetch(vec![
 			read_progress::user_id::equals(auth.0.id),
 		]))
@@ -57,7 +76,7 @@ pub async fn get_media(
 		));
 	}
 
-	let count = db.media_count().await?;
+	let count = db.media_count(status).await?;
 
 	let media = base_query
 		.paginated(page_params.clone())
@@ -71,10 +90,14 @@ pub async fn get_media(
 }
 
 /// Get all media with identical checksums. This heavily implies duplicate files.  
-/// This is a paginated request, and has various pagination params available.
+/// This is a paginated request, and has various pagination params available. Optional
+/// query param `hash` picks the checksum to compare: `quick` (the default) only covers the
+/// start of each file, while `full` covers all of it, but only once the full checksum job
+/// has run.
 #[openapi(tag = "Media")]
-#[get("/media/duplicates?<unpaged>&<page_params..>")]
+#[get("/media/duplicates?<hash>&<unpaged>&<page_params..>")]
 pub async fn get_duplicate_media(
+	hash: Option<ChecksumKind>,
 	unpaged: Option<bool>,
 	page_params: Option<PagedRequestParams>,
 	ctx: &Context,
@@ -82,9 +105,22 @@ pub async fn get_duplicate_media(
 ) -> ApiResult<Json<Pageable<Vec<Media>>>> {
 	let db = ctx.get_db();
 
+	let kind = hash.unwrap_or_default();
+	let checksums = db.shared_checksums(kind).await?;
+
+	let filter = match kind {
+		ChecksumKind::Quick => media::checksum::in_vec(checksums),
+		ChecksumKind::Full => media::full_checksum::in_vec(checksums),
+	};
+
 	let media: Vec<Media> = db
-		._query_raw(raw!("SELECT * FROM media WHERE checksum IN (SELECT checksum FROM media GROUP BY checksum HAVING COUNT(*) > 1)"))
-		.await?;
+		.media()
+		.find_many(vec![filter])
+		.exec()
+		.await?
+		.into_iter()
+		.map(|m| m.into())
+		.collect();
 
 	let unpaged = unpaged.unwrap_or(page_params.is_none());
 
@@ -94,6 +130,165 @@ pub async fn get_duplicate_media(
 
 	Ok(Json((media, page_params).into()))
 }
+
+/// Start computing the checksum of the entire file of each media, in the background. Only
+/// media without one are checksummed, unless `force` is set.
+#[openapi(tag = "Media")]
+#[post("/media/checksums?<force>")]
+pub async fn compute_full_checksums(
+	force: Option<bool>,
+	ctx: &Context,
+	_auth: AdminGuard,
+) -> ApiResult<()> {
+	ctx.spawn_job(Box::new(FullChecksumJob {
+		force: force.unwrap_or(false),
+	}));
+
+	Ok(())
+}
+
+/// Get the media which share a checksum, but whose full checksums show they aren't the same
+/// file, grouped by their checksum. These would be reported as duplicates by
+/// `/media/duplicates` when comparing by the quick checksum.
+#[openapi(tag = "Media")]
+#[get("/media/checksum-conflicts")]
+pub async fn get_checksum_conflicts(
+	ctx: &Context,
+	_auth: Auth,
+) -> ApiResult<Json<Vec<ChecksumConflict>>> {
+	Ok(Json(ctx.db.checksum_conflicts().await?))
+}
+
+/// Start computing the perceptual hash of the cover of each media, in the background. Only
+/// media without one are hashed, unless `force` is set.
+#[openapi(tag = "Media")]
+#[post("/media/cover-hashes?<force>")]
+pub async fn compute_cover_hashes(
+	force: Option<bool>,
+	ctx: &Context,
+	_auth: AdminGuard,
+) -> ApiResult<()> {
+	ctx.spawn_job(Box::new(CoverHashJob {
+		force: force.unwrap_or(false),
+	}));
+
+	Ok(())
+}
+
+/// Get clusters of media whose covers look alike, which likely are the same book in
+/// different files (e.g. a cbr and a re-encoded cbz). Optional query param `distance` is
+/// the most bits the cover hash of a media may differ in from the first media in its
+/// cluster, out of 64. Only media whose cover hash has been computed are compared.
+#[openapi(tag = "Media")]
+#[get("/media/similar?<distance>")]
+pub async fn get_similar_media(
+	distance: Option<u32>,
+	ctx: &Context,
+	_auth: Auth,
+) -> ApiResult<Json<Vec<SimilarMediaCluster>>> {
+	let distance = distance.unwrap_or(cover_hash::DEFAULT_MAX_DISTANCE);
+
+	if distance > 64 {
+		return Err(ApiError::BadRequest(format!(
+			"Invalid distance {}, cover hashes are 64 bits",
+			distance
+		)));
+	}
+
+	let media: Vec<Media> = ctx
+		.db
+		.media()
+		.find_many(vec![media::cover_hash::not(None)])
+		.exec()
+		.await?
+		.into_iter()
+		.map(|m| m.into())
+		.collect();
+
+	let (media, hashes): (Vec<Media>, Vec<u64>) = media
+		.into_iter()
+		.filter_map(|m| {
+			let hash = m.cover_hash.as_deref().and_then(cover_hash::parse_hash)?;
+			Some((m, hash))
+		})
+		.unzip();
+
+	let mut media = media.into_iter().map(Some).collect::<Vec<Option<Media>>>();
+
+	let clusters = cover_hash::cluster_hashes(&hashes, distance)
+		.into_iter()
+		.map(|cluster| {
+			let max_distance = cluster
+				.iter()
+				.flat_map(|a| cluster.iter().map(|b| hashes[*a] ^ hashes[*b]))
+				.map(|difference| difference.count_ones())
+				.max()
+				.unwrap_or_default();
+
+			let mut cluster_media = cluster
+				.into_iter()
+				.filter_map(|index| media[index].take())
+				.collect::<Vec<Media>>();
+
+			cluster_media.sort_by_key(|m| m.size);
+
+			SimilarMediaCluster {
+				max_distance,
+				media: cluster_media,
+			}
+		})
+		.collect();
+
+	Ok(Json(clusters))
+}
+
+/// Start reading every media in full, in the background, to find corrupt files. Media which
+/// can't be read are given an ERROR status, with the reason in their `statusReason`.
+#[openapi(tag = "Media")]
+#[post("/media/verify")]
+pub async fn verify_media(ctx: &Context, _auth: AdminGuard) -> ApiResult<()> {
+	ctx.spawn_job(Box::new(MediaVerificationJob));
+
+	Ok(())
+}
+
+/// Get the results of the last verification of each media: how many were read without
+/// problems or haven't been verified yet, and which could not be read and why.
+#[openapi(tag = "Media")]
+#[get("/media/verification")]
+pub async fn get_media_verification(
+	ctx: &Context,
+	_auth: AdminGuard,
+) -> ApiResult<Json<MediaVerificationReport>> {
+	let media = ctx
+		.db
+		.media()
+		.find_many(vec![])
+		.order_by(media::path::order(Direction::Asc))
+		.exec()
+		.await?;
+
+	let mut report = MediaVerificationReport {
+		verified: 0,
+		unverified: 0,
+		errored: vec![],
+	};
+
+	for media in media {
+		if media.status == "ERROR" {
+			report.errored.push(media.into());
+		} else if media.status == "MISSING" {
+			continue;
+		} else if media.verified_at.is_some() {
+			report.verified += 1;
+		} else {
+			report.unverified += 1;
+		}
+	}
+
+	Ok(Json(report))
+}
+
 // TODO: I will need to add epub progress in here SOMEHOW... this will be rather
 // difficult...
 // TODO: paginate?
@@ -158,6 +353,7 @@ pub async fn get_media_by_id(
 		.with(media::read_progresses::fetch(vec![
 			read_progress::user_id::equals(auth.0.id),
 		]))
+		.with(media::metadata::fetch())
 		.exec()
 		.await?;
 
@@ -227,15 +423,18 @@ pub async fn convert_media_to_cbz(
 
 	let media = media.unwrap();
 
-	if media.extension != "cbr" {
+	if !media.extension.eq_ignore_ascii_case("cbr") {
 		return Err(ApiError::BadRequest(format!(
 			"Media with id {} is not a cbr file. Only cbr files can be converted to cbz",
 			id
 		)));
 	}
 
-	// TODO: write me...
-	unimplemented!()
+	ctx.spawn_job(Box::new(CbzConversionJob {
+		media_ids: vec![media.id],
+	}));
+
+	Ok(())
 }
 
 #[openapi(tag = "Media")]
@@ -266,7 +465,17 @@ pub async fn get_media_page(
 					id, book.pages
 				)))
 			} else {
-				Ok(fs::media_file::get_page(&book.path, page)?)
+				let image = db
+					.media_page()
+					.find_first(vec![
+						media_page::media_id::equals(book.id),
+						media_page::number::equals(page),
+					])
+					.exec()
+					.await?
+					.map(PageImage::from);
+
+				Ok(ctx.cache.get_page(&book.path, page, image.as_ref())?)
 			}
 		},
 		None => Err(ApiError::NotFound(format!(
@@ -303,7 +512,10 @@ pub async fn get_media_thumbnail(
 
 	let book = book.unwrap();
 
-	Ok(fs::media_file::get_page(book.path.as_str(), 1)?)
+	Ok(fs::media_file::get_thumbnail(
+		book.path.as_str(),
+		book.thumbnail_path.as_deref(),
+	)?)
 }
 
 // FIXME: this doesn't really handle certain errors correctly, e.g. media/user not found
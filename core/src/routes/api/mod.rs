@@ -8,6 +8,7 @@ use crate::{
 };
 
 pub mod auth;
+pub mod cache;
 pub mod epub;
 pub mod filesystem;
 pub mod job;
@@ -40,6 +41,10 @@ pub fn api() -> Vec<Route> {
 		library::get_library_by_id,
 		library::get_library_series,
 		library::scan_library,
+		library::scan_library_path,
+		library::generate_library_scan_report,
+		library::get_library_scan_report,
+		library::get_library_scans,
 		library::create_library,
 		library::update_library,
 		library::delete_library,
@@ -47,6 +52,7 @@ pub fn api() -> Vec<Route> {
 		series::get_series,
 		series::get_series_by_id,
 		series::get_series_thumbnail,
+		series::scan_series,
 		series::get_series_media,
 		series::series_next_media,
 		// media api
@@ -58,6 +64,12 @@ pub fn api() -> Vec<Route> {
 		media::get_media_thumbnail,
 		media::update_media_progress,
 		media::get_duplicate_media,
+		media::compute_full_checksums,
+		media::get_checksum_conflicts,
+		media::compute_cover_hashes,
+		media::get_similar_media,
+		media::verify_media,
+		media::get_media_verification,
 		// epub api
 		epub::get_epub,
 		epub::get_epub_chatper,
@@ -70,6 +82,8 @@ pub fn api() -> Vec<Route> {
 		// log api
 		log::clear_logs,
 		log::get_log_info,
+		// cache api
+		cache::get_cache_stats,
 	]
 }
 
@@ -6,9 +6,11 @@ use crate::{
 	db::migration::CountQueryReturn,
 	fs,
 	guards::auth::Auth,
+	job::jobs::scan::SeriesScannerJob,
 	prisma::{media, read_progress, series},
 	types::{
 		alias::{ApiResult, Context},
+		enums::FileStatus,
 		errors::ApiError,
 		http::ImageResponse,
 		models::{media::Media, series::Series},
@@ -17,11 +19,13 @@ use crate::{
 };
 
 /// Get all series accessible by user. This is a paginated respone, and
-/// accepts various paginated request params.
+/// accepts various paginated request params. Optional query param `status` will only
+/// return series with that status.
 #[openapi(tag = "Series")]
-#[get("/series?<load_media>&<unpaged>&<page_params..>")]
+#[get("/series?<load_media>&<status>&<unpaged>&<page_params..>")]
 pub async fn get_series(
 	load_media: Option<bool>,
+	status: Option<String>,
 	unpaged: Option<bool>,
 	page_params: Option<PagedRequestParams>,
 	ctx: &Context,
@@ -31,8 +35,13 @@ pub async fn get_series(
 
 	let load_media = load_media.unwrap_or(false);
 
+	let filters = match FileStatus::from_query(status)? {
+		Some(status) => vec![series::status::equals(status)],
+		None => vec![],
+	};
+
 	let action = db.series();
-	let action = action.find_many(vec![]);
+	let action = action.find_many(filters);
 
 	let query = match load_media {
 		true => action.with(
@@ -123,6 +132,21 @@ pub async fn get_series_thumbnail(
 ) -> ApiResult<ImageResponse> {
 	let db = ctx.get_db();
 
+	let series = db
+		.series()
+		.find_unique(series::id::equals(id.clone()))
+		.exec()
+		.await?;
+
+	if let Some(thumbnail_path) = series.and_then(|s| s.thumbnail_path) {
+		match fs::media_file::get_image(&thumbnail_path) {
+			Ok(image) => return Ok(image),
+			Err(e) => {
+				log::warn!("Failed to read series thumbnail {}: {}", thumbnail_path, e);
+			},
+		}
+	}
+
 	let media = db
 		.media()
 		.find_first(vec![media::series_id::equals(Some(id.clone()))])
@@ -139,15 +163,45 @@ pub async fn get_series_thumbnail(
 
 	let media = media.unwrap();
 
-	Ok(fs::media_file::get_page(media.path.as_str(), 1)?)
+	Ok(fs::media_file::get_thumbnail(
+		media.path.as_str(),
+		media.thumbnail_path.as_deref(),
+	)?)
+}
+
+/// Queue a ScannerJob to scan only the series by id. The job, when started, is
+/// executed in a separate thread.
+#[openapi(tag = "Series")]
+#[post("/series/<id>/scan")]
+pub async fn scan_series(id: String, ctx: &Context, _auth: Auth) -> Result<(), ApiError> {
+	let db = ctx.get_db();
+
+	let series = db
+		.series()
+		.find_unique(series::id::equals(id.clone()))
+		.exec()
+		.await?;
+
+	if series.is_none() {
+		return Err(ApiError::NotFound(format!(
+			"Series with id {} not found",
+			id
+		)));
+	}
+
+	ctx.spawn_job(Box::new(SeriesScannerJob { series_id: id }));
+
+	Ok(())
 }
 
 /// Returns the media in a given series. This is a paginated respone, and
-/// accepts various paginated request params.
+/// accepts various paginated request params. Optional query param `status` will only
+/// return media with that status.
 #[openapi(tag = "Series")]
-#[get("/series/<id>/media?<unpaged>&<page_params..>")]
+#[get("/series/<id>/media?<status>&<unpaged>&<page_params..>")]
 pub async fn get_series_media(
 	id: String,
+	status: Option<String>,
 	unpaged: Option<bool>,
 	page_params: Option<PagedRequestParams>,
 	ctx: &Context,
@@ -155,13 +209,20 @@ pub async fn get_series_media(
 ) -> ApiResult<Json<Pageable<Vec<Media>>>> {
 	let db = ctx.get_db();
 
+	let mut filters = vec![media::series_id::equals(Some(id))];
+
+	if let Some(status) = FileStatus::from_query(status)? {
+		filters.push(media::status::equals(status));
+	}
+
 	let media = db
 		.media()
-		.find_many(vec![media::series_id::equals(Some(id))])
+		.find_many(filters)
 		.with(media::read_progresses::fetch(vec![
 			read_progress::user_id::equals(auth.0.id),
 		]))
 		.order_by(media::name::order(Direction::Asc))
+		.with(media::metadata::fetch())
 		.exec()
 		.await?
 		.into_iter()
@@ -2,7 +2,7 @@ use prisma_client_rust::{chrono, Direction};
 use rocket::Route;
 
 use crate::{
-	fs,
+	fs::{self, page_index::PageImage},
 	guards::auth::Auth,
 	opds::{
 		self,
@@ -12,7 +12,7 @@ use crate::{
 		models::OpdsSeries,
 		opensearch::OpdsOpenSearch,
 	},
-	prisma::{self, library, media, read_progress},
+	prisma::{self, library, media, media_page, read_progress},
 	types::{
 		alias::{ApiResult, Context},
 		errors::ApiError,
@@ -172,6 +172,7 @@ async fn keep_reading(ctx: &Context, auth: Auth) -> ApiResult<XmlResponse> {
 			read_progress::user_id::equals(user_id),
 			read_progress::page::gt(0),
 		]))
+		.with(media::metadata::fetch())
 		.order_by(media::name::order(Direction::Asc))
 		.exec()
 		.await?
@@ -345,11 +346,12 @@ async fn series_by_id(
 		.find_unique(prisma::series::id::equals(id.clone()))
 		.with(
 			prisma::series::media::fetch(vec![])
-				.order_by(media::name::order(Direction::Asc)), // Note: I really wanted to be able to just paginate the query here,
-			                                                // but I need to be able to determine whether or not the series has more media
-			                                                // in the below logic for the OPDS feed.
-			                                                // .skip(start.try_into()?)
-			                                                // .take(end.try_into()?),
+				.order_by(media::name::order(Direction::Asc))
+				.with(media::metadata::fetch()), // Note: I really wanted to be able to just paginate the query here,
+			                                  // but I need to be able to determine whether or not the series has more media
+			                                  // in the below logic for the OPDS feed.
+			                                  // .skip(start.try_into()?)
+			                                  // .take(end.try_into()?),
 		)
 		.exec()
 		.await?;
@@ -400,7 +402,10 @@ async fn book_thumbnail(
 		.await?;
 
 	if let Some(b) = book {
-		Ok(fs::media_file::get_page(&b.path, 1)?)
+		Ok(fs::media_file::get_thumbnail(
+			&b.path,
+			b.thumbnail_path.as_deref(),
+		)?)
 	} else {
 		Err(ApiError::NotFound(format!("Book {} not found", &id)))
 	}
@@ -436,7 +441,19 @@ async fn book_page(
 			correct_page = 0;
 		}
 
-		Ok(fs::media_file::get_page(&b.path, correct_page as i32)?)
+		let page = correct_page as i32;
+
+		let image = db
+			.media_page()
+			.find_first(vec![
+				media_page::media_id::equals(b.id),
+				media_page::number::equals(page),
+			])
+			.exec()
+			.await?
+			.map(PageImage::from);
+
+		Ok(ctx.cache.get_page(&b.path, page, image.as_ref())?)
 	} else {
 		Err(ApiError::NotFound(format!("Book {} not found", &id)))
 	}
@@ -1,3 +1,7 @@
+use std::str::FromStr;
+
+use crate::types::errors::ApiError;
+
 pub enum UserRole {
 	ServerOwner,
 	Member,
@@ -17,3 +21,65 @@ impl Into<String> for UserRole {
 		}
 	}
 }
+
+/// The status of a library, series or media, as of the last scan or access.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FileStatus {
+	Ready,
+	Missing,
+	/// The media could not be read, see its `status_reason`.
+	Error,
+}
+
+impl FileStatus {
+	/// Validates the optional `status` query param used to filter list endpoints.
+	pub fn from_query(status: Option<String>) -> Result<Option<String>, ApiError> {
+		status
+			.map(|status| FileStatus::from_str(&status).map(|status| status.into()))
+			.transpose()
+	}
+}
+
+impl FromStr for FileStatus {
+	type Err = ApiError;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s.to_uppercase().as_str() {
+			"READY" => Ok(FileStatus::Ready),
+			"MISSING" => Ok(FileStatus::Missing),
+			"ERROR" => Ok(FileStatus::Error),
+			_ => Err(ApiError::BadRequest(format!("Invalid status: {}", s))),
+		}
+	}
+}
+
+impl Into<String> for FileStatus {
+	fn into(self) -> String {
+		match self {
+			FileStatus::Ready => "READY".to_string(),
+			FileStatus::Missing => "MISSING".to_string(),
+			FileStatus::Error => "ERROR".to_string(),
+		}
+	}
+}
+
+/// What started a library scan.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ScanTrigger {
+	/// A user requested the scan.
+	Manual,
+	/// The library was just created.
+	Create,
+	/// The library was just updated.
+	Update,
+}
+
+impl Into<String> for ScanTrigger {
+	fn into(self) -> String {
+		match self {
+			ScanTrigger::Manual => "MANUAL".to_string(),
+			ScanTrigger::Create => "CREATE".to_string(),
+			ScanTrigger::Update => "UPDATE".to_string(),
+		}
+	}
+}
@@ -28,10 +28,16 @@ pub enum ProcessFileError {
 	EpubOpenError(String),
 	#[error("Error while attempting to read .epub file: {0}")]
 	EpubReadError(String),
-	// #[error("Error while attempting to read .pdf file")]
-	// PdfReadError(#[from] pdf_rs::PdfError),
+	#[error("Error while attempting to read .pdf file: {0}")]
+	PdfReadError(String),
+	#[error("Unable to load pdfium: {0}")]
+	PdfiumLoadError(String),
+	#[error("Error while attempting to read 7z file: {0}")]
+	SevenZipReadError(String),
 	#[error("Could not find an image")]
 	NoImageError,
+	#[error("{0} is corrupt: {1}")]
+	CorruptPageError(String, String),
 	#[error("Could not open rar file")]
 	RarOpenError,
 	#[error("Error reading file content in rar")]
@@ -40,6 +46,8 @@ pub enum ProcessFileError {
 	RarByteReadError(#[from] std::str::Utf8Error),
 	#[error("Unsupported file type: {0}")]
 	UnsupportedFileType(String),
+	#[error("Failed to convert file: {0}")]
+	ConversionError(String),
 	#[error("An unknown error occurred: {0}")]
 	Unknown(String),
 }
@@ -234,6 +242,12 @@ impl From<ProcessFileError> for ApiError {
 	}
 }
 
+impl From<ScanError> for ApiError {
+	fn from(error: ScanError) -> ApiError {
+		ApiError::InternalServerError(error.to_string())
+	}
+}
+
 impl From<anyhow::Error> for ApiError {
 	fn from(error: anyhow::Error) -> ApiError {
 		ApiError::InternalServerError(error.to_string())
@@ -13,6 +13,11 @@ pub enum InternalEvent {
 	QueueJob(Box<dyn Job>),
 	JobComplete(String),
 	JobFailed(String, ApiError),
+	/// Start watching a library for filesystem changes. Contains the library id and path.
+	/// If the library is already being watched, the existing watcher is replaced.
+	WatchLibrary(String, String),
+	/// Stop watching a library for filesystem changes. Contains the library id.
+	UnwatchLibrary(String),
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -34,6 +39,9 @@ pub enum ClientEvent {
 	JobComplete(String),
 	CreatedMedia(prisma::media::Data),
 	CreatedSeries(prisma::series::Data),
+	UpdatedMedia(prisma::media::Data),
+	UpdatedSeries(prisma::series::Data),
+	UpdatedLibrary(prisma::library::Data),
 }
 
 impl ClientEvent {
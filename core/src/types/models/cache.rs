@@ -0,0 +1,31 @@
+use rocket_okapi::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// How the in-memory cache of pages and open archives is doing since the server started.
+/// Sizes are in bytes.
+#[derive(Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CacheStats {
+	/// The number of pages served from the cache.
+	pub page_hits: u64,
+	/// The number of pages which had to be read from disk.
+	pub page_misses: u64,
+	/// The number of pages evicted, either to make room or because their file changed.
+	pub page_evictions: u64,
+	/// The number of pages currently cached.
+	pub page_count: u64,
+	/// The combined size of the pages currently cached.
+	pub size: u64,
+	/// The most the pages in the cache may add up to.
+	pub max_size: u64,
+	/// The number of times an open archive was reused.
+	pub archive_hits: u64,
+	/// The number of times an archive had to be opened.
+	pub archive_misses: u64,
+	/// The number of open archives closed, either to make room or because their file changed.
+	pub archive_evictions: u64,
+	/// The number of archives currently open.
+	pub archive_count: u64,
+	/// The most archives which may be open at once.
+	pub max_archives: u64,
+}
@@ -1,11 +1,74 @@
 // use prisma_client_rust::chrono::{DateTime, FixedOffset};
 use rocket_okapi::JsonSchema;
 use serde::{Deserialize, Serialize};
+use std::str::FromStr;
 
-use crate::prisma;
+use crate::{prisma, types::errors::ApiError};
 
 use super::{series::Series, tag::Tag};
 
+/// How series are detected within a library.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize, JsonSchema)]
+pub enum LibraryLayoutMode {
+	/// Every top level folder is a series. Nested folders are 'folded' into the series
+	/// represented by the top level folder. ex: "Series/Volume 1/Chapter 1.cbz"
+	#[serde(rename = "TOP_LEVEL_FOLDERS")]
+	TopLevelFolders,
+	/// Every folder which directly contains media is a series, no matter how deeply it is
+	/// nested. ex: "Publisher/Series/Issue 1.cbz"
+	#[serde(rename = "LEAF_FOLDERS")]
+	LeafFolders,
+}
+
+impl Default for LibraryLayoutMode {
+	fn default() -> Self {
+		LibraryLayoutMode::TopLevelFolders
+	}
+}
+
+impl FromStr for LibraryLayoutMode {
+	type Err = ApiError;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s {
+			"TOP_LEVEL_FOLDERS" => Ok(LibraryLayoutMode::TopLevelFolders),
+			"LEAF_FOLDERS" => Ok(LibraryLayoutMode::LeafFolders),
+			_ => Err(ApiError::BadRequest(format!(
+				"Invalid library layout mode: {}",
+				s
+			))),
+		}
+	}
+}
+
+impl Into<String> for LibraryLayoutMode {
+	fn into(self) -> String {
+		match self {
+			LibraryLayoutMode::TopLevelFolders => "TOP_LEVEL_FOLDERS".to_string(),
+			LibraryLayoutMode::LeafFolders => "LEAF_FOLDERS".to_string(),
+		}
+	}
+}
+
+/// Ignore patterns are stored as a single newline separated string, the same as they
+/// would appear in a `.stumpignore` file.
+pub fn parse_ignore_patterns(patterns: Option<&str>) -> Vec<String> {
+	patterns
+		.unwrap_or_default()
+		.lines()
+		.map(|line| line.trim())
+		.filter(|line| !line.is_empty())
+		.map(|line| line.to_string())
+		.collect()
+}
+
+pub fn join_ignore_patterns(patterns: &[String]) -> Option<String> {
+	match patterns.is_empty() {
+		true => None,
+		false => Some(patterns.join("\n")),
+	}
+}
+
 #[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct Library {
@@ -18,6 +81,17 @@ pub struct Library {
 	pub path: String,
 	/// The status of the library since last scan or access. ex: "READY" or "MISSING"
 	pub status: String,
+	/// How series are detected within the library.
+	pub layout_mode: LibraryLayoutMode,
+	/// Whether files directly in the library root are each treated as a one-shot series.
+	pub root_files_as_one_shots: bool,
+	/// Whether symbolic links are followed while scanning the library.
+	pub follow_symlinks: bool,
+	/// Gitignore style patterns for files and folders the scanner should skip, in addition
+	/// to any `.stumpignore` files in the library. ex: ["@eaDir", "_extras/"]
+	pub ignore_patterns: Vec<String>,
+	/// Whether leaf folders holding only images are each treated as a single book.
+	pub folder_books: bool,
 	// The date in which the library was last updated. This is usually after a scan. ex: "2022-04-20 04:20:69"
 	pub updated_at: String,
 	/// The series that are in this library. Will be `None` only if the relation is not loaded.
@@ -50,6 +124,12 @@ impl Into<Library> for prisma::library::Data {
 			description: self.description,
 			path: self.path,
 			status: self.status,
+			layout_mode: LibraryLayoutMode::from_str(&self.layout_mode)
+				.unwrap_or_default(),
+			root_files_as_one_shots: self.root_files_as_one_shots,
+			follow_symlinks: self.follow_symlinks,
+			ignore_patterns: parse_ignore_patterns(self.ignore_patterns.as_deref()),
+			folder_books: self.folder_books,
 			updated_at: self.updated_at.to_string(),
 			series,
 			tags,
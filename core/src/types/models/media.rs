@@ -3,7 +3,9 @@ use serde::{Deserialize, Serialize};
 
 use crate::prisma;
 
-use super::{read_progress::ReadProgress, series::Series, tag::Tag};
+use super::{
+	metadata::MediaMetadata, read_progress::ReadProgress, series::Series, tag::Tag,
+};
 
 #[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
@@ -14,15 +16,23 @@ pub struct Media {
 	/// The description of the media. ex: "Spidey and his superspy sister, Teresa Parker, dig to uncover THE CHAMELEON CONSPIRACY."
 	pub description: Option<String>,
 	/// The size of the media in bytes.
-	pub size: i32,
+	pub size: i64,
 	/// The file extension of the media. ex: "cbz"
 	pub extension: String,
 	/// The number of pages in the media. ex: "69"
 	pub pages: i32,
 	// pub updated_at: DateTime<FixedOffset>,
 	pub updated_at: String,
+	/// The last modified time of the file on disk, as of the last scan.
+	pub modified_at: Option<String>,
 	/// The checksum hash of the file contents. Used to ensure only one instance of a file in the database.
 	pub checksum: Option<String>,
+	/// The checksum hash of the entire file contents, once the full checksum job has
+	/// computed it. Used to tell apart files whose `checksum` is the same.
+	pub full_checksum: Option<String>,
+	/// The perceptual hash of the cover of the media, once the cover hash job has computed
+	/// it. Used to find the same book in different files, e.g. a cbr and a re-encoded cbz.
+	pub cover_hash: Option<String>,
 	/// The path of the media. ex: "/home/user/media/comics/The Amazing Spider-Man (2018) #69.cbz"
 	pub path: String,
 	/// The ID of the series this media belongs to.
@@ -33,7 +43,51 @@ pub struct Media {
 	pub read_progresses: Option<Vec<ReadProgress>>,
 	/// The user assigned tags for the media. ex: ["comic", "spiderman"]. Will be `None` only if the relation is not loaded.
 	pub tags: Option<Vec<Tag>>,
-	// pub status: String,
+	/// The metadata of the media, derived from its ComicInfo.xml. Will be `None` if the media
+	/// has no metadata, or if the relation is not loaded.
+	pub metadata: Option<MediaMetadata>,
+	/// The status of the media. ex: "READY", "MISSING" or "ERROR"
+	pub status: String,
+	/// Why the media has its status. ex: "Page 12.jpg is corrupt: The image is empty"
+	pub status_reason: Option<String>,
+	/// When the verification job last read the media in full.
+	pub verified_at: Option<String>,
+}
+
+/// Media which share a checksum, but not their contents. Since the checksum only covers the
+/// start of each file, these would otherwise look like duplicates.
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ChecksumConflict {
+	/// The checksum the media share.
+	pub checksum: String,
+	/// The media, ordered by their full checksum.
+	pub media: Vec<Media>,
+}
+
+/// Media whose covers look alike, i.e. are likely the same book in different files. Every
+/// media is within the requested distance of the first media found for the cluster.
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SimilarMediaCluster {
+	/// The most bits the cover hash of any two media in the cluster differ in.
+	pub max_distance: u32,
+	/// The media, ordered by their size.
+	pub media: Vec<Media>,
+}
+
+/// The results of the last verification of each media, see `MediaVerificationJob`.
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MediaVerificationReport {
+	/// The number of media which were read in full without problems.
+	pub verified: u64,
+	/// The number of media which haven't been verified since they were added or changed.
+	/// Missing media aren't counted.
+	pub unverified: u64,
+	/// The media which could not be read, with the reason in their `status_reason`, ordered
+	/// by their path.
+	pub errored: Vec<Media>,
 }
 
 impl Into<Media> for prisma::media::Data {
@@ -67,6 +121,14 @@ impl Into<Media> for prisma::media::Data {
 			},
 		};
 
+		let metadata = match self.metadata() {
+			Ok(metadata) => metadata.map(|metadata| metadata.to_owned().into()),
+			Err(e) => {
+				log::trace!("Failed to load metadata for media: {}", e);
+				None
+			},
+		};
+
 		Media {
 			id: self.id,
 			name: self.name,
@@ -75,12 +137,19 @@ impl Into<Media> for prisma::media::Data {
 			extension: self.extension,
 			pages: self.pages,
 			updated_at: self.updated_at.to_string(),
+			modified_at: self.modified_at.map(|date| date.to_string()),
 			checksum: self.checksum,
+			full_checksum: self.full_checksum,
+			cover_hash: self.cover_hash,
 			path: self.path,
 			series_id: self.series_id.unwrap(),
 			series,
 			read_progresses,
 			tags,
+			metadata,
+			status: self.status,
+			status_reason: self.status_reason,
+			verified_at: self.verified_at.map(|date| date.to_string()),
 		}
 	}
 }
@@ -0,0 +1,364 @@
+use rocket::serde::json::serde_json;
+use rocket_okapi::JsonSchema;
+use serde::{Deserialize, Deserializer, Serialize};
+
+use crate::prisma;
+
+/// The metadata of a media file, derived from its ComicInfo.xml. Follows the Anansi
+/// ComicInfo schema (https://anansi-project.github.io/docs/comicinfo/schemas/v2.0), which is
+/// deserialized from the PascalCase element names. Numbers are parsed leniently, so a
+/// malformed value (ex: "2018?") is dropped instead of failing the whole document.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, JsonSchema)]
+#[serde(rename_all(serialize = "camelCase", deserialize = "PascalCase"))]
+pub struct MediaMetadata {
+	/// The title of the book. ex: "The Chameleon Conspiracy, Part One"
+	#[serde(default)]
+	pub title: Option<String>,
+	/// The name of the series the book belongs to. ex: "The Amazing Spider-Man"
+	#[serde(default)]
+	pub series: Option<String>,
+	/// The number of the book in the series. Not always a whole number. ex: "1.5"
+	#[serde(default)]
+	pub number: Option<String>,
+	/// The total number of books in the series. ex: 12
+	#[serde(default, deserialize_with = "lenient_int")]
+	pub count: Option<i32>,
+	/// The volume of the series. ex: 2018
+	#[serde(default, deserialize_with = "lenient_int")]
+	pub volume: Option<i32>,
+	/// The series the book also belongs to, e.g. a crossover event. ex: "Spider-Verse"
+	#[serde(default)]
+	pub alternate_series: Option<String>,
+	/// The number of the book in the alternate series. ex: "3"
+	#[serde(default)]
+	pub alternate_number: Option<String>,
+	/// The total number of books in the alternate series. ex: 6
+	#[serde(default, deserialize_with = "lenient_int")]
+	pub alternate_count: Option<i32>,
+	/// A description of the book.
+	#[serde(default)]
+	pub summary: Option<String>,
+	/// Free form notes about the book, often left by the tool that tagged it.
+	#[serde(default)]
+	pub notes: Option<String>,
+	/// The year the book was released. ex: 2018
+	#[serde(default, deserialize_with = "lenient_int")]
+	pub year: Option<i32>,
+	/// The month the book was released. ex: 12
+	#[serde(default, deserialize_with = "lenient_int")]
+	pub month: Option<i32>,
+	/// The day the book was released. ex: 25
+	#[serde(default, deserialize_with = "lenient_int")]
+	pub day: Option<i32>,
+	/// The writers of the book, comma separated. ex: "Nick Spencer"
+	#[serde(default)]
+	pub writer: Option<String>,
+	/// The pencillers of the book, comma separated.
+	#[serde(default)]
+	pub penciller: Option<String>,
+	/// The inkers of the book, comma separated.
+	#[serde(default)]
+	pub inker: Option<String>,
+	/// The colorists of the book, comma separated.
+	#[serde(default)]
+	pub colorist: Option<String>,
+	/// The letterers of the book, comma separated.
+	#[serde(default)]
+	pub letterer: Option<String>,
+	/// The cover artists of the book, comma separated.
+	#[serde(default)]
+	pub cover_artist: Option<String>,
+	/// The editors of the book, comma separated.
+	#[serde(default)]
+	pub editor: Option<String>,
+	/// The translators of the book, comma separated.
+	#[serde(default)]
+	pub translator: Option<String>,
+	/// The publisher of the book. ex: "Marvel"
+	#[serde(default)]
+	pub publisher: Option<String>,
+	/// The imprint of the publisher the book was released under. ex: "Vertigo"
+	#[serde(default)]
+	pub imprint: Option<String>,
+	/// The genres of the book, comma separated. ex: "Superhero, Action"
+	#[serde(default)]
+	pub genre: Option<String>,
+	/// The tags of the book, comma separated.
+	#[serde(default)]
+	pub tags: Option<String>,
+	/// A link to more information about the book, comma separated if there are several.
+	#[serde(default)]
+	pub web: Option<String>,
+	/// The language of the book, as an ISO code. ex: "en"
+	#[serde(default, rename(deserialize = "LanguageISO"))]
+	pub language_iso: Option<String>,
+	/// The format of the book. ex: "Trade Paperback"
+	#[serde(default)]
+	pub format: Option<String>,
+	/// Whether the book is in black and white. ex: "Yes", "No" or "Unknown"
+	#[serde(default)]
+	pub black_and_white: Option<String>,
+	/// Whether the book is a manga, and if so whether it reads right to left.
+	/// ex: "Yes", "No", "Unknown" or "YesAndRightToLeft"
+	#[serde(default)]
+	pub manga: Option<String>,
+	/// The characters in the book, comma separated.
+	#[serde(default)]
+	pub characters: Option<String>,
+	/// The teams in the book, comma separated.
+	#[serde(default)]
+	pub teams: Option<String>,
+	/// The locations in the book, comma separated.
+	#[serde(default)]
+	pub locations: Option<String>,
+	/// The main character or team of the book. ex: "Spider-Man"
+	#[serde(default)]
+	pub main_character_or_team: Option<String>,
+	/// Who scanned the book, and how.
+	#[serde(default)]
+	pub scan_information: Option<String>,
+	/// The story arcs the book is part of, comma separated. ex: "The Chameleon Conspiracy"
+	#[serde(default)]
+	pub story_arc: Option<String>,
+	/// The position of the book in each of the story arcs, comma separated. ex: "1"
+	#[serde(default)]
+	pub story_arc_number: Option<String>,
+	/// The groups the series belongs to, comma separated. ex: "Spider-Man"
+	#[serde(default)]
+	pub series_group: Option<String>,
+	/// The age rating of the book. ex: "Teen"
+	#[serde(default)]
+	pub age_rating: Option<String>,
+	/// The community rating of the book, from 0 to 5. ex: 4.5
+	#[serde(default, deserialize_with = "lenient_float")]
+	pub community_rating: Option<f64>,
+	/// A review of the book.
+	#[serde(default)]
+	pub review: Option<String>,
+	/// The Global Trade Item Number of the book, e.g. its ISBN.
+	#[serde(default, rename(deserialize = "GTIN"))]
+	pub gtin: Option<String>,
+	/// The number of pages in the book. ex: 22
+	#[serde(default, deserialize_with = "lenient_int")]
+	pub page_count: Option<i32>,
+	/// Information about the individual pages of the book, e.g. which is the cover.
+	#[serde(default, deserialize_with = "comic_info_pages")]
+	pub pages: Vec<MediaMetadataPage>,
+}
+
+/// A single page of a book, as described in the `Pages` element of its ComicInfo.xml.
+/// These are deserialized from the attributes of each `Page` element, or from the JSON they
+/// are stored as.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, JsonSchema)]
+#[serde(rename_all(serialize = "camelCase", deserialize = "PascalCase"))]
+pub struct MediaMetadataPage {
+	/// The index of the image in the book, starting at 0.
+	#[serde(default, alias = "image", deserialize_with = "lenient_int")]
+	pub image: Option<i32>,
+	/// The kind of page. ex: "FrontCover", "Story", "Advertisement" or "Deleted"
+	#[serde(default, rename(deserialize = "Type"), alias = "kind")]
+	pub kind: Option<String>,
+	/// Whether the image spans two pages.
+	#[serde(default, alias = "doublePage", deserialize_with = "lenient_bool")]
+	pub double_page: Option<bool>,
+	/// The size of the image in bytes.
+	#[serde(default, alias = "imageSize", deserialize_with = "lenient_int")]
+	pub image_size: Option<i32>,
+	/// The key used to look the page up in the tool that tagged the book.
+	#[serde(default, alias = "key")]
+	pub key: Option<String>,
+	/// The name of the bookmark on the page, if there is one.
+	#[serde(default, alias = "bookmark")]
+	pub bookmark: Option<String>,
+	/// The width of the image in pixels.
+	#[serde(default, alias = "imageWidth", deserialize_with = "lenient_int")]
+	pub image_width: Option<i32>,
+	/// The height of the image in pixels.
+	#[serde(default, alias = "imageHeight", deserialize_with = "lenient_int")]
+	pub image_height: Option<i32>,
+}
+
+#[derive(Deserialize)]
+struct ComicInfoPages {
+	#[serde(default, rename = "Page")]
+	page: Vec<MediaMetadataPage>,
+}
+
+fn comic_info_pages<'de, D>(deserializer: D) -> Result<Vec<MediaMetadataPage>, D::Error>
+where
+	D: Deserializer<'de>,
+{
+	Ok(ComicInfoPages::deserialize(deserializer)?.page)
+}
+
+/// A value which is expected to be a number or a boolean. ComicInfo.xml only has text, while
+/// the pages stored alongside the media are JSON, so both are accepted.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum LenientValue {
+	Bool(bool),
+	Int(i64),
+	Float(f64),
+	Text(String),
+}
+
+impl LenientValue {
+	fn as_f64(&self) -> Option<f64> {
+		match self {
+			LenientValue::Int(value) => Some(*value as f64),
+			LenientValue::Float(value) => Some(*value),
+			LenientValue::Text(value) => value.trim().parse::<f64>().ok(),
+			LenientValue::Bool(_) => None,
+		}
+	}
+}
+
+/// Parses whole numbers, and numbers with a fraction (ex: "2018.0"), which are truncated.
+/// Anything else is treated as missing.
+pub(crate) fn lenient_int<'de, D>(deserializer: D) -> Result<Option<i32>, D::Error>
+where
+	D: Deserializer<'de>,
+{
+	Ok(
+		Option::<LenientValue>::deserialize(deserializer)?.and_then(|value| {
+			match value {
+				LenientValue::Text(ref text) => text.trim().parse::<i32>().ok(),
+				_ => None,
+			}
+			.or_else(|| value.as_f64().map(|value| value as i32))
+		}),
+	)
+}
+
+fn lenient_float<'de, D>(deserializer: D) -> Result<Option<f64>, D::Error>
+where
+	D: Deserializer<'de>,
+{
+	Ok(Option::<LenientValue>::deserialize(deserializer)?
+		.and_then(|value| value.as_f64()))
+}
+
+fn lenient_bool<'de, D>(deserializer: D) -> Result<Option<bool>, D::Error>
+where
+	D: Deserializer<'de>,
+{
+	Ok(
+		Option::<LenientValue>::deserialize(deserializer)?.and_then(
+			|value| match value {
+				LenientValue::Bool(value) => Some(value),
+				LenientValue::Text(text) => match text.trim().to_lowercase().as_str() {
+					"true" | "yes" | "1" => Some(true),
+					"false" | "no" | "0" => Some(false),
+					_ => None,
+				},
+				_ => None,
+			},
+		),
+	)
+}
+
+impl Into<MediaMetadata> for prisma::media_metadata::Data {
+	fn into(self) -> MediaMetadata {
+		let pages = self
+			.pages
+			.as_deref()
+			.and_then(|pages| serde_json::from_str(pages).ok())
+			.unwrap_or_default();
+
+		MediaMetadata {
+			title: self.title,
+			series: self.series,
+			number: self.number,
+			count: self.count,
+			volume: self.volume,
+			alternate_series: self.alternate_series,
+			alternate_number: self.alternate_number,
+			alternate_count: self.alternate_count,
+			summary: self.summary,
+			notes: self.notes,
+			year: self.year,
+			month: self.month,
+			day: self.day,
+			writer: self.writer,
+			penciller: self.penciller,
+			inker: self.inker,
+			colorist: self.colorist,
+			letterer: self.letterer,
+			cover_artist: self.cover_artist,
+			editor: self.editor,
+			translator: self.translator,
+			publisher: self.publisher,
+			imprint: self.imprint,
+			genre: self.genre,
+			tags: self.tags,
+			web: self.web,
+			language_iso: self.language_iso,
+			format: self.format,
+			black_and_white: self.black_and_white,
+			manga: self.manga,
+			characters: self.characters,
+			teams: self.teams,
+			locations: self.locations,
+			main_character_or_team: self.main_character_or_team,
+			scan_information: self.scan_information,
+			story_arc: self.story_arc,
+			story_arc_number: self.story_arc_number,
+			series_group: self.series_group,
+			age_rating: self.age_rating,
+			community_rating: self.community_rating,
+			review: self.review,
+			gtin: self.gtin,
+			page_count: self.page_count,
+			pages,
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn parses_comic_info() {
+		let xml = r#"<?xml version="1.0" encoding="utf-8"?>
+<ComicInfo xmlns:xsd="http://www.w3.org/2001/XMLSchema" xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance">
+	<Title>The Chameleon Conspiracy, Part One</Title>
+	<Series>The Amazing Spider-Man</Series>
+	<Number>1.5</Number>
+	<Volume>2018</Volume>
+	<Year> 2018 </Year>
+	<Month>7?</Month>
+	<Writer>Nick Spencer</Writer>
+	<LanguageISO>en</LanguageISO>
+	<CommunityRating>4.5</CommunityRating>
+	<Manga>No</Manga>
+	<Pages>
+		<Page Image="0" Type="FrontCover" ImageWidth="1988" ImageHeight="3056" />
+		<Page Image="1" DoublePage="True" />
+	</Pages>
+</ComicInfo>"#;
+
+		let metadata: MediaMetadata = serde_xml_rs::from_str(xml).unwrap();
+
+		assert_eq!(
+			metadata.title.as_deref(),
+			Some("The Chameleon Conspiracy, Part One")
+		);
+		assert_eq!(metadata.number.as_deref(), Some("1.5"));
+		assert_eq!(metadata.volume, Some(2018));
+		assert_eq!(metadata.year, Some(2018));
+		assert_eq!(metadata.month, None);
+		assert_eq!(metadata.writer.as_deref(), Some("Nick Spencer"));
+		assert_eq!(metadata.language_iso.as_deref(), Some("en"));
+		assert_eq!(metadata.community_rating, Some(4.5));
+		assert_eq!(metadata.pages.len(), 2);
+		assert_eq!(metadata.pages[0].kind.as_deref(), Some("FrontCover"));
+		assert_eq!(metadata.pages[0].image_width, Some(1988));
+		assert_eq!(metadata.pages[1].double_page, Some(true));
+
+		let stored = serde_json::to_string(&metadata.pages).unwrap();
+		let pages: Vec<MediaMetadataPage> = serde_json::from_str(&stored).unwrap();
+
+		assert_eq!(pages, metadata.pages);
+	}
+}
@@ -1,9 +1,12 @@
+pub mod cache;
 pub mod epub;
 pub mod library;
 pub mod list_directory;
 pub mod log;
 pub mod media;
+pub mod metadata;
 pub mod read_progress;
+pub mod scan;
 pub mod series;
 pub mod tag;
 pub mod user;
@@ -11,9 +14,9 @@ pub mod user;
 use rocket_okapi::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-use crate::prisma;
+use crate::{fs::page_index::PageImage, prisma};
 
-use self::user::UserPreferences;
+use self::{metadata::MediaMetadata, user::UserPreferences};
 
 #[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
 pub struct AuthenticatedUser {
@@ -54,43 +57,11 @@ pub struct LoginRequest {
 	pub password: String,
 }
 
-// Derived from ComicInfo.xml
-#[derive(Debug, Serialize, Deserialize, PartialEq)]
-#[serde(rename_all = "camelCase")]
-pub struct MediaMetadata {
-	#[serde(rename = "Series")]
-	pub series: Option<String>,
-	#[serde(rename = "Number")]
-	pub number: Option<usize>,
-	#[serde(rename = "Web")]
-	pub web: Option<String>,
-	#[serde(rename = "Summary")]
-	pub summary: Option<String>,
-	#[serde(rename = "Publisher")]
-	pub publisher: Option<String>,
-	#[serde(rename = "Genre")]
-	pub genre: Option<String>,
-	#[serde(rename = "PageCount")]
-	pub page_count: Option<usize>,
-}
-
-impl MediaMetadata {
-	pub fn default() -> Self {
-		Self {
-			series: None,
-			number: None,
-			web: None,
-			summary: None,
-			publisher: None,
-			genre: None,
-			page_count: None,
-		}
-	}
-}
-
 pub struct ProcessedMediaFile {
 	pub checksum: Option<String>,
 	pub metadata: Option<MediaMetadata>,
-	// pub entries: Vec<String>,
 	pub pages: i32,
+	/// The images of the book in reading order. Empty for books which aren't made of images,
+	/// i.e. epubs and pdfs.
+	pub page_table: Vec<PageImage>,
 }
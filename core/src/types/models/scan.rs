@@ -0,0 +1,126 @@
+use rocket_okapi::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::prisma;
+
+/// What a library scan would do, without anything having been written to the database.
+#[derive(Debug, Clone, Default, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ScanReport {
+	/// The id of the library the report is for.
+	pub library_id: String,
+	/// When the report was generated. The library may have changed since.
+	pub generated_at: String,
+	/// The paths of the series that would be created.
+	pub series_to_create: Vec<String>,
+	/// The paths of existing series which no longer exist, and would be marked MISSING.
+	pub series_missing: Vec<String>,
+	/// The paths of the media that would be created.
+	pub media_to_create: Vec<String>,
+	/// The paths of existing media which changed on disk, and would be updated.
+	pub media_to_update: Vec<String>,
+	/// The paths of MISSING media which were found again, and would be marked READY.
+	pub media_to_restore: Vec<String>,
+	/// Existing media which were moved or renamed, and would be relinked to their new path.
+	pub media_to_relink: Vec<RelinkedFile>,
+	/// The paths of existing media which were not found, and would be marked MISSING.
+	pub media_missing: Vec<String>,
+	/// Files (and folders) the scanner would skip over, and why.
+	pub skipped: Vec<SkippedFile>,
+	/// Files the scanner would fail to process, and the error it would fail with.
+	pub failed: Vec<FailedFile>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct RelinkedFile {
+	/// The path the media is currently recorded at.
+	pub from: String,
+	/// The path the media was found at.
+	pub to: String,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SkippedFile {
+	pub path: String,
+	/// Why the file was skipped. ex: "Matched an ignore pattern"
+	pub reason: String,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct FailedFile {
+	pub path: String,
+	/// The error encountered while processing the file.
+	pub error: String,
+}
+
+/// A single run of the scanner over a library, or part of one.
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct LibraryScan {
+	pub id: String,
+	/// The id of the library that was scanned.
+	pub library_id: String,
+	/// What started the scan. ex: "MANUAL", "CREATE" or "UPDATE"
+	pub trigger: String,
+	/// The path that was scanned. This is the library path, unless only part of the library
+	/// was scanned.
+	pub path: String,
+	/// The status of the scan. ex: "RUNNING", "COMPLETED" or "FAILED"
+	pub status: String,
+	/// The reason the scan failed, if it did.
+	pub failure_reason: Option<String>,
+	/// The number of files the scan came across.
+	pub files_seen: i32,
+	/// The number of media created by the scan.
+	pub media_created: i32,
+	/// The number of media updated (changed, restored or relinked) by the scan.
+	pub media_updated: i32,
+	/// The number of media marked MISSING by the scan.
+	pub media_missing: i32,
+	/// The date in which the scan started. ex: "2022-04-20 04:20:69"
+	pub started_at: String,
+	/// The date in which the scan finished, if it has. ex: "2022-04-20 04:20:69"
+	pub completed_at: Option<String>,
+	/// The files which could not be scanned. Will be `None` only if the relation is not
+	/// loaded.
+	pub errors: Option<Vec<FailedFile>>,
+}
+
+impl Into<LibraryScan> for prisma::library_scan::Data {
+	fn into(self) -> LibraryScan {
+		let errors = match self.errors() {
+			Ok(errors) => Some(
+				errors
+					.iter()
+					.map(|error| FailedFile {
+						path: error.path.clone(),
+						error: error.message.clone(),
+					})
+					.collect(),
+			),
+			Err(e) => {
+				log::trace!("Failed to load errors for library scan: {}", e);
+				None
+			},
+		};
+
+		LibraryScan {
+			id: self.id,
+			library_id: self.library_id,
+			trigger: self.trigger,
+			path: self.path,
+			status: self.status,
+			failure_reason: self.failure_reason,
+			files_seen: self.files_seen,
+			media_created: self.media_created,
+			media_updated: self.media_updated,
+			media_missing: self.media_missing,
+			started_at: self.started_at.to_string(),
+			completed_at: self.completed_at.map(|date| date.to_string()),
+			errors,
+		}
+	}
+}
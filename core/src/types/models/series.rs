@@ -15,6 +15,14 @@ pub struct Series {
 	pub path: String,
 	/// The description of the series. ex: "The best series ever"
 	pub description: Option<String>,
+	/// The publisher of the series. ex: "Marvel"
+	pub publisher: Option<String>,
+	/// The year the series started. ex: 2018
+	pub year: Option<i32>,
+	/// The publication status of the series. ex: "Continuing" or "Ended"
+	pub publication_status: Option<String>,
+	/// The total number of issues in the series, which may be more than are in the library.
+	pub total_issues: Option<i32>,
 	/// The status of the series since last scan or access
 	pub status: String,
 	// pub updated_at: DateTime<FixedOffset>,
@@ -86,6 +94,10 @@ impl Into<Series> for prisma::series::Data {
 			name: self.name,
 			path: self.path,
 			description: self.description,
+			publisher: self.publisher,
+			year: self.year,
+			publication_status: self.publication_status,
+			total_issues: self.total_issues,
 			status: self.status,
 			updated_at: self.updated_at.to_string(),
 			library_id: self.library_id.unwrap(),
@@ -29,6 +29,23 @@ impl Into<prisma_client_rust::Direction> for Direction {
 	}
 }
 
+/// Which checksum media are compared by when looking for duplicates.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, JsonSchema, FromFormField)]
+pub enum ChecksumKind {
+	/// The checksum taken while scanning, which only covers the start of each file.
+	#[serde(rename = "quick")]
+	Quick,
+	/// The checksum of the entire file, computed by the full checksum job.
+	#[serde(rename = "full")]
+	Full,
+}
+
+impl Default for ChecksumKind {
+	fn default() -> Self {
+		ChecksumKind::Quick
+	}
+}
+
 /// Model used in media API to alter sorting/ordering of queried media
 #[derive(Debug, Deserialize, JsonSchema)]
 pub struct QueryOrder {
@@ -2,6 +2,7 @@ use rocket::tokio::{self, sync::mpsc::UnboundedReceiver};
 
 use crate::{
 	config::context::Context,
+	fs::scanner::watcher::LibraryWatcher,
 	job::jobs::Jobs,
 	types::event::{
 		ClientEvent, InternalEvent, InternalTask, TaskResponder, TaskResponse,
@@ -14,6 +15,7 @@ type TaskReceiver = UnboundedReceiver<TaskResponder<InternalTask>>;
 pub struct EventManager {
 	ctx: Context,
 	jobs: Jobs,
+	watcher: LibraryWatcher,
 }
 
 impl EventManager {
@@ -21,6 +23,29 @@ impl EventManager {
 		EventManager {
 			ctx,
 			jobs: Jobs::new(),
+			watcher: LibraryWatcher::new(),
+		}
+	}
+
+	/// Starts a watcher for every library in the database, so changes made while Stump is
+	/// running don't require a manual scan.
+	async fn watch_libraries(&mut self) {
+		let libraries = match self.ctx.get_db().library().find_many(vec![]).exec().await {
+			Ok(libraries) => libraries,
+			Err(e) => {
+				log::error!("Failed to load libraries to watch: {:?}", e);
+				return;
+			},
+		};
+
+		for library in libraries {
+			self.watch_library(library.id, library.path);
+		}
+	}
+
+	fn watch_library(&mut self, id: String, path: String) {
+		if let Err(e) = self.watcher.watch(self.ctx.get_ctx(), id.clone(), path) {
+			log::error!("Failed to watch library {}: {:?}", id, e);
 		}
 	}
 
@@ -41,6 +66,12 @@ impl EventManager {
 				self.jobs.dequeue(id);
 				// let _ = self.ctx.emit_client_event(format!("JobFailed: {:?}", err));
 			},
+			InternalEvent::WatchLibrary(id, path) => {
+				self.watch_library(id, path);
+			},
+			InternalEvent::UnwatchLibrary(id) => {
+				self.watcher.unwatch(&id);
+			},
 		}
 	}
 
@@ -62,6 +93,8 @@ impl EventManager {
 		let mut e_rx = e_rx;
 		let mut t_rx = t_rx;
 
+		self.watch_libraries().await;
+
 		loop {
 			tokio::select! {
 				Some(task) = t_rx.recv() => {